@@ -0,0 +1,19 @@
+//! Embed the commit being built as `CHIPPY_GIT_COMMIT`, left unset outside of a git checkout.
+
+use std::process::Command;
+
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=CHIPPY_GIT_COMMIT={}", commit.trim());
+    }
+
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs");
+}
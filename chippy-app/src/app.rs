@@ -1,31 +1,338 @@
-use std::{fs, sync::Arc};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    input::{InputHandler, KeyEvent},
-    window::{self, Window, WindowContainer},
+    audio,
+    browser::RomBrowser,
+    console,
+    display::{DisplayBackend, MiniquadDisplay},
+    favorites::Favorites,
+    input::{InputHandler, KeyEvent, KeyLayout, KeyRepeat, VirtualKeypad},
+    storage::{self, Storage},
+    watcher::RomWatcher,
+    window::{self, Theme, Window, WindowContainer},
+};
+use chippy_core::{
+    audio::Waveform,
+    breakpoint::RunOutcome,
+    render,
+    rom::RomWarning,
+    trace::{TraceFormat, TraceWriter},
+    ExecutingChip8, MemoryRegion, QuirkProfile, Quirks, ResetKind, DEFAULT_MAX_CYCLES,
 };
-use chippy_core::ExecutingChip8;
-use egui::{Image, TextureId, Vec2};
-use mq::{Texture, TextureParams};
+use egui::{util::id_type_map::SerializableAny, Color32, Image, Rect, Stroke, TextureId, Vec2};
+use log::Level;
+use mq::{KeyCode, Texture};
+use serde::{Deserialize, Serialize};
 
 use crate::debugger::DebuggerWindow;
 use {egui_miniquad as egui_mq, miniquad as mq};
 
+/// Regions offered when dumping or loading memory.
+const MEMORY_REGIONS: [(&str, MemoryRegion); 2] = [
+    ("Full memory", MemoryRegion::Full),
+    ("Program only (0x200+)", MemoryRegion::Program),
+];
+
+/// Unlit and lit pixels. The core only has the one plane until XO-CHIP is supported,
+/// so there are no second plane colors.
+const DEFAULT_PALETTE: [Color32; 2] = [Color32::BLACK, Color32::WHITE];
+
+/// Smallest scale the pixel grid is drawn at, below this the lines would hide the pixels.
+const GRID_MIN_SCALE: f32 = 4.0;
+
+/// Common instruction frequencies offered in the settings, in Hz.
+const FREQUENCY_PRESETS: [u32; 4] = [60, 500, 700, 1000];
+const MAX_FREQUENCY: u32 = 6000;
+
+/// Highest draws per frame offered by reduce flicker.
+const MAX_DRAWS_PER_FRAME: u32 = 16;
+
+/// How often the measured instruction rate is updated.
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Size of a pixel in saved screenshots.
+const SCREENSHOT_SCALE: usize = 8;
+
+const WINDOW_SIZE_KEY: &str = "main/window_size";
+const STARTUP_KEY: &str = "main/startup";
+const LAST_ROM_KEY: &str = "main/last_rom";
+const FRAME_CAP_KEY: &str = "main/frame_cap";
+const ROM_DIR_KEY: &str = "main/rom_dir";
+const KEYPAD_KEY: &str = "main/keypad";
+const KEY_LAYOUT_KEY: &str = "main/key_layout";
+const STATUS_LINE_KEY: &str = "main/status_line";
+const DEFAULT_WINDOW_SIZE: (f32, f32) = (640.0, 400.0);
+const MIN_WINDOW_SIZE: (f32, f32) = (320.0, 200.0);
+// Anything larger than an 8K display is a corrupted value.
+const MAX_WINDOW_SIZE: (f32, f32) = (7680.0, 4320.0);
+
+/// Minimum length of a frame while paused in low power mode.
+const PAUSED_FRAME: Duration = Duration::from_millis(50);
+
+/// Size a trace file is rotated at.
+const MAX_TRACE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// ROM picked and read on a worker thread, `None` if the dialog was cancelled.
+type PickedRom = Option<(RomOrigin, io::Result<Vec<u8>>)>;
+
+/// Where a ROM was read from.
+enum RomOrigin {
+    File(PathBuf),
+    #[cfg(feature = "url-rom")]
+    Url(String),
+}
+
 pub struct MainApp {
     chip8: Arc<ExecutingChip8>,
-    screen_texture: Option<Texture>,
+    display: Box<dyn DisplayBackend>,
+    // Pixels last presented to `display`.
+    screen_rgba: Vec<u8>,
+    // Colors of unlit and lit pixels, see `SettingsWindow::palette`.
+    palette: [Color32; 2],
+    // Rewrite `screen_rgba` even if the screen didn't change, e.g. after a palette change.
+    screen_stale: bool,
+    // Pending ROM being picked and read, polled in `update`.
+    // Locked only so the window stays `Sync`.
+    rom_loader: Option<Mutex<Receiver<PickedRom>>>,
+    // URL being typed for File > Open ROM from URL, `None` while the prompt is closed.
+    #[cfg(feature = "url-rom")]
+    url_prompt: Option<String>,
+    // Error shown in a dialog until dismissed.
+    error_dialog: Option<String>,
+    // Name of the loaded ROM, `None` when nothing is loaded.
+    rom_name: Option<String>,
+    // Problems found in the loaded ROM, shown until dismissed.
+    rom_warnings: Vec<RomWarning>,
+    // Watches the file the ROM was loaded from, `None` for the embedded ROM.
+    rom_watcher: Option<RomWatcher>,
+    // Reload the ROM when its file changes.
+    watch_rom: bool,
+    // Image drawn behind the letterboxed screen.
+    background_texture: Option<Texture>,
+    // Physical pixels per logical pixel, kept up to date by resize events.
+    pixels_per_point: f32,
     debugger_window: WindowContainer<DebuggerWindow>,
     settings_open: bool,
+    about_open: bool,
+    // Pause execution when the window loses focus.
+    pause_on_blur: bool,
+    // Resume execution paused by `pause_on_blur` when focus returns.
+    resume_on_focus: bool,
+    // Mute the beep when the window loses focus, see `SettingsWindow::mute_on_blur`.
+    mute_on_blur: bool,
+    // See `SettingsWindow::break_opens_debugger`.
+    break_opens_debugger: bool,
+    // ROM the settings in effect were picked for, see `SettingsWindow::for_rom`.
+    settings_rom: Option<u64>,
+    // Execution was paused by losing focus rather than by the user.
+    auto_paused: bool,
+    // Redraw at a low rate while execution is paused.
+    low_power_paused: bool,
+    frame_cap: FrameCap,
+    // When the last update finished, to pace frames for `frame_cap`.
+    last_frame: Instant,
+    // When the instruction rate was last measured and the cycle count then.
+    rate_sample: (Instant, u64),
+    // Instructions executed per second over the last `RATE_INTERVAL`.
+    measured_rate: f64,
+    // What is loaded on launch.
+    startup: Startup,
+    key_repeat: KeyRepeat,
+    key_layout: KeyLayout,
+    log_open: bool,
+    rom_browser: RomBrowser,
+    browser_open: bool,
+    // On-screen keypad for touch screens and the mouse.
+    virtual_keypad: VirtualKeypad,
+    keypad_open: bool,
+    // Line under the screen with the PC, the next instruction and the cycle count.
+    status_line: bool,
+    // Halted or idle state execution is in, shown in the status bar.
+    run_status: Option<RunOutcome>,
+    // Open the debugger when execution stops on an error.
+    debug_on_error: bool,
+    // Keys released by `KeyRepeat::Pulse`, pressed again on the next update.
+    repressed: Vec<KeyCode>,
 }
 
-#[derive(Clone)]
+/// What is loaded on launch.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum Startup {
+    /// The ROM last opened from a file, nothing if it can't be read.
+    LastRom,
+    /// The embedded instruction test ROM.
+    #[default]
+    TestRom,
+    /// Nothing, execution starts once a ROM is opened.
+    Empty,
+}
+
+/// Highest rate the window is redrawn at.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+enum FrameCap {
+    Fps30,
+    Fps60,
+    Fps120,
+    /// As often as miniquad draws.
+    #[default]
+    Uncapped,
+}
+
+/// How execution is paced.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum ExecutionMode {
+    /// Fixed instruction frequency on the executor thread.
+    Frequency,
+    /// Instructions per 60 Hz frame on the executor thread.
+    InstructionsPerFrame,
+    /// Instructions per rendered frame, driven by the render loop.
+    Vsync,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct SettingsWindow {
     frequency: u32,
+    mode: ExecutionMode,
+    ipf: u32,
+    max_cycles: u32,
+    cycle_costs: bool,
+    // Hold `DRW`s beyond `draws_per_frame` until the next frame.
+    reduce_flicker: bool,
+    draws_per_frame: u32,
+    warn_misaligned_pc: bool,
+    // Stop with an error on running into zeroed memory.
+    trap_empty_memory: bool,
+    // Sleep while the program only waits for input.
+    skip_idle: bool,
+    // Open the debugger at the stopped PC when execution hits a breakpoint.
+    break_opens_debugger: bool,
+    quirks: Quirks,
+    beep_frequency: f32,
+    waveform: Waveform,
+    volume: f32,
+    // Silence the beep while the window is in the background.
+    mute_on_blur: bool,
+    background_image: Option<PathBuf>,
+    show_grid: bool,
+    grid_color: Color32,
+    /// Colors of unlit and lit pixels.
+    palette: [Color32; 2],
 }
 
 impl Default for SettingsWindow {
     fn default() -> Self {
-        Self { frequency: 600 }
+        Self {
+            frequency: 600,
+            mode: ExecutionMode::Frequency,
+            ipf: 10,
+            max_cycles: DEFAULT_MAX_CYCLES as u32,
+            cycle_costs: false,
+            reduce_flicker: false,
+            draws_per_frame: 1,
+            warn_misaligned_pc: false,
+            trap_empty_memory: false,
+            skip_idle: true,
+            break_opens_debugger: true,
+            quirks: Quirks::default(),
+            beep_frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 1.0,
+            mute_on_blur: true,
+            background_image: None,
+            show_grid: false,
+            grid_color: Color32::from_rgba_unmultiplied(128, 128, 128, 40),
+            palette: DEFAULT_PALETTE,
+        }
+    }
+}
+
+impl Startup {
+    fn name(self) -> &'static str {
+        match self {
+            Startup::LastRom => "Open last ROM",
+            Startup::TestRom => "Open test ROM",
+            Startup::Empty => "Wait for a ROM",
+        }
+    }
+}
+
+impl FrameCap {
+    const ALL: [FrameCap; 4] = [
+        FrameCap::Fps30,
+        FrameCap::Fps60,
+        FrameCap::Fps120,
+        FrameCap::Uncapped,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            FrameCap::Fps30 => "30 FPS",
+            FrameCap::Fps60 => "60 FPS",
+            FrameCap::Fps120 => "120 FPS",
+            FrameCap::Uncapped => "Uncapped",
+        }
+    }
+
+    /// Shortest time between frames, `None` if uncapped.
+    fn interval(self) -> Option<Duration> {
+        match self {
+            FrameCap::Fps30 => Some(Duration::from_micros(33333)),
+            FrameCap::Fps60 => Some(Duration::from_micros(16666)),
+            FrameCap::Fps120 => Some(Duration::from_micros(8333)),
+            FrameCap::Uncapped => None,
+        }
+    }
+}
+
+impl SettingsWindow {
+    fn id() -> egui::Id {
+        egui::Id::new("settings_window")
+    }
+
+    /// Where the settings saved for a single ROM are kept.
+    fn rom_id(rom_hash: u64) -> egui::Id {
+        egui::Id::new(("rom_settings", rom_hash))
+    }
+
+    /// Settings saved for a ROM, `None` if it uses the global settings.
+    fn saved_for_rom(ctx: &egui::Context, rom_hash: u64) -> Option<Self> {
+        ctx.data().get_persisted(Self::rom_id(rom_hash))
+    }
+
+    /// Settings in effect for a ROM, its own if saved and the global settings otherwise.
+    fn for_rom(ctx: &egui::Context, rom_hash: u64) -> Self {
+        Self::saved_for_rom(ctx, rom_hash).unwrap_or_else(|| Self::load_state(ctx, Self::id()))
+    }
+
+    /// Push the settings into the emulator.
+    fn apply(&self, chip8: &ExecutingChip8) {
+        match self.mode {
+            ExecutionMode::Frequency => chip8.set_frequency(self.frequency as i32),
+            _ => chip8.set_ipf(self.ipf as i32),
+        }
+
+        chip8.set_vsync(self.mode == ExecutionMode::Vsync);
+        chip8.set_max_cycles(self.max_cycles as i32);
+        chip8.set_skip_idle(self.skip_idle);
+
+        let mut chip8 = chip8.write().unwrap();
+        chip8.cycle_costs = self.cycle_costs;
+        chip8.max_draws_per_frame = self.reduce_flicker.then_some(self.draws_per_frame);
+        chip8.warn_misaligned_pc = self.warn_misaligned_pc;
+        chip8.trap_empty_memory = self.trap_empty_memory;
+        chip8.set_quirks(self.quirks);
+        chip8.beeper.frequency = self.beep_frequency;
+        chip8.beeper.waveform = self.waveform;
+        chip8.beeper.volume = self.volume;
     }
 }
 
@@ -34,10 +341,10 @@ trait EguiState<T> {
     fn save_state(self, ctx: &egui::Context, id: egui::Id);
 }
 
-/// Anything that can be cloned can be stored as state.
+/// Anything that can be cloned and serialized can be stored as state.
 impl<T> EguiState<T> for T
 where
-    T: Send + Sync + Clone + Default + 'static,
+    T: SerializableAny + Default,
 {
     fn load_state(ctx: &egui::Context, id: egui::Id) -> Self {
         ctx.data().get_persisted(id).unwrap_or_default()
@@ -51,79 +358,560 @@ where
 impl MainApp {
     pub fn new() -> Self {
         let chip8 = Arc::new(ExecutingChip8::new());
+        audio::start(chip8.clone());
 
-        chip8
-            .write()
-            .unwrap()
-            .load_rom(include_bytes!("Instruction-test.ch8").to_vec());
+        if let Some((path, format)) = trace_from_args() {
+            match TraceWriter::new(&path, MAX_TRACE_BYTES) {
+                Ok(mut writer) => {
+                    writer.set_format(format);
+                    chip8
+                        .write()
+                        .unwrap()
+                        .set_trace_hook(Some(writer.into_hook()))
+                }
+                Err(err) => log::warn!("Unable to create trace {}: {}", path.display(), err),
+            }
+        }
+
+        #[cfg(feature = "remote-debug")]
+        if let Some(port) = crate::remote::port_from_args() {
+            crate::remote::start(chip8.clone(), port);
+        }
 
-        chip8.set_running(true);
+        let startup = storage::storage().get(STARTUP_KEY).unwrap_or_default();
 
         let chip8_clone = chip8.clone();
-        Self {
+        let mut app = Self {
             chip8,
-            screen_texture: None,
+            display: Box::new(MiniquadDisplay::default()),
+            screen_rgba: Vec::new(),
+            palette: DEFAULT_PALETTE,
+            screen_stale: true,
+            rom_loader: None,
+            #[cfg(feature = "url-rom")]
+            url_prompt: None,
+            error_dialog: None,
+            rom_name: None,
+            rom_warnings: Vec::new(),
+            rom_watcher: None,
+            watch_rom: false,
+            background_texture: None,
+            pixels_per_point: 1.0,
             debugger_window: WindowContainer::new(DebuggerWindow::new(chip8_clone)),
             settings_open: false,
+            about_open: false,
+            pause_on_blur: true,
+            resume_on_focus: true,
+            mute_on_blur: true,
+            break_opens_debugger: true,
+            settings_rom: None,
+            auto_paused: false,
+            low_power_paused: false,
+            frame_cap: storage::storage().get(FRAME_CAP_KEY).unwrap_or_default(),
+            last_frame: Instant::now(),
+            rate_sample: (Instant::now(), 0),
+            measured_rate: 0.0,
+            startup,
+            key_repeat: KeyRepeat::default(),
+            key_layout: storage::storage().get(KEY_LAYOUT_KEY).unwrap_or_default(),
+            log_open: false,
+            rom_browser: storage::storage()
+                .get(ROM_DIR_KEY)
+                .map(RomBrowser::new)
+                .unwrap_or_default(),
+            browser_open: false,
+            virtual_keypad: VirtualKeypad::default(),
+            keypad_open: storage::storage().get(KEYPAD_KEY).unwrap_or_default(),
+            status_line: storage::storage().get(STATUS_LINE_KEY).unwrap_or_default(),
+            run_status: None,
+            debug_on_error: false,
+            repressed: Vec::new(),
+        };
+
+        match startup {
+            Startup::LastRom => {
+                let path = storage::storage().get::<PathBuf>(LAST_ROM_KEY);
+                match path.map(|path| (fs::read(&path), path)) {
+                    Some((Ok(rom), path)) => app.load_rom_file(path, rom),
+                    Some((Err(err), path)) => {
+                        log::warn!("Unable to read last ROM {}: {}", path.display(), err)
+                    }
+                    None => {}
+                }
+            }
+            Startup::TestRom => {
+                app.chip8
+                    .load_rom(include_bytes!("Instruction-test.ch8").to_vec());
+                app.rom_name = Some("Instruction test".into());
+                app.chip8.set_running(true);
+            }
+            Startup::Empty => {}
+        }
+
+        app
+    }
+
+    /// Load a ROM read from `path` and remember it for the next launch.
+    fn load_rom_file(&mut self, path: PathBuf, rom: Vec<u8>) {
+        self.rom_warnings = self.chip8.read().unwrap().validate_rom(&rom);
+        self.chip8.load_rom(rom);
+
+        // Nothing was running before the first ROM.
+        if self.rom_name.is_none() {
+            self.chip8.set_running(true);
+        }
+
+        self.rom_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        storage::storage().set(LAST_ROM_KEY, &path);
+        self.rom_watcher = Some(RomWatcher::new(path));
+    }
+
+    /// Switch to a settings bundle, e.g. the one saved for a newly loaded ROM.
+    fn use_settings(&mut self, ctx: &mut mq::Context, settings: &SettingsWindow) {
+        settings.apply(&self.chip8);
+        self.palette = settings.palette;
+        self.screen_stale = true;
+        self.mute_on_blur = settings.mute_on_blur;
+        self.break_opens_debugger = settings.break_opens_debugger;
+        replace_texture(
+            ctx,
+            &mut self.background_texture,
+            settings.background_image.as_deref(),
+        );
+    }
+
+    /// Side panel listing the ROMs in a directory, loading one when clicked.
+    fn draw_rom_browser(&mut self, egui_ctx: &egui::Context) {
+        let mut picked = None;
+        let mut favorites = Favorites::load_state(egui_ctx, Favorites::id());
+        let mut favorites_changed = false;
+
+        egui::SidePanel::left("rom_browser")
+            .resizable(true)
+            .show(egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Choose folder").clicked() {
+                        let mut dialog = rfd::FileDialog::new();
+                        if let Some(dir) = self.rom_browser.dir() {
+                            dialog = dialog.set_directory(dir);
+                        }
+
+                        if let Some(dir) = dialog.pick_folder() {
+                            storage::storage().set(ROM_DIR_KEY, &dir);
+                            self.rom_browser = RomBrowser::new(dir);
+                        }
+                    }
+
+                    if ui
+                        .add_enabled(self.rom_browser.dir().is_some(), egui::Button::new("⟳"))
+                        .on_hover_text("Refresh")
+                        .clicked()
+                    {
+                        self.rom_browser.rescan();
+                    }
+                });
+
+                match self.rom_browser.dir() {
+                    Some(dir) => ui.label(dir.display().to_string()),
+                    None => ui.label("No folder chosen"),
+                };
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for entry in self.rom_browser.entries() {
+                        let variant = entry
+                            .variant
+                            .map_or("unreadable".to_string(), |variant| variant.to_string());
+
+                        ui.horizontal(|ui| {
+                            if let Some(hash) = entry.hash {
+                                let favorite = favorites.contains(hash);
+                                if ui
+                                    .small_button(if favorite { "★" } else { "☆" })
+                                    .on_hover_text("Favorite")
+                                    .clicked()
+                                {
+                                    if favorite {
+                                        favorites.remove(hash);
+                                    } else {
+                                        favorites.add(&entry.path, hash);
+                                    }
+                                    favorites_changed = true;
+                                }
+                            }
+
+                            if ui.selectable_label(false, &entry.name).clicked() {
+                                picked = Some(entry.path.clone());
+                            }
+
+                            ui.weak(format!("{} bytes, {}", entry.size, variant));
+                        });
+                    }
+
+                    if self.rom_browser.entries().is_empty() && self.rom_browser.dir().is_some() {
+                        ui.label("No ROMs found");
+                    }
+                });
+            });
+
+        if favorites_changed {
+            favorites.save_state(egui_ctx, Favorites::id());
+        }
+
+        if let Some(path) = picked {
+            self.load_rom_path(path);
+        }
+    }
+
+    /// Read and load a ROM file on the UI thread, for files known to exist.
+    fn load_rom_path(&mut self, path: PathBuf) {
+        match fs::read(&path) {
+            Ok(rom) => self.load_rom_file(path, rom),
+            Err(err) => log::warn!("Unable to read ROM {}: {}", path.display(), err),
+        }
+    }
+
+    /// Entries to star the loaded ROM and open favorite ROMs.
+    fn favorites_menu(&mut self, ui: &mut egui::Ui) {
+        let mut favorites = Favorites::load_state(ui.ctx(), Favorites::id());
+        let mut changed = false;
+        let mut picked = None;
+
+        // Only ROMs opened from a file can be found again.
+        if let Some(path) = self
+            .rom_watcher
+            .as_ref()
+            .map(|watcher| watcher.path().to_owned())
+        {
+            let hash = self.chip8.read().unwrap().rom_hash();
+
+            // A favorite loaded from elsewhere was moved, follow it.
+            let moved = favorites
+                .entries()
+                .iter()
+                .any(|favorite| favorite.hash == hash && favorite.path != path);
+            if moved {
+                favorites.add(&path, hash);
+                changed = true;
+            }
+
+            if favorites.contains(hash) {
+                if ui.button("Remove from favorites").clicked() {
+                    favorites.remove(hash);
+                    changed = true;
+                    ui.close_menu();
+                }
+            } else if ui.button("Add to favorites").clicked() {
+                favorites.add(&path, hash);
+                changed = true;
+                ui.close_menu();
+            }
+        }
+
+        ui.menu_button("Favorites", |ui| {
+            if favorites.entries().is_empty() {
+                ui.label("No favorites");
+            }
+
+            let mut removed = None;
+            for favorite in favorites.entries() {
+                ui.horizontal(|ui| {
+                    let path = favorite.path.display().to_string();
+                    if ui
+                        .add_enabled(favorite.available(), egui::Button::new(&favorite.name))
+                        .on_hover_text(&path)
+                        .on_disabled_hover_text(format!("Unavailable, {} is missing", path))
+                        .clicked()
+                    {
+                        picked = Some(favorite.path.clone());
+                        ui.close_menu();
+                    }
+
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        removed = Some(favorite.hash);
+                    }
+                });
+            }
+
+            if let Some(hash) = removed {
+                favorites.remove(hash);
+                changed = true;
+            }
+        });
+
+        if changed {
+            favorites.save_state(ui.ctx(), Favorites::id());
+        }
+
+        if let Some(path) = picked {
+            self.load_rom_path(path);
+        }
+    }
+
+    /// Pick and read a ROM on a worker thread so the UI doesn't stall.
+    fn open_rom(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let picked = rfd::FileDialog::new().pick_file().map(|path| {
+                let rom = fs::read(&path);
+                (RomOrigin::File(path), rom)
+            });
+
+            let _ = sender.send(picked);
+        });
+
+        self.rom_loader = Some(Mutex::new(receiver));
+    }
+
+    /// Download a ROM on a worker thread, loaded like one from `open_rom`.
+    #[cfg(feature = "url-rom")]
+    fn open_rom_url(&mut self, url: String) {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let rom = crate::download::fetch_rom(&url);
+            let _ = sender.send(Some((RomOrigin::Url(url), rom)));
+        });
+
+        self.rom_loader = Some(Mutex::new(receiver));
+    }
+
+    /// Load the ROM from `open_rom` once it's ready.
+    fn poll_rom_loader(&mut self) {
+        let picked = match self
+            .rom_loader
+            .as_ref()
+            .map(|receiver| receiver.lock().unwrap().try_recv())
+        {
+            Some(Ok(picked)) => picked,
+            Some(Err(TryRecvError::Empty)) | None => return,
+            Some(Err(TryRecvError::Disconnected)) => None,
+        };
+
+        self.rom_loader = None;
+
+        match picked {
+            Some((RomOrigin::File(path), Ok(rom))) => self.load_rom_file(path, rom),
+            Some((RomOrigin::File(path), Err(err))) => {
+                log::warn!("Unable to read ROM {}: {}", path.display(), err)
+            }
+            #[cfg(feature = "url-rom")]
+            Some((RomOrigin::Url(url), Ok(rom))) => {
+                self.rom_warnings = self.chip8.read().unwrap().validate_rom(&rom);
+                self.chip8.load_rom(rom);
+
+                if self.rom_name.is_none() {
+                    self.chip8.set_running(true);
+                }
+
+                // Downloads aren't remembered as the last ROM, and can't be watched.
+                self.rom_name = Some(crate::download::rom_name(&url));
+                self.rom_watcher = None;
+            }
+            #[cfg(feature = "url-rom")]
+            Some((RomOrigin::Url(url), Err(err))) => {
+                let message = format!("Unable to download {}: {}", url, err);
+                log::warn!("{}", message);
+                self.error_dialog = Some(message);
+            }
+            None => {}
+        }
+    }
+
+    /// Run a Rhai script alongside the ROM, see `chippy_core::script`.
+    #[cfg(feature = "scripting")]
+    fn load_script(&self, source: &str) {
+        match chippy_core::script::Script::new(source) {
+            Ok(script) => self.chip8.write().unwrap().set_script(Some(script)),
+            Err(err) => log::warn!("Unable to load script: {}", err),
         }
     }
 
-    fn screen_rgba(&self) -> [u8; 64 * 32 * 4] {
-        let binding = self.chip8.read().unwrap();
-        let screen_flattened = binding.screen.flatten();
-        let mut buffer = [0; 64 * 32 * 4];
+    /// Update `measured_rate` once every `RATE_INTERVAL`.
+    fn measure_rate(&mut self) {
+        let (since, count) = self.rate_sample;
+        let elapsed = since.elapsed();
+        if elapsed < RATE_INTERVAL {
+            return;
+        }
+
+        let cycle_count = self.chip8.read().unwrap().cycle_count();
+        // Loading a ROM doesn't reset the count, but a new machine would.
+        self.measured_rate = cycle_count.saturating_sub(count) as f64 / elapsed.as_secs_f64();
+        self.rate_sample = (Instant::now(), cycle_count);
+    }
+
+    /// React to execution halting, going idle or failing.
+    fn poll_run_events(&mut self) {
+        for event in self.chip8.drain_events() {
+            match event {
+                RunOutcome::Error(err) => {
+                    log::error!("{}", err);
+
+                    if self.debug_on_error && !self.debugger_window.is_open() {
+                        let _ = self.debugger_window.open();
+                    }
+                }
+                RunOutcome::Breakpoint(pc) if self.break_opens_debugger => {
+                    self.debugger_window.write().show_pc(pc);
+                    if !self.debugger_window.is_open() {
+                        let _ = self.debugger_window.open();
+                    }
+                }
+                RunOutcome::Breakpoint(_) => {}
+                RunOutcome::Halted | RunOutcome::Idle => self.run_status = Some(event),
+                RunOutcome::CycleLimit => {}
+            }
+        }
+
+        // Events are only sent when a state is entered, so check whether it was left,
+        // e.g. by a key press or loading another ROM.
+        if let Some(status) = self.run_status {
+            let chip8 = self.chip8.read().unwrap();
+            let current = match status {
+                RunOutcome::Halted => chip8.is_halted(),
+                RunOutcome::Idle => chip8.is_idle(),
+                _ => false,
+            };
 
-        // TODO: Make colors configurable for both foreground and background.
-        for (i, el) in screen_flattened.iter().enumerate() {
-            if *el {
-                buffer[i * 4] = 255;
-                buffer[i * 4 + 1] = 255;
-                buffer[i * 4 + 2] = 255;
-            } else {
-                buffer[i * 4] = 0;
-                buffer[i * 4 + 1] = 0;
-                buffer[i * 4 + 2] = 0;
+            if !current {
+                drop(chip8);
+                self.run_status = None;
             }
+        }
+    }
+
+    /// Reload and reset when the watched ROM file changes.
+    fn poll_rom_watcher(&mut self) {
+        if !self.watch_rom {
+            return;
+        }
+
+        if let Some(rom) = self.rom_watcher.as_mut().and_then(RomWatcher::poll) {
+            self.rom_warnings = self.chip8.read().unwrap().validate_rom(&rom);
+            self.chip8.load_rom(rom);
+        }
+    }
+
+    /// Colors of lit and unlit pixels, fully opaque.
+    fn screen_colors(&self) -> ([u8; 4], [u8; 4]) {
+        (opaque(self.palette[1]), opaque(self.palette[0]))
+    }
+
+    /// Save the screen as a PNG, each pixel drawn `SCREENSHOT_SCALE` times larger.
+    fn save_screenshot(&self, path: &Path) {
+        let (fg, bg) = self.screen_colors();
+        let (rgba, width, height) =
+            self.chip8
+                .read()
+                .unwrap()
+                .render_to_rgba(fg, bg, SCREENSHOT_SCALE);
+
+        if let Err(err) = image::save_buffer(path, &rgba, width, height, image::ColorType::Rgba8) {
+            log::error!("Unable to save screenshot {}: {}", path.display(), err);
+        }
+    }
 
-            // Alpha always 100%
-            buffer[i * 4 + 3] = 255;
+    /// Rewrite `screen_rgba` if the screen changed since it was last written.
+    /// Returns whether it was rewritten.
+    fn update_screen_rgba(&mut self) -> bool {
+        let mut binding = self.chip8.write().unwrap();
+        let stale = std::mem::take(&mut self.screen_stale);
+        if !binding.take_screen_dirty() && !stale {
+            return false;
         }
 
-        buffer
+        let (fg, bg) = self.screen_colors();
+        (self.screen_rgba, _, _) = binding.render_to_rgba(fg, bg, 1);
+
+        true
+    }
+
+    fn present_screen(&mut self) {
+        let (width, height) = self.chip8.read().unwrap().screen_dimensions();
+        self.display
+            .present(&self.screen_rgba, width as u32, height as u32);
     }
 }
 
 impl Window for MainApp {
     fn config(&self) -> mq::conf::Conf {
+        // Miniquad can't place windows, so only the size is restored.
+        let (width, height) = storage::storage()
+            .get::<(f32, f32)>(WINDOW_SIZE_KEY)
+            .filter(|(width, height)| width.is_finite() && height.is_finite())
+            .map(|(width, height)| {
+                (
+                    width.clamp(MIN_WINDOW_SIZE.0, MAX_WINDOW_SIZE.0),
+                    height.clamp(MIN_WINDOW_SIZE.1, MAX_WINDOW_SIZE.1),
+                )
+            })
+            .unwrap_or(DEFAULT_WINDOW_SIZE);
+
         mq::conf::Conf {
             high_dpi: true,
             icon: None,
-            window_width: 640,
-            window_height: 400,
-            window_title: window::window_title("Emulator"),
+            window_width: width as i32,
+            window_height: height as i32,
+            window_title: window::window_title(self.rom_name.as_deref().unwrap_or("Emulator")),
             window_resizable: true,
             ..Default::default()
         }
     }
 
-    fn on_open(&mut self, ctx: &mut mq::Context, _egui_ctx: &mut egui_mq::EguiMq) {
-        self.screen_texture = Some(Texture::from_data_and_format(
-            ctx,
-            vec![0; 64 * 32 * 4].as_slice(),
-            TextureParams {
-                format: mq::TextureFormat::RGBA8,
-                wrap: mq::TextureWrap::Clamp,
-                filter: mq::FilterMode::Nearest,
-                width: 64,
-                height: 32,
-            },
-        ));
+    fn on_open(&mut self, ctx: &mut mq::Context, egui_ctx: &mut egui_mq::EguiMq) {
+        self.pixels_per_point = ctx.dpi_scale();
+
+        let rom_hash = self.chip8.read().unwrap().rom_hash();
+        let settings = SettingsWindow::for_rom(egui_ctx.egui_ctx(), rom_hash);
+        self.use_settings(ctx, &settings);
+        self.settings_rom = Some(rom_hash);
+
+        self.update_screen_rgba();
+        self.present_screen();
     }
 
     fn update(&mut self, mq_ctx: &mut mq::Context) {
-        self.screen_texture
-            .unwrap()
-            .update(mq_ctx, &self.screen_rgba());
+        self.poll_rom_loader();
+        self.poll_rom_watcher();
+
+        self.poll_run_events();
+        self.measure_rate();
+
+        for keycode in self.repressed.drain(..) {
+            self.chip8.write().unwrap().keypad.key_event(
+                self.key_layout,
+                KeyEvent::KeyDown,
+                keycode,
+            );
+        }
+
+        // Only executes when in vsync mode.
+        self.chip8.run_frame();
+
+        // Static screens don't need an upload every frame.
+        let screen_changed = self.update_screen_rgba();
+        if screen_changed {
+            self.present_screen();
+        }
+
+        // Miniquad always redraws, so slow the loop down instead.
+        // Changes such as single steps are still drawn on the next frame.
+        let frame = if self.low_power_paused && !self.chip8.is_running() && !screen_changed {
+            Some(PAUSED_FRAME)
+        } else {
+            self.frame_cap.interval()
+        };
+
+        if let Some(frame) = frame {
+            thread::sleep(frame.saturating_sub(self.last_frame.elapsed()));
+        }
+
+        self.last_frame = Instant::now();
     }
 
     fn draw(&mut self, mq_ctx: &mut mq::Context, egui_ctx: &mut egui_mq::EguiMq) {
@@ -131,22 +919,168 @@ impl Window for MainApp {
         mq_ctx.begin_default_pass(mq::PassAction::clear_color(0.0, 0.0, 0.0, 1.0));
         mq_ctx.end_render_pass();
 
-        egui_ctx.run(mq_ctx, |_mq_ctx, egui_ctx| {
+        egui_ctx.run(mq_ctx, |mq_ctx, egui_ctx| {
+            // Whichever way a ROM was loaded, its own settings replace the previous ROM's.
+            let rom_hash = self.chip8.read().unwrap().rom_hash();
+            if self.settings_rom != Some(rom_hash) {
+                self.use_settings(mq_ctx, &SettingsWindow::for_rom(egui_ctx, rom_hash));
+                self.settings_rom = Some(rom_hash);
+            }
+
             egui::TopBottomPanel::top("my_panel").show(&egui_ctx, |ui| {
                 egui::menu::bar(ui, |ui| {
                     ui.menu_button("File", |ui| {
-                        if ui.button("Open ROM").clicked() {
-                            if let Some(path) = rfd::FileDialog::new().pick_file() {
-                                self.chip8
-                                    .write()
-                                    .unwrap()
-                                    .load_rom(fs::read(path).expect("Unable to read ROM"));
+                        if ui
+                            .add_enabled(self.rom_loader.is_none(), egui::Button::new("Open ROM"))
+                            .clicked()
+                        {
+                            self.open_rom();
+                            ui.close_menu();
+                        }
+
+                        #[cfg(feature = "url-rom")]
+                        if ui
+                            .add_enabled(
+                                self.rom_loader.is_none(),
+                                egui::Button::new("Open ROM from URL"),
+                            )
+                            .clicked()
+                        {
+                            self.url_prompt.get_or_insert_with(String::new);
+                            ui.close_menu();
+                        }
+
+                        ui.separator();
+                        self.favorites_menu(ui);
+
+                        #[cfg(feature = "scripting")]
+                        {
+                            ui.separator();
+
+                            if ui.button("Load script").clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Rhai script", &["rhai"])
+                                    .pick_file()
+                                {
+                                    match fs::read_to_string(&path) {
+                                        Ok(source) => self.load_script(&source),
+                                        Err(err) => log::warn!(
+                                            "Unable to read script {}: {}",
+                                            path.display(),
+                                            err
+                                        ),
+                                    }
+                                }
+                                ui.close_menu();
+                            }
+
+                            if ui.button("Unload script").clicked() {
+                                self.chip8.write().unwrap().set_script(None);
+                                ui.close_menu();
+                            }
+                        }
+
+                        ui.separator();
+
+                        if ui.button("Save screenshot").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("PNG image", &["png"])
+                                .set_file_name("screenshot.png")
+                                .save_file()
+                            {
+                                self.save_screenshot(&path);
+                            }
+                            ui.close_menu();
+                        }
+
+                        ui.menu_button("Dump memory", |ui| {
+                            for (name, region) in MEMORY_REGIONS {
+                                if ui.button(name).clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .set_file_name("memory.bin")
+                                        .save_file()
+                                    {
+                                        let dump = self.chip8.read().unwrap().dump_memory(region);
+                                        if let Err(err) = fs::write(&path, dump) {
+                                            log::error!(
+                                                "Unable to write memory dump {}: {}",
+                                                path.display(),
+                                                err
+                                            );
+                                        }
+                                    }
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+
+                        ui.menu_button("Load memory dump", |ui| {
+                            for (name, region) in MEMORY_REGIONS {
+                                if ui.button(name).clicked() {
+                                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                                        match fs::read(&path) {
+                                            Ok(dump) => self
+                                                .chip8
+                                                .write()
+                                                .unwrap()
+                                                .load_memory_dump(region, &dump),
+                                            Err(err) => log::warn!(
+                                                "Unable to read memory dump {}: {}",
+                                                path.display(),
+                                                err
+                                            ),
+                                        }
+                                    }
+                                    ui.close_menu();
+                                }
                             }
+                        });
+                    });
+
+                    ui.menu_button("View", |ui| {
+                        let mut theme = window::theme();
+                        let changed = ui.radio_value(&mut theme, Theme::Dark, "Dark").changed()
+                            | ui.radio_value(&mut theme, Theme::Light, "Light").changed();
+
+                        if changed {
+                            window::set_theme(theme);
                             ui.close_menu();
                         }
+
+                        ui.separator();
+                        ui.checkbox(&mut self.log_open, "Log");
+                        ui.checkbox(&mut self.browser_open, "ROM browser");
+                        if ui.checkbox(&mut self.keypad_open, "Keypad").changed() {
+                            storage::storage().set(KEYPAD_KEY, &self.keypad_open);
+
+                            // Keys held on the keypad can't be released once it's gone.
+                            self.virtual_keypad.reset();
+                            self.chip8.write().unwrap().keypad.reset();
+                        }
+
+                        if ui.checkbox(&mut self.status_line, "Status line").changed() {
+                            storage::storage().set(STATUS_LINE_KEY, &self.status_line);
+                        }
                     });
 
                     ui.menu_button("System", |ui| {
+                        if ui
+                            .add_enabled(self.rom_name.is_some(), egui::Button::new("Reset"))
+                            .clicked()
+                        {
+                            self.chip8.reset(ResetKind::Cold);
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .add_enabled(self.rom_name.is_some(), egui::Button::new("Warm reset"))
+                            .on_hover_text("Restart keeping registers and memory")
+                            .clicked()
+                        {
+                            self.chip8.reset(ResetKind::Warm);
+                            ui.close_menu();
+                        }
+
                         if ui
                             .add_enabled(
                                 !self.debugger_window.is_open(),
@@ -166,34 +1100,706 @@ impl Window for MainApp {
                             ui.close_menu();
                         }
                     });
+
+                    ui.menu_button("Help", |ui| {
+                        if ui
+                            .add_enabled(!self.about_open, egui::Button::new("About"))
+                            .clicked()
+                        {
+                            self.about_open = true;
+                            ui.close_menu();
+                        }
+                    });
+
+                    // Miniquad can't retitle an open window, so the title only
+                    // has the ROM it was opened with. Show the current one here.
+                    if self.rom_loader.is_some() {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.spinner();
+                            ui.label("Loading…");
+                        });
+                    } else if let Some(name) = &self.rom_name {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(name);
+                        });
+                    }
                 });
             });
 
+            if self.status_line {
+                egui::TopBottomPanel::bottom("status_line").show(&egui_ctx, |ui| {
+                    let chip8 = self.chip8.read().unwrap();
+                    let (word, next) = chip8.peek_next_opcode();
+
+                    ui.horizontal(|ui| {
+                        ui.monospace(format!("PC {:04X}", chip8.pc));
+                        ui.separator();
+                        ui.monospace(format!("{:04X}  {}", word, next.get_opcode_str().0));
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.monospace(format!("{} cycles", chip8.cycle_count()));
+                        });
+                    });
+                });
+            }
+
+            if let Some(err) = self.chip8.last_error() {
+                egui::TopBottomPanel::bottom("status_bar").show(&egui_ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::RED, format!("⚠ {}", err));
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("Dismiss").clicked() {
+                                self.chip8.clear_error();
+                            }
+                        });
+                    });
+                });
+            } else if let Some(status) = self.run_status {
+                egui::TopBottomPanel::bottom("status_bar").show(&egui_ctx, |ui| {
+                    ui.label(match status {
+                        RunOutcome::Idle => "Waiting for a key",
+                        _ => "Program halted",
+                    });
+                });
+            }
+
+            if !self.rom_warnings.is_empty() {
+                egui::TopBottomPanel::bottom("rom_warnings").show(&egui_ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        let listing = self
+                            .rom_warnings
+                            .iter()
+                            .map(RomWarning::to_string)
+                            .collect::<Vec<_>>()
+                            .join("\n");
+
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("⚠ {} possible problems with this ROM", self.rom_warnings.len()),
+                        )
+                        .on_hover_text(listing);
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("Dismiss").clicked() {
+                                self.rom_warnings.clear();
+                            }
+                        });
+                    });
+                });
+            }
+
+            if self.log_open {
+                egui::TopBottomPanel::bottom("log")
+                    .resizable(true)
+                    .show(&egui_ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.strong("Log");
+
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                if ui.button("Clear").clicked() {
+                                    console::entries().clear();
+                                }
+
+                                if ui.button("Copy").clicked() {
+                                    ui.output().copied_text = console::text();
+                                }
+                            });
+                        });
+
+                        egui::ScrollArea::vertical()
+                            .auto_shrink([false; 2])
+                            .stick_to_bottom(true)
+                            .show(ui, |ui| {
+                                for entry in console::entries().iter() {
+                                    let color = match entry.level {
+                                        Level::Error => Color32::RED,
+                                        Level::Warn => Color32::YELLOW,
+                                        _ => ui.visuals().text_color(),
+                                    };
+
+                                    ui.colored_label(color, &entry.message);
+                                }
+                            });
+                    });
+            }
+
+            if self.browser_open {
+                self.draw_rom_browser(egui_ctx);
+            }
+
+            if self.keypad_open {
+                egui::TopBottomPanel::bottom("keypad").show(&egui_ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        let mut chip8 = self.chip8.write().unwrap();
+                        self.virtual_keypad.draw(ui, &mut chip8.keypad);
+                    });
+                });
+            }
+
             egui::CentralPanel::default().show(&egui_ctx, |ui| {
-                ui.add_sized(
-                    ui.available_size(),
+                let rect = ui.available_rect_before_wrap();
+
+                if let Some(background) = self.background_texture {
                     Image::new(
-                        TextureId::User(self.screen_texture.unwrap().gl_internal_id() as u64),
-                        Vec2::new(640.0, 360.0),
-                    ),
-                );
+                        TextureId::User(background.gl_internal_id() as u64),
+                        rect.size(),
+                    )
+                    .paint_at(ui, rect);
+                }
+
+                let (width, height) = self.chip8.read().unwrap().screen_dimensions();
+                let screen = letterbox(rect, (width, height), self.pixels_per_point);
+                if let Some(texture) = self.display.texture(mq_ctx) {
+                    ui.put(screen, Image::new(texture, screen.size()));
+                }
+
+                let settings = SettingsWindow::for_rom(egui_ctx, rom_hash);
+                let scale = screen.width() / width as f32;
+                if settings.show_grid && scale >= GRID_MIN_SCALE {
+                    let stroke = Stroke::new(1.0, settings.grid_color);
+
+                    for x in 1..width {
+                        let x = screen.left() + x as f32 * scale;
+                        ui.painter()
+                            .vline(x, screen.y_range(), stroke);
+                    }
+
+                    for y in 1..height {
+                        let y = screen.top() + y as f32 * scale;
+                        ui.painter()
+                            .hline(screen.x_range(), y, stroke);
+                    }
+                }
             });
 
+            #[cfg(feature = "url-rom")]
+            if let Some(url) = &mut self.url_prompt {
+                let mut open = true;
+                let mut submitted = false;
+
+                egui::Window::new("Open ROM from URL")
+                    .open(&mut open)
+                    .resizable(false)
+                    .show(egui_ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            let input = ui.add(
+                                egui::TextEdit::singleline(url)
+                                    .hint_text("https://…/rom.ch8")
+                                    .desired_width(300.0),
+                            );
+
+                            submitted = (input.lost_focus() && ui.input().key_pressed(egui::Key::Enter))
+                                | ui.add_enabled(!url.trim().is_empty(), egui::Button::new("Open")).clicked();
+                        });
+                    });
+
+                if submitted && !url.trim().is_empty() {
+                    let url = url.trim().to_string();
+                    self.url_prompt = None;
+                    self.open_rom_url(url);
+                } else if !open {
+                    self.url_prompt = None;
+                }
+            }
+
+            if let Some(message) = &self.error_dialog {
+                let mut dismissed = false;
+
+                egui::Window::new("Error")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+                    .show(egui_ctx, |ui| {
+                        ui.label(message);
+                        dismissed = ui.button("OK").clicked();
+                    });
+
+                if dismissed {
+                    self.error_dialog = None;
+                }
+            }
+
+            egui::Window::new("About")
+                .open(&mut self.about_open)
+                .resizable(false)
+                .show(egui_ctx, |ui| {
+                    ui.heading(window::window_title("CHIP-8 emulator"));
+                    ui.label("CHIP-8 emulator with an interpreter, a JIT and a debugger.");
+
+                    ui.separator();
+
+                    egui::Grid::new("about_grid").show(ui, |ui| {
+                        ui.label("Commit");
+                        ui.label(option_env!("CHIPPY_GIT_COMMIT").unwrap_or("Unknown"));
+                        ui.end_row();
+
+                        let chip8 = self.chip8.read().unwrap();
+
+                        ui.label("Quirks");
+                        ui.label(format!("{:?}", chip8.quirks()));
+                        ui.end_row();
+
+                        if let Some(name) = &self.rom_name {
+                            ui.label("ROM");
+                            ui.label(name);
+                            ui.end_row();
+
+                            ui.label("ROM hash");
+                            ui.label(format!("{:016X}", chip8.rom_hash()));
+                            ui.end_row();
+                        }
+                    });
+                });
+
             egui::Window::new("Settings")
                 .open(&mut self.settings_open)
                 .show(egui_ctx, |ui| {
-                    let persistent_id = ui.make_persistent_id("frequency_selector_option");
+                    let profile = SettingsWindow::saved_for_rom(egui_ctx, rom_hash);
+                    let mut per_rom = profile.is_some();
+                    let mut settings = profile.unwrap_or_else(|| {
+                        SettingsWindow::load_state(egui_ctx, SettingsWindow::id())
+                    });
+
+                    if ui
+                        .add_enabled(
+                            self.rom_name.is_some(),
+                            egui::Checkbox::new(&mut per_rom, "Only for this ROM"),
+                        )
+                        .on_hover_text("Keep these settings for the loaded ROM, restored on opening it")
+                        .changed()
+                        && !per_rom
+                    {
+                        // Back to the global settings.
+                        egui_ctx
+                            .data()
+                            .remove::<SettingsWindow>(SettingsWindow::rom_id(rom_hash));
+                        settings = SettingsWindow::load_state(egui_ctx, SettingsWindow::id());
+                        self.use_settings(mq_ctx, &settings);
+                    }
+
+                    ui.separator();
+
+                    let mode_changed = ui
+                        .horizontal(|ui| {
+                            ui.radio_value(&mut settings.mode, ExecutionMode::Frequency, "Hz")
+                                .changed()
+                                | ui.radio_value(
+                                    &mut settings.mode,
+                                    ExecutionMode::InstructionsPerFrame,
+                                    "IPF",
+                                )
+                                .changed()
+                                | ui.radio_value(&mut settings.mode, ExecutionMode::Vsync, "VSync")
+                                    .on_hover_text(
+                                        "Run on the render loop, pauses while the window isn't drawn",
+                                    )
+                                    .changed()
+                        })
+                        .inner;
+
+                    let speed_changed = if settings.mode != ExecutionMode::Frequency {
+                        let changed = ui
+                            .add(
+                                egui::Slider::new(&mut settings.ipf, 1..=100)
+                                    .text("Instructions per frame"),
+                            )
+                            .changed();
+
+                        let cap_changed = ui
+                            .horizontal(|ui| {
+                                let changed = ui
+                                    .add(egui::DragValue::new(&mut settings.max_cycles).clamp_range(1..=100000))
+                                    .changed();
+                                ui.label("Max instructions per frame")
+                                    .on_hover_text("Keeps the window responsive at extreme speeds");
+
+                                if self.chip8.is_capped() {
+                                    ui.colored_label(Color32::YELLOW, "⚠ Limited");
+                                }
+
+                                changed
+                            })
+                            .inner;
+
+                        changed || cap_changed
+                    } else {
+                        let mut changed = ui
+                            .add(
+                                egui::Slider::new(&mut settings.frequency, 1..=MAX_FREQUENCY)
+                                    .text("Frequency")
+                                    .suffix(" Hz"),
+                            )
+                            .changed();
+
+                        ui.horizontal(|ui| {
+                            for preset in FREQUENCY_PRESETS {
+                                if ui
+                                    .selectable_label(
+                                        settings.frequency == preset,
+                                        format!("{} Hz", preset),
+                                    )
+                                    .clicked()
+                                {
+                                    settings.frequency = preset;
+                                    changed = true;
+                                }
+                            }
+
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut settings.frequency)
+                                        .clamp_range(1..=MAX_FREQUENCY)
+                                        .suffix(" Hz"),
+                                )
+                                .changed();
+                        });
 
-                    let mut settings = SettingsWindow::load_state(egui_ctx, persistent_id);
+                        changed
+                    };
+
+                    // Compare against what the machine actually manages.
+                    // Cycle costs make instructions take several cycles, so the rates differ anyway.
+                    let target = match settings.mode {
+                        ExecutionMode::Frequency => settings.frequency as f64,
+                        _ => settings.ipf as f64 * 60.0,
+                    };
+                    let measured = format!("Measured {:.0} Hz", self.measured_rate);
+                    if self.chip8.is_running()
+                        && !settings.cycle_costs
+                        && self.measured_rate < target * 0.9
+                    {
+                        ui.colored_label(Color32::YELLOW, measured)
+                            .on_hover_text("Execution is slower than requested");
+                    } else {
+                        ui.label(measured);
+                    }
+
+                    let cost_changed = ui
+                        .checkbox(&mut settings.cycle_costs, "Instruction cycle costs")
+                        .on_hover_text("Slow instructions such as DRW take several cycles")
+                        .changed();
+
+                    let flicker_changed = ui
+                        .horizontal(|ui| {
+                            ui.checkbox(&mut settings.reduce_flicker, "Reduce flicker")
+                                .on_hover_text("Wait for the next frame after this many sprites are drawn")
+                                .changed()
+                                | ui.add_enabled(
+                                    settings.reduce_flicker,
+                                    egui::DragValue::new(&mut settings.draws_per_frame)
+                                        .clamp_range(1..=MAX_DRAWS_PER_FRAME)
+                                        .suffix(" draws"),
+                                )
+                                .changed()
+                        })
+                        .inner;
+
+                    let warn_changed = ui
+                        .checkbox(&mut settings.warn_misaligned_pc, "Warn on odd PC")
+                        .on_hover_text("Log jumps to odd addresses, which are usually mistakes")
+                        .changed();
+
+                    let idle_changed = ui
+                        .checkbox(&mut settings.skip_idle, "Sleep while waiting for input")
+                        .on_hover_text("Stop executing while the ROM only polls the keypad")
+                        .changed();
+
+                    let trap_changed = ui
+                        .checkbox(&mut settings.trap_empty_memory, "Stop on empty memory")
+                        .on_hover_text(
+                            "Fail on 0000 0000, usually from running past the end of the program",
+                        )
+                        .changed();
 
                     if ui
-                        .add(egui::Slider::new(&mut settings.frequency, 1..=6000).text("Frequency"))
+                        .checkbox(&mut settings.break_opens_debugger, "Open debugger on breakpoint")
                         .changed()
                     {
-                        self.chip8.set_frequency(settings.frequency as i32);
+                        self.break_opens_debugger = settings.break_opens_debugger;
                     }
 
-                    settings.save_state(egui_ctx, persistent_id);
+                    ui.separator();
+
+                    let profile = egui::ComboBox::from_label("Quirks")
+                        .selected_text(QuirkProfile::detect(settings.quirks).name())
+                        .show_ui(ui, |ui| {
+                            let mut changed = false;
+                            for profile in QuirkProfile::PRESETS {
+                                if ui
+                                    .selectable_label(
+                                        settings.quirks == Quirks::from_profile(profile),
+                                        profile.name(),
+                                    )
+                                    .clicked()
+                                {
+                                    settings.quirks = Quirks::from_profile(profile);
+                                    changed = true;
+                                }
+                            }
+
+                            changed
+                        });
+
+                    let mut quirks_changed = profile.inner.unwrap_or(false);
+                    profile
+                        .response
+                        .on_hover_text("Behavior differing between interpreters, pick the one the ROM was made for");
+
+                    egui::CollapsingHeader::new("Advanced quirks").show(ui, |ui| {
+                        let quirks = &mut settings.quirks;
+                        quirks_changed |= ui
+                            .checkbox(&mut quirks.reset_vf_on_logic, "Logic clears VF")
+                            .on_hover_text("OR, AND and XOR set VF to 0")
+                            .changed()
+                            | ui.checkbox(&mut quirks.strict_sub_flag, "Strict SUB flag")
+                                .on_hover_text("SUB and SUBN clear VF when the operands are equal")
+                                .changed()
+                            | ui.checkbox(&mut quirks.align_jumps, "Align jumps")
+                                .on_hover_text("Jumps clear the lowest bit of the target")
+                                .changed()
+                            | ui.checkbox(&mut quirks.wrap_x, "Wrap sprites horizontally")
+                                .on_hover_text("Otherwise sprites are clipped at the right edge")
+                                .changed()
+                            | ui.checkbox(&mut quirks.wrap_y, "Wrap sprites vertically")
+                                .on_hover_text("Otherwise sprites are clipped at the bottom edge")
+                                .changed()
+                            | ui.checkbox(&mut quirks.shift_uses_vy, "Shift Vy")
+                                .on_hover_text("SHR and SHL shift Vy into Vx, not Vx in place")
+                                .changed()
+                            | ui.checkbox(&mut quirks.load_store_increments_i, "LD [I] moves I")
+                                .on_hover_text("Storing and loading registers moves I past them")
+                                .changed()
+                            | ui.checkbox(&mut quirks.jump_with_vx, "Jump with Vx")
+                                .on_hover_text("JP V0, xnn adds Vx instead of V0")
+                                .changed()
+                            | ui.checkbox(&mut quirks.display_wait, "Display wait")
+                                .on_hover_text("DRW waits for the next frame to draw")
+                                .changed()
+                            | ui.checkbox(&mut quirks.index_overflow_vf, "I overflow sets VF")
+                                .on_hover_text("ADD I, Vx sets VF when I passes the end of memory")
+                                .changed();
+                    });
+
+                    ui.separator();
+
+                    let beep_changed = ui
+                        .add(
+                            egui::Slider::new(&mut settings.beep_frequency, 50.0..=2000.0)
+                                .text("Beep tone (Hz)"),
+                        )
+                        .changed();
+
+                    let waveform_changed = egui::ComboBox::from_label("Beep waveform")
+                        .selected_text(format!("{:?}", settings.waveform))
+                        .show_ui(ui, |ui| {
+                            let mut changed = false;
+                            for waveform in [Waveform::Square, Waveform::Sine, Waveform::Triangle] {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut settings.waveform,
+                                        waveform,
+                                        format!("{:?}", waveform),
+                                    )
+                                    .changed();
+                            }
+
+                            changed
+                        })
+                        .inner
+                        .unwrap_or(false);
+
+                    let volume_changed = ui
+                        .add(egui::Slider::new(&mut settings.volume, 0.0..=1.0).text("Volume"))
+                        .changed();
+
+                    if ui
+                        .checkbox(&mut settings.mute_on_blur, "Mute when unfocused")
+                        .changed()
+                    {
+                        self.mute_on_blur = settings.mute_on_blur;
+                    }
+
+                    if mode_changed
+                        || speed_changed
+                        || cost_changed
+                        || flicker_changed
+                        || warn_changed
+                        || trap_changed
+                        || idle_changed
+                        || quirks_changed
+                        || beep_changed
+                        || waveform_changed
+                        || volume_changed
+                    {
+                        settings.apply(&self.chip8);
+                    }
+
+                    ui.separator();
+
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut settings.show_grid, "Pixel grid")
+                            .on_hover_text(format!("Only shown at {}x scale or larger", GRID_MIN_SCALE));
+                        ui.color_edit_button_srgba(&mut settings.grid_color);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Palette");
+
+                        let names = ["Background", "Foreground"];
+                        for (color, name) in settings.palette.iter_mut().zip(names) {
+                            ui.color_edit_button_srgba(color).on_hover_text(name);
+                        }
+                    });
+
+                    if settings.palette != self.palette {
+                        self.palette = settings.palette;
+                        self.screen_stale = true;
+                    }
+
+                    ui.separator();
+
+                    let mut background_changed = false;
+                    ui.horizontal(|ui| {
+                        ui.label("Background image");
+
+                        if ui.button("Choose").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Image", &["png", "jpg", "jpeg"])
+                                .pick_file()
+                            {
+                                settings.background_image = Some(path);
+                                background_changed = true;
+                            }
+                        }
+
+                        if ui
+                            .add_enabled(
+                                settings.background_image.is_some(),
+                                egui::Button::new("Clear"),
+                            )
+                            .clicked()
+                        {
+                            settings.background_image = None;
+                            background_changed = true;
+                        }
+                    });
+
+                    if background_changed {
+                        replace_texture(
+                            mq_ctx,
+                            &mut self.background_texture,
+                            settings.background_image.as_deref(),
+                        );
+                    }
+
+                    ui.separator();
+
+                    let watch_changed = ui
+                        .add_enabled(
+                            self.rom_watcher.is_some(),
+                            egui::Checkbox::new(&mut self.watch_rom, "Reload ROM on change"),
+                        )
+                        .on_hover_text("Only available for ROMs opened from a file")
+                        .changed();
+
+                    // Don't reload for changes made while not watching.
+                    if watch_changed && self.watch_rom {
+                        if let Some(watcher) = &mut self.rom_watcher {
+                            *watcher = RomWatcher::new(watcher.path().to_owned());
+                        }
+                    }
+
+                    ui.separator();
+
+                    ui.checkbox(&mut self.pause_on_blur, "Pause when unfocused");
+                    ui.add_enabled(
+                        self.pause_on_blur,
+                        egui::Checkbox::new(&mut self.resume_on_focus, "Resume when focused"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label("Key repeat");
+                        ui.radio_value(&mut self.key_repeat, KeyRepeat::Ignore, "Ignore")
+                            .on_hover_text("Keep held keys pressed");
+                        ui.radio_value(&mut self.key_repeat, KeyRepeat::Pulse, "Pulse")
+                            .on_hover_text("Briefly release held keys on each repeat, like tapping them");
+                    });
+
+                    let layout_changed = ui
+                        .horizontal(|ui| {
+                            let layout = &mut self.key_layout;
+                            ui.label("Key layout");
+                            let cosmac = ui
+                                .radio_value(layout, KeyLayout::Cosmac, "COSMAC")
+                                .on_hover_text("Rows 1234, QWER, ASDF and ZXCV like the keypad");
+                            let positional = ui
+                                .radio_value(layout, KeyLayout::Positional, "Positional")
+                                .on_hover_text("Arrows or WASD for 2, 4, 6 and 8, space for 5");
+
+                            cosmac.changed() || positional.changed()
+                        })
+                        .inner;
+
+                    if layout_changed {
+                        storage::storage().set(KEY_LAYOUT_KEY, &self.key_layout);
+
+                        // Keys held under the old layout would never be released.
+                        self.chip8.write().unwrap().keypad.reset();
+                    }
+
+                    ui.checkbox(&mut self.debug_on_error, "Open debugger on error");
+
+                    ui.checkbox(&mut self.low_power_paused, "Low power when paused")
+                        .on_hover_text("Redraw less often while execution is paused");
+
+                    let frame_cap = egui::ComboBox::from_label("Frame rate")
+                        .selected_text(self.frame_cap.name())
+                        .show_ui(ui, |ui| {
+                            let mut changed = false;
+                            for frame_cap in FrameCap::ALL {
+                                changed |= ui
+                                    .selectable_value(&mut self.frame_cap, frame_cap, frame_cap.name())
+                                    .changed();
+                            }
+
+                            changed
+                        });
+
+                    if frame_cap.inner.unwrap_or(false) {
+                        storage::storage().set(FRAME_CAP_KEY, &self.frame_cap);
+                    }
+
+                    frame_cap
+                        .response
+                        .on_hover_text("In vsync mode this also limits execution speed");
+
+                    ui.separator();
+
+                    let startup_changed = egui::ComboBox::from_label("On startup")
+                        .selected_text(self.startup.name())
+                        .show_ui(ui, |ui| {
+                            let mut changed = false;
+                            for startup in [Startup::LastRom, Startup::TestRom, Startup::Empty] {
+                                changed |= ui
+                                    .selectable_value(&mut self.startup, startup, startup.name())
+                                    .changed();
+                            }
+
+                            changed
+                        })
+                        .inner
+                        .unwrap_or(false);
+
+                    if startup_changed {
+                        storage::storage().set(STARTUP_KEY, &self.startup);
+                    }
+
+                    let id = if per_rom {
+                        SettingsWindow::rom_id(rom_hash)
+                    } else {
+                        SettingsWindow::id()
+                    };
+                    settings.save_state(egui_ctx, id);
                 });
         });
 
@@ -206,33 +1812,201 @@ impl Window for MainApp {
         mq_ctx.commit_frame();
     }
 
-    fn on_event(&mut self, _ctx: &mut mq::Context, event: window::Event) -> bool {
+    fn persistence_id(&self) -> Option<&'static str> {
+        Some("main")
+    }
+
+    fn save(&mut self, ctx: &mut mq::Context, storage: &mut Storage) {
+        // Screen size is in physical pixels while the config uses logical ones.
+        let (width, height) = ctx.screen_size();
+        let dpi_scale = ctx.dpi_scale();
+        storage.set(WINDOW_SIZE_KEY, &(width / dpi_scale, height / dpi_scale));
+    }
+
+    fn on_event(&mut self, ctx: &mut mq::Context, event: window::Event) -> bool {
         match event {
             window::Event::KeyUp {
                 keycode,
                 keymods: _,
-            } => self
-                .chip8
-                .write()
-                .unwrap()
-                .keypad
-                .key_event(KeyEvent::KeyUp, keycode),
+            } => {
+                // Released before the pulse finished.
+                self.repressed.retain(|repressed| *repressed != keycode);
+
+                self.chip8.write().unwrap().keypad.key_event(
+                    self.key_layout,
+                    KeyEvent::KeyUp,
+                    keycode,
+                )
+            }
             window::Event::KeyDown {
                 keycode,
                 keymods: _,
                 repeat,
             } => {
                 if !repeat {
-                    self.chip8
-                        .write()
-                        .unwrap()
-                        .keypad
-                        .key_event(KeyEvent::KeyDown, keycode)
+                    self.chip8.write().unwrap().keypad.key_event(
+                        self.key_layout,
+                        KeyEvent::KeyDown,
+                        keycode,
+                    )
+                } else if let Some(event) = self.key_repeat.translate() {
+                    let caught = self.chip8.write().unwrap().keypad.key_event(
+                        self.key_layout,
+                        event,
+                        keycode,
+                    );
+                    if caught {
+                        self.repressed.push(keycode);
+                    }
+
+                    caught
                 } else {
                     false
                 }
             }
+            window::Event::WindowFocus { focused } => {
+                self.chip8.write().unwrap().beeper.muted = self.mute_on_blur && !focused;
+
+                if !focused {
+                    // Key up events go to the focused window, so held keys would stay down.
+                    self.chip8.write().unwrap().keypad.reset();
+                    self.virtual_keypad.reset();
+
+                    // Never auto-resume something the user paused themselves.
+                    if self.pause_on_blur && self.chip8.is_running() {
+                        self.chip8.set_running(false);
+                        self.auto_paused = true;
+                    }
+                } else if self.auto_paused {
+                    if self.resume_on_focus {
+                        self.chip8.set_running(true);
+                    }
+                    self.auto_paused = false;
+                }
+
+                false
+            }
+            window::Event::Resized { .. } => {
+                // The letterbox is recomputed every frame, only the scale can go stale.
+                self.pixels_per_point = ctx.dpi_scale();
+                false
+            }
+            window::Event::Touch { phase, id, x, y } => {
+                if !self.keypad_open {
+                    return true;
+                }
+
+                // Touches are in physical pixels, the keypad was laid out in points.
+                let pos = egui::pos2(x / self.pixels_per_point, y / self.pixels_per_point);
+                let mut chip8 = self.chip8.write().unwrap();
+                !self.virtual_keypad.touch(phase, id, pos, &mut chip8.keypad)
+            }
             _ => true,
         }
     }
 }
+
+/// File passed with `--trace <file>` to write an execution trace to, if any.
+/// `--trace-state <file>` writes the state after each instruction instead.
+fn trace_from_args() -> Option<(PathBuf, TraceFormat)> {
+    let mut args = env::args();
+    while let Some(arg) = args.next() {
+        let format = match arg.as_str() {
+            "--trace" => TraceFormat::Instructions,
+            "--trace-state" => TraceFormat::State,
+            _ => continue,
+        };
+
+        return args.next().map(|path| (PathBuf::from(path), format));
+    }
+
+    None
+}
+
+/// `color` as RGBA with the alpha ignored, the screen is always fully opaque.
+fn opaque(color: Color32) -> [u8; 4] {
+    let [r, g, b, _] = color.to_array();
+    [r, g, b, 255]
+}
+
+/// Largest integer multiple of the screen resolution centered in `rect`, see `render::fit`.
+/// Falls back to a fractional scale when `rect` is smaller than the screen.
+///
+/// The multiple is of physical pixels and the result is aligned to them,
+/// so each emulated pixel covers the same amount of display pixels on high DPI displays too.
+fn letterbox(rect: Rect, (width, height): (usize, usize), pixels_per_point: f32) -> Rect {
+    let min = (rect.min.to_vec2() * pixels_per_point).round();
+    let max = (rect.max.to_vec2() * pixels_per_point).floor();
+    let physical = Rect::from_min_max(min.to_pos2(), max.to_pos2());
+
+    let scale = (physical.width() / width as f32).min(physical.height() / height as f32);
+    if scale < 1.0 {
+        let size = Vec2::new(width as f32 * scale, height as f32 * scale);
+        return Rect::from_center_size(rect.center(), size / pixels_per_point);
+    }
+
+    let fitted = render::fit(
+        (width, height),
+        (physical.width() as usize, physical.height() as usize),
+    );
+
+    Rect::from_min_size(
+        ((physical.min.to_vec2() + Vec2::new(fitted.x as f32, fitted.y as f32)) / pixels_per_point)
+            .to_pos2(),
+        Vec2::new(fitted.width as f32, fitted.height as f32) / pixels_per_point,
+    )
+}
+
+/// Replace a texture with an image file, leaving no texture if it can't be loaded.
+fn replace_texture(ctx: &mut mq::Context, texture: &mut Option<Texture>, path: Option<&Path>) {
+    if let Some(texture) = texture.take() {
+        texture.delete();
+    }
+
+    *texture = path.and_then(|path| load_texture(ctx, path));
+}
+
+/// Load an image file as a texture, `None` if it can't be used.
+fn load_texture(ctx: &mut mq::Context, path: &Path) -> Option<Texture> {
+    let image = match image::open(path) {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => {
+            log::warn!("Unable to load image {}: {}", path.display(), err);
+            return None;
+        }
+    };
+
+    match (u16::try_from(image.width()), u16::try_from(image.height())) {
+        (Ok(width), Ok(height)) => Some(Texture::from_rgba8(ctx, width, height, &image)),
+        _ => {
+            log::warn!("Image {} is too large", path.display());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rom_settings_override_global() {
+        let ctx = egui::Context::default();
+
+        let global = SettingsWindow::default();
+        global.save_state(&ctx, SettingsWindow::id());
+
+        let mut saved = SettingsWindow::default();
+        saved.quirks.wrap_x = !saved.quirks.wrap_x;
+        saved.palette[1] = Color32::GREEN;
+        saved.clone().save_state(&ctx, SettingsWindow::rom_id(1));
+
+        let loaded = SettingsWindow::for_rom(&ctx, 1);
+        assert_eq!(loaded.quirks, saved.quirks);
+        assert_eq!(loaded.palette, saved.palette);
+
+        let unknown = SettingsWindow::for_rom(&ctx, 2);
+        assert_eq!(unknown.quirks, Quirks::default());
+        assert_eq!(unknown.palette, DEFAULT_PALETTE);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use chippy_core::ExecutingChip8;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use once_cell::sync::Lazy;
+
+/// Most samples kept for the scope, about 10 ms at common sample rates.
+const SCOPE_SIZE: usize = 512;
+
+/// Start of the buffer last sent to the output device, first channel only.
+static SCOPE: Lazy<Mutex<Vec<f32>>> = Lazy::new(Default::default);
+
+/// Samples last played, for drawing the waveform.
+pub fn scope() -> Vec<f32> {
+    SCOPE.lock().unwrap().clone()
+}
+
+/// Play the emulator's beep on the default output device.
+/// The stream can't be moved between threads so it's kept alive on its own.
+pub fn start(chip8: Arc<ExecutingChip8>) {
+    thread::spawn(move || match build_stream(chip8) {
+        Ok(_stream) => loop {
+            thread::park();
+        },
+        Err(err) => log::error!("Unable to start audio: {}", err),
+    });
+}
+
+fn build_stream(chip8: Arc<ExecutingChip8>) -> Result<cpal::Stream, Box<dyn Error>> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("no output device")?;
+
+    let config = device.default_output_config()?.config();
+    let sample_rate = config.sample_rate.0;
+    let channels = config.channels as usize;
+
+    let stream = device.build_output_stream(
+        &config,
+        move |data: &mut [f32], _| {
+            chip8
+                .write()
+                .unwrap()
+                .audio_samples(data, sample_rate, channels);
+
+            let mut scope = SCOPE.lock().unwrap();
+            scope.clear();
+            scope.extend(data.iter().step_by(channels.max(1)).take(SCOPE_SIZE));
+        },
+        |err| log::error!("Audio stream error: {}", err),
+    )?;
+
+    stream.play()?;
+    Ok(stream)
+}
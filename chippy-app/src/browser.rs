@@ -0,0 +1,107 @@
+//! Listing of the ROMs in a directory, for going through a collection without the file dialog.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chippy_core::rom::{self, Variant};
+
+/// Extensions of files listed as ROMs.
+const ROM_EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+
+/// Subdirectories deeper than this aren't listed, in case of links back up the tree.
+const MAX_DEPTH: usize = 8;
+
+pub struct RomEntry {
+    pub path: PathBuf,
+    /// Path relative to the browsed directory.
+    pub name: String,
+    pub size: u64,
+    /// `None` if the file couldn't be read.
+    pub variant: Option<Variant>,
+    /// `rom::hash` of the file, `None` if it couldn't be read.
+    pub hash: Option<u64>,
+}
+
+/// ROMs found in a directory and its subdirectories, sorted by path.
+#[derive(Default)]
+pub struct RomBrowser {
+    dir: Option<PathBuf>,
+    entries: Vec<RomEntry>,
+}
+
+impl RomBrowser {
+    /// List the ROMs in `dir`.
+    pub fn new(dir: PathBuf) -> Self {
+        let mut browser = Self {
+            dir: Some(dir),
+            entries: Vec::new(),
+        };
+
+        browser.rescan();
+        browser
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    /// List the directory again, e.g. after files were added.
+    pub fn rescan(&mut self) {
+        self.entries.clear();
+
+        if let Some(dir) = &self.dir {
+            if let Err(err) = scan(dir, dir, 0, &mut self.entries) {
+                log::warn!("Unable to list ROMs in {}: {}", dir.display(), err);
+            }
+        }
+
+        self.entries.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+}
+
+fn scan(base: &Path, dir: &Path, depth: usize, entries: &mut Vec<RomEntry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            // An unreadable subdirectory shouldn't hide the rest.
+            if depth < MAX_DEPTH {
+                let _ = scan(base, &path, depth + 1, entries);
+            }
+
+            continue;
+        }
+
+        let is_rom = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ROM_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+            });
+
+        if !is_rom {
+            continue;
+        }
+
+        let contents = fs::read(&path).ok();
+        entries.push(RomEntry {
+            name: path
+                .strip_prefix(base)
+                .unwrap_or(&path)
+                .display()
+                .to_string(),
+            size: contents.as_ref().map_or(0, |rom| rom.len() as u64),
+            variant: contents.as_deref().map(rom::detect_variant),
+            hash: contents.as_deref().map(rom::hash),
+            path,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Logger keeping recent messages for the in-app log panel.
+//!
+//! Messages are still printed, but builds started without a console can only show them here.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{Mutex, MutexGuard},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+/// Most messages kept, older ones are dropped.
+const MAX_ENTRIES: usize = 1000;
+
+static ENTRIES: Lazy<Mutex<VecDeque<Entry>>> = Lazy::new(Default::default);
+
+pub struct Entry {
+    pub level: Level,
+    pub message: String,
+}
+
+struct Console;
+
+impl Log for Console {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let message = record.args().to_string();
+        println!("[{}] {}", record.level(), message);
+
+        let mut entries = entries();
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+        }
+
+        entries.push_back(Entry {
+            level: record.level(),
+            message,
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger, this should happen before anything logs.
+pub fn init() {
+    static CONSOLE: Console = Console;
+
+    if log::set_logger(&CONSOLE).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Messages logged so far, oldest first.
+pub fn entries() -> MutexGuard<'static, VecDeque<Entry>> {
+    ENTRIES.lock().unwrap()
+}
+
+/// All messages as text, e.g. to paste in a bug report.
+pub fn text() -> String {
+    entries().iter().fold(String::new(), |mut text, entry| {
+        let _ = writeln!(text, "[{}] {}", entry.level, entry.message);
+        text
+    })
+}
@@ -1,23 +1,312 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    ops::Range,
+    sync::Arc,
+    time::Duration,
+};
 
 use chippy_core::{
-    opcode::{extract_opcode_from_array, OpCode},
-    ExecutingChip8,
+    breakpoint::Condition,
+    disassembler::{self, DisasmLine},
+    opcode::OpCode,
+    types::C8Addr,
+    Chip8, ExecutingChip8, BIG_FONT_ADDR, BIG_FONT_SIZE, FONT_ADDR, FONT_SIZE, MAX_CALL_DEPTH,
+    PROGRAM_ADDR,
 };
-use egui::{Align, Color32, RichText, Ui};
+use egui::{Align, Color32, RichText, Sense, Ui};
 
-use crate::window::{self, Window};
+use crate::{
+    audio, storage,
+    window::{self, Window},
+};
 
 use {egui_miniquad as egui_mq, miniquad as mq};
 
+/// Bytes shown per row of the memory viewer.
+const MEMORY_ROW: usize = 16;
+
+/// Regions annotated in the memory viewer, in address order.
+const MEMORY_MAP: [(&str, Range<usize>); 4] = [
+    ("Font", FONT_ADDR..FONT_ADDR + FONT_SIZE),
+    ("Big font", BIG_FONT_ADDR..BIG_FONT_ADDR + BIG_FONT_SIZE),
+    ("Reserved", BIG_FONT_ADDR + BIG_FONT_SIZE..PROGRAM_ADDR),
+    ("Program", PROGRAM_ADDR..usize::MAX),
+];
+
+/// Bytes highlighted from I, the most a sprite or `LD [I], Vx` reaches.
+const INDEX_SPAN: usize = 16;
+
+/// Largest step N, more would freeze the UI while running.
+const MAX_STEP_COUNT: usize = 1_000_000;
+
+/// Longest run for.
+const MAX_RUN_MILLIS: u64 = 2000;
+
+/// Storage key of the disassembly syntax toggle.
+const OCTO_SYNTAX_KEY: &str = "debugger/octo_syntax";
+
+/// Storage key of the audio scope toggle.
+const AUDIO_SCOPE_KEY: &str = "debugger/audio_scope";
+
+/// Height of the audio scope in points.
+const SCOPE_HEIGHT: f32 = 60.0;
+
+/// Memory writes kept while recording them.
+const WRITE_LOG_SIZE: usize = 256;
+
 enum DebuggerTab {
     Registers,
     Dissasembly,
+    Memory,
+}
+
+/// Disassembly of the program region, only rebuilt when memory changes.
+#[derive(Default)]
+struct Listing {
+    memory: Vec<u8>,
+    lines: Vec<DisasmLine>,
+    targets: BTreeSet<C8Addr>,
+}
+
+impl Listing {
+    fn update(&mut self, memory: &[u8]) {
+        if self.memory != memory {
+            self.memory = memory.to_vec();
+            self.lines = disassembler::disassemble(memory, 0x200, memory.len());
+            self.targets = disassembler::jump_targets(&self.lines);
+        }
+    }
+}
+
+/// Names of branch targets, generated as `L_0218` unless the user renamed them.
+/// Renamed labels are persisted per ROM hash.
+#[derive(Default)]
+struct Labels {
+    rom_hash: u64,
+    names: BTreeMap<C8Addr, String>,
+    /// Only list labels containing this.
+    filter: String,
+    /// Label being renamed and its new name.
+    renaming: Option<(C8Addr, String)>,
+}
+
+impl Labels {
+    /// Load the names assigned for the ROM if it changed.
+    fn update(&mut self, rom_hash: u64) {
+        if self.rom_hash != rom_hash {
+            self.rom_hash = rom_hash;
+            self.names = storage::storage()
+                .get(&self.storage_key())
+                .unwrap_or_default();
+            self.renaming = None;
+        }
+    }
+
+    fn name(&self, addr: C8Addr) -> String {
+        self.names
+            .get(&addr)
+            .cloned()
+            .unwrap_or_else(|| format!("L_{:04X}", addr))
+    }
+
+    /// Rename a label, an empty name restores the generated one.
+    fn rename(&mut self, addr: C8Addr, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            self.names.remove(&addr);
+        } else {
+            self.names.insert(addr, name.to_owned());
+        }
+
+        storage::storage().set(&self.storage_key(), &self.names);
+    }
+
+    fn storage_key(&self) -> String {
+        format!("labels/{:016X}", self.rom_hash)
+    }
+}
+
+#[derive(PartialEq)]
+enum SearchKind {
+    /// Hex bytes where `?` matches any nibble, e.g. `6?00`.
+    Bytes,
+    /// Decimal value, two bytes (big endian) if it doesn't fit in one.
+    Value,
+}
+
+/// Search of memory for a pattern, only rerun when the query or memory changes.
+struct MemorySearch {
+    query: String,
+    kind: SearchKind,
+    memory: Vec<u8>,
+    /// Query changed since the last search.
+    changed: bool,
+    invalid: bool,
+    /// Length of the pattern the results were found with.
+    len: usize,
+    results: Vec<C8Addr>,
+    /// Result last jumped to.
+    selected: Option<C8Addr>,
+}
+
+impl Default for MemorySearch {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            kind: SearchKind::Bytes,
+            memory: Vec::new(),
+            changed: false,
+            invalid: false,
+            len: 0,
+            results: Vec::new(),
+            selected: None,
+        }
+    }
+}
+
+impl MemorySearch {
+    fn update(&mut self, memory: &[u8]) {
+        if !self.changed && self.memory == memory {
+            return;
+        }
+
+        self.changed = false;
+        self.memory = memory.to_vec();
+
+        let pattern = self.pattern();
+        self.invalid = pattern.is_none();
+
+        let pattern = pattern.unwrap_or_default();
+        self.len = pattern.len();
+        self.results = if pattern.is_empty() {
+            Vec::new()
+        } else {
+            memory
+                .windows(pattern.len())
+                .enumerate()
+                .filter(|(_, bytes)| {
+                    bytes
+                        .iter()
+                        .zip(&pattern)
+                        .all(|(byte, (value, mask))| byte & mask == *value)
+                })
+                .map(|(addr, _)| addr as C8Addr)
+                .collect()
+        };
+    }
+
+    /// Bytes to match as `(value, mask)` pairs, `None` if the query is malformed.
+    fn pattern(&self) -> Option<Vec<(u8, u8)>> {
+        let query = self.query.trim();
+        if query.is_empty() {
+            return Some(Vec::new());
+        }
+
+        match self.kind {
+            SearchKind::Bytes => {
+                let nibbles = query
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(|c| match c {
+                        '?' => Some((0, 0)),
+                        _ => c.to_digit(16).map(|digit| (digit as u8, 0xF)),
+                    })
+                    .collect::<Option<Vec<(u8, u8)>>>()?;
+
+                nibbles
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [(high, high_mask), (low, low_mask)] => {
+                            Some((high << 4 | low, high_mask << 4 | low_mask))
+                        }
+                        _ => None,
+                    })
+                    .collect()
+            }
+            SearchKind::Value => {
+                let value: u16 = query.parse().ok()?;
+                Some(match u8::try_from(value) {
+                    Ok(byte) => vec![(byte, 0xFF)],
+                    Err(_) => value.to_be_bytes().map(|byte| (byte, 0xFF)).to_vec(),
+                })
+            }
+        }
+    }
+
+    /// Is the address part of the selected result.
+    fn is_selected(&self, addr: C8Addr) -> bool {
+        self.selected.is_some_and(|start| {
+            let start = start as usize;
+            (start..start + self.len).contains(&(addr as usize))
+        })
+    }
+}
+
+/// Values shown in the registers tab, used to highlight what a step changed.
+#[derive(Clone)]
+struct RegisterSnapshot {
+    pc: u16,
+    sp: usize,
+    index: u16,
+    registers: [u8; 16],
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl RegisterSnapshot {
+    fn new(chip8: &Chip8) -> Self {
+        Self {
+            pc: chip8.pc,
+            sp: chip8.sp,
+            index: chip8.index,
+            registers: chip8.registers,
+            delay_timer: chip8.delay_timer,
+            sound_timer: chip8.sound_timer,
+        }
+    }
+}
+
+/// Action requested from a disassembly row.
+/// Applied after drawing since the state is read locked while drawing.
+enum RowAction {
+    RunTo(C8Addr),
+    ToggleBreakpoint(C8Addr),
 }
 
 pub struct DebuggerWindow {
     chip8: Arc<ExecutingChip8>,
     selected: DebuggerTab,
+    listing: Listing,
+    labels: Labels,
+    /// Address the disassembly should scroll to on the next frame.
+    scroll_to: Option<C8Addr>,
+    row_action: Option<RowAction>,
+    /// Registers before the last single step.
+    before_step: Option<RegisterSnapshot>,
+    /// Keep the current instruction centered in the disassembly.
+    follow_pc: bool,
+    /// Show the disassembly in Octo syntax instead of the mnemonics.
+    octo_syntax: bool,
+    /// Address typed in the memory viewer's go to box.
+    memory_goto: String,
+    /// Address the memory viewer should scroll to on the next frame.
+    memory_scroll_to: Option<C8Addr>,
+    search: MemorySearch,
+    /// Instructions run by the step N button.
+    step_count: usize,
+    /// PC after the last step N or run for.
+    stepped_to: Option<C8Addr>,
+    /// Milliseconds run by the run for button.
+    run_millis: u64,
+    /// Registers before the run for in progress, shown as the last step once it's over.
+    run_for_from: Option<RegisterSnapshot>,
+    /// Seed set by the reseed button.
+    seed: u64,
+    /// Recording memory writes for the writes panel.
+    record_writes: bool,
+    /// Show the waveform being played below the registers.
+    audio_scope: bool,
 }
 
 impl DebuggerWindow {
@@ -25,8 +314,31 @@ impl DebuggerWindow {
         Self {
             chip8,
             selected: DebuggerTab::Registers,
+            listing: Listing::default(),
+            labels: Labels::default(),
+            scroll_to: None,
+            row_action: None,
+            before_step: None,
+            follow_pc: true,
+            octo_syntax: storage::storage().get(OCTO_SYNTAX_KEY).unwrap_or_default(),
+            memory_goto: String::new(),
+            memory_scroll_to: None,
+            search: MemorySearch::default(),
+            step_count: 10,
+            stepped_to: None,
+            run_millis: 100,
+            run_for_from: None,
+            seed: 0,
+            record_writes: false,
+            audio_scope: storage::storage().get(AUDIO_SCOPE_KEY).unwrap_or_default(),
         }
     }
+
+    /// Switch to the disassembly scrolled to `pc`, e.g. where execution stopped.
+    pub fn show_pc(&mut self, pc: C8Addr) {
+        self.selected = DebuggerTab::Dissasembly;
+        self.scroll_to = Some(pc);
+    }
 }
 
 impl Window for DebuggerWindow {
@@ -59,17 +371,145 @@ impl Window for DebuggerWindow {
                         .clicked()
                     {
                         self.selected = DebuggerTab::Dissasembly;
+                        mq_ctx.set_window_size(1000, 540)
+                    }
+
+                    ui.separator();
+
+                    if ui
+                        .add(
+                            egui::Button::new("Memory")
+                                .fill(Color32::from_rgba_unmultiplied(0, 0, 0, 0)),
+                        )
+                        .clicked()
+                    {
+                        self.selected = DebuggerTab::Memory;
                         mq_ctx.set_window_size(850, 540)
                     }
 
                     ui.separator();
 
+                    if let DebuggerTab::Registers = self.selected {
+                        if ui
+                            .button("Copy state")
+                            .on_hover_text("Copy the machine state as JSON, e.g. for a bug report")
+                            .clicked()
+                        {
+                            let snapshot = self.chip8.snapshot();
+                            ui.output().copied_text = serde_json::to_string_pretty(&snapshot)
+                                .expect("Unable to serialize the machine state");
+                        }
+                    }
+
+                    if let DebuggerTab::Memory = self.selected {
+                        let goto = ui.add(
+                            egui::TextEdit::singleline(&mut self.memory_goto)
+                                .hint_text("Address")
+                                .desired_width(60.0),
+                        );
+
+                        if (goto.lost_focus() && ui.input().key_pressed(egui::Key::Enter))
+                            | ui.button("Go to").clicked()
+                        {
+                            if let Ok(addr) = C8Addr::from_str_radix(self.memory_goto.trim(), 16) {
+                                self.memory_scroll_to = Some(addr);
+                            }
+                        }
+
+                        if ui.button("Go to I").clicked() {
+                            self.memory_scroll_to = Some(self.chip8.read().unwrap().index);
+                        }
+                    }
+
+                    if let DebuggerTab::Dissasembly = self.selected {
+                        ui.checkbox(&mut self.follow_pc, "Follow PC");
+
+                        if ui
+                            .checkbox(&mut self.octo_syntax, "Octo syntax")
+                            .on_hover_text("Show instructions as Octo statements, e.g. v1 := 0x0a")
+                            .changed()
+                        {
+                            storage::storage().set(OCTO_SYNTAX_KEY, &self.octo_syntax);
+                        }
+
+                        if ui
+                            .add_enabled(!self.follow_pc, egui::Button::new("Go to PC"))
+                            .clicked()
+                        {
+                            self.scroll_to = Some(self.chip8.read().unwrap().pc);
+                        }
+
+                        ui.separator();
+
+                        if ui.button("Copy").clicked() {
+                            ui.output().copied_text =
+                                disassembler::rom_listing(&self.chip8.read().unwrap());
+                        }
+
+                        if ui.button("Save").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Assembly", &["asm"])
+                                .set_file_name("rom.asm")
+                                .save_file()
+                            {
+                                let listing =
+                                    disassembler::rom_listing(&self.chip8.read().unwrap());
+                                if let Err(err) = fs::write(&path, listing) {
+                                    log::error!(
+                                        "Unable to write disassembly {}: {}",
+                                        path.display(),
+                                        err
+                                    );
+                                }
+                            }
+                        }
+
+                        ui.separator();
+
+                        let mut coverage = self.chip8.read().unwrap().coverage_enabled();
+                        if ui
+                            .checkbox(&mut coverage, "Coverage")
+                            .on_hover_text("Record which instructions run, to tell code from data")
+                            .changed()
+                        {
+                            self.chip8.write().unwrap().set_coverage(coverage);
+                        }
+
+                        if ui
+                            .add_enabled(coverage, egui::Button::new("Save coverage"))
+                            .on_hover_text("Save the disassembly marking each line as code or data")
+                            .clicked()
+                        {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .add_filter("Assembly", &["asm"])
+                                .set_file_name("rom-coverage.asm")
+                                .save_file()
+                            {
+                                let listing =
+                                    disassembler::coverage_listing(&self.chip8.read().unwrap());
+                                if let Err(err) = fs::write(&path, listing) {
+                                    log::error!(
+                                        "Unable to write disassembly {}: {}",
+                                        path.display(),
+                                        err
+                                    );
+                                }
+                            }
+                        }
+                    }
+
                     ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                        let (_, next) = self.chip8.read().unwrap().peek_next_opcode();
                         if ui
                             .add_enabled(!self.chip8.is_running(), egui::Button::new("⮫"))
+                            .on_hover_text(format!("Step {}", next.get_opcode_str().0))
                             .clicked()
                         {
-                            self.chip8.write().unwrap().interpreter()
+                            let mut chip8 = self.chip8.write().unwrap();
+                            self.before_step = Some(RegisterSnapshot::new(&chip8));
+                            if let Err(err) = chip8.interpreter() {
+                                self.chip8.report_error(err);
+                            }
                         }
 
                         if ui
@@ -82,10 +522,234 @@ impl Window for DebuggerWindow {
                         {
                             self.chip8.set_running(!self.chip8.is_running())
                         }
+
+                        ui.add(
+                            egui::DragValue::new(&mut self.step_count)
+                                .clamp_range(1..=MAX_STEP_COUNT),
+                        );
+
+                        if ui
+                            .add_enabled(!self.chip8.is_running(), egui::Button::new("Step N"))
+                            .on_hover_text(
+                                "Run N instructions, stopping early at a breakpoint or halt",
+                            )
+                            .clicked()
+                        {
+                            let mut chip8 = self.chip8.write().unwrap();
+                            self.before_step = Some(RegisterSnapshot::new(&chip8));
+                            if let Err(err) = chip8.step(self.step_count) {
+                                self.chip8.report_error(err);
+                            }
+                            self.stepped_to = Some(chip8.pc);
+                        }
+
+                        ui.separator();
+
+                        ui.add(
+                            egui::DragValue::new(&mut self.run_millis)
+                                .clamp_range(1..=MAX_RUN_MILLIS)
+                                .suffix(" ms"),
+                        );
+
+                        if ui
+                            .add_enabled(!self.chip8.is_running(), egui::Button::new("Run for"))
+                            .on_hover_text(
+                                "Run at the current speed, stopping early at a breakpoint",
+                            )
+                            .clicked()
+                        {
+                            let before = RegisterSnapshot::new(&self.chip8.read().unwrap());
+                            self.run_for_from = Some(before);
+                            self.chip8.run_for(Duration::from_millis(self.run_millis));
+                        }
+
+                        ui.separator();
+
+                        ui.add(egui::DragValue::new(&mut self.seed));
+
+                        if ui
+                            .button("Reseed")
+                            .on_hover_text(
+                                "Restart RND from this seed, to repeat the same random numbers",
+                            )
+                            .clicked()
+                        {
+                            self.chip8.reseed(self.seed);
+                        }
+
+                        ui.separator();
+
+                        if let Some(pc) = self.stepped_to {
+                            ui.label(format!("PC {:04X}", pc));
+                        }
                     })
                 })
             });
 
+            // Highlights only make sense while single-stepping.
+            if self.chip8.is_running() {
+                self.before_step = None;
+                self.stepped_to = None;
+            } else if let Some(before) = self.run_for_from.take() {
+                // The run for ended, on its own or at a breakpoint.
+                self.before_step = Some(before);
+                self.stepped_to = Some(self.chip8.read().unwrap().pc);
+            }
+
+            if let DebuggerTab::Memory = self.selected {
+                self.search.update(&self.chip8.read().unwrap().memory);
+
+                egui::SidePanel::right("debug_memory_search").show(&egui_ctx, |ui| {
+                    ui.heading("Search");
+
+                    let search = &mut self.search;
+                    let query = ui.add(egui::TextEdit::singleline(&mut search.query).hint_text(
+                        match search.kind {
+                            SearchKind::Bytes => "Hex, e.g. 6?00",
+                            SearchKind::Value => "Decimal value",
+                        },
+                    ));
+
+                    ui.horizontal(|ui| {
+                        search.changed |= query.changed()
+                            | ui.radio_value(&mut search.kind, SearchKind::Bytes, "Bytes")
+                                .changed()
+                            | ui.radio_value(&mut search.kind, SearchKind::Value, "Value")
+                                .changed();
+                    });
+
+                    if search.invalid {
+                        ui.colored_label(ui.visuals().error_fg_color, "Invalid pattern");
+                    } else if !search.query.trim().is_empty() {
+                        ui.label(format!("{} matches", search.results.len()));
+                    }
+
+                    ui.separator();
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for addr in &search.results {
+                            if ui
+                                .selectable_label(
+                                    search.selected == Some(*addr),
+                                    RichText::new(format!("{:03X}", addr)).monospace(),
+                                )
+                                .clicked()
+                            {
+                                search.selected = Some(*addr);
+                                self.memory_scroll_to = Some(*addr);
+                            }
+                        }
+                    });
+                });
+
+                egui::SidePanel::right("debug_memory_writes").show(&egui_ctx, |ui| {
+                    ui.heading("Writes");
+
+                    ui.horizontal(|ui| {
+                        if ui
+                            .checkbox(&mut self.record_writes, "Record")
+                            .on_hover_text("Keep the most recent writes made by instructions")
+                            .changed()
+                        {
+                            self.chip8
+                                .write()
+                                .unwrap()
+                                .set_write_log(if self.record_writes {
+                                    WRITE_LOG_SIZE
+                                } else {
+                                    0
+                                });
+                        }
+
+                        if ui.button("Clear").clicked() {
+                            self.chip8.write().unwrap().clear_write_log();
+                        }
+                    });
+
+                    ui.separator();
+
+                    let chip8 = self.chip8.read().unwrap();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        // Newest first.
+                        for write in chip8.recent_writes().iter().rev() {
+                            if ui
+                                .selectable_label(
+                                    false,
+                                    RichText::new(format!(
+                                        "{:03X} {:02X}→{:02X}",
+                                        write.addr, write.old, write.new
+                                    ))
+                                    .monospace(),
+                                )
+                                .on_hover_text(format!("Cycle {}", write.cycle))
+                                .clicked()
+                            {
+                                self.memory_scroll_to = Some(write.addr);
+                            }
+                        }
+                    });
+                });
+            }
+
+            if let DebuggerTab::Dissasembly = self.selected {
+                {
+                    let chip8 = self.chip8.read().unwrap();
+                    self.listing.update(&chip8.memory);
+                    self.labels.update(chip8.rom_hash());
+                }
+
+                egui::SidePanel::right("debug_labels").show(&egui_ctx, |ui| {
+                    ui.heading("Labels");
+                    ui.add(egui::TextEdit::singleline(&mut self.labels.filter).hint_text("Filter"));
+                    ui.separator();
+
+                    let mut renamed = None;
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        let filter = self.labels.filter.to_lowercase();
+
+                        for addr in &self.listing.targets {
+                            let name = self.labels.name(*addr);
+
+                            match &mut self.labels.renaming {
+                                Some((renaming, new_name)) if renaming == addr => {
+                                    let edit = ui.text_edit_singleline(new_name);
+                                    edit.request_focus();
+
+                                    if edit.lost_focus() {
+                                        if ui.input().key_pressed(egui::Key::Enter) {
+                                            renamed = Some((*addr, new_name.clone()));
+                                        }
+
+                                        self.labels.renaming = None;
+                                    }
+                                }
+                                _ if !name.to_lowercase().contains(&filter) => {}
+                                _ => {
+                                    let label = ui
+                                        .selectable_label(
+                                            false,
+                                            RichText::new(format!("{:03X} {}", addr, name))
+                                                .monospace(),
+                                        )
+                                        .on_hover_text("Double click to rename");
+
+                                    if label.double_clicked() {
+                                        self.labels.renaming = Some((*addr, name));
+                                    } else if label.clicked() {
+                                        self.scroll_to = Some(*addr);
+                                        self.follow_pc = false;
+                                    }
+                                }
+                            }
+                        }
+                    });
+
+                    if let Some((addr, name)) = renamed {
+                        self.labels.rename(addr, &name);
+                    }
+                });
+            }
+
             egui::CentralPanel::default().show(&egui_ctx, |ui| {
                 let chip8 = self.chip8.read().unwrap();
 
@@ -98,32 +762,103 @@ impl Window for DebuggerWindow {
                                 .min_col_width(100.0)
                                 .striped(true)
                                 .show(ui, |ui| {
-                                    ui.heading("PC");
-                                    ui.monospace(format!("{:X}", chip8.pc));
-                                    ui.end_row();
+                                    let current = RegisterSnapshot::new(&chip8);
+                                    let before = self.before_step.as_ref().unwrap_or(&current);
+
+                                    let changed_color = theme_color(
+                                        ui,
+                                        Color32::YELLOW,
+                                        Color32::from_rgb(170, 110, 0),
+                                    );
+
+                                    let register_row =
+                                        |ui: &mut Ui,
+                                         name: String,
+                                         value: String,
+                                         changed: bool| {
+                                            ui.heading(name);
+                                            ui.monospace(if changed {
+                                                RichText::new(value).color(changed_color)
+                                            } else {
+                                                RichText::new(value)
+                                            });
+                                            ui.end_row();
+                                        };
+
+                                    register_row(
+                                        ui,
+                                        "PC".into(),
+                                        format!("{:X}", current.pc),
+                                        current.pc != before.pc,
+                                    );
 
                                     ui.heading("SP");
-                                    ui.monospace(format!("{:X}", chip8.sp));
+                                    let sp = RichText::new(format!("{:X}", current.sp));
+                                    ui.monospace(if chip8.stack_near_limit() {
+                                        sp.color(Color32::RED)
+                                    } else if current.sp != before.sp {
+                                        sp.color(changed_color)
+                                    } else {
+                                        sp
+                                    })
+                                    .on_hover_text(format!(
+                                        "Call depth {} of {}",
+                                        chip8.call_depth(),
+                                        MAX_CALL_DEPTH
+                                    ));
                                     ui.end_row();
 
-                                    ui.heading("I");
-                                    ui.monospace(format!("{:X}", chip8.index));
-                                    ui.end_row();
+                                    register_row(
+                                        ui,
+                                        "I".into(),
+                                        format!("{:X}", current.index),
+                                        current.index != before.index,
+                                    );
 
-                                    for v in 0..15 {
-                                        ui.heading(format!("V{}", v));
-                                        ui.monospace(format!("{:X}", chip8.registers[v]));
-                                        ui.end_row();
+                                    for v in 0..16 {
+                                        register_row(
+                                            ui,
+                                            format!("V{:X}", v),
+                                            format!("{:X}", current.registers[v]),
+                                            current.registers[v] != before.registers[v],
+                                        );
                                     }
 
-                                    ui.heading("DT");
-                                    ui.monospace(format!("{:X}", chip8.delay_timer));
-                                    ui.end_row();
+                                    register_row(
+                                        ui,
+                                        "DT".into(),
+                                        format!("{:X}", current.delay_timer),
+                                        current.delay_timer != before.delay_timer,
+                                    );
+
+                                    register_row(
+                                        ui,
+                                        "ST".into(),
+                                        format!("{:X}", current.sound_timer),
+                                        current.sound_timer != before.sound_timer,
+                                    );
 
-                                    ui.heading("ST");
-                                    ui.monospace(format!("{:X}", chip8.sound_timer));
+                                    ui.heading("Seed");
+                                    ui.monospace(match chip8.seed() {
+                                        Some(seed) => seed.to_string(),
+                                        None => "Random".into(),
+                                    });
                                     ui.end_row();
                                 });
+
+                            ui.separator();
+
+                            if ui
+                                .checkbox(&mut self.audio_scope, "Audio scope")
+                                .on_hover_text("Show the waveform played while ST is active")
+                                .changed()
+                            {
+                                storage::storage().set(AUDIO_SCOPE_KEY, &self.audio_scope);
+                            }
+
+                            if self.audio_scope {
+                                draw_scope(ui, chip8.sound_timer > 0);
+                            }
                         }
                         DebuggerTab::Dissasembly => {
                             egui::Grid::new("debug_dissasembly")
@@ -137,41 +872,210 @@ impl Window for DebuggerWindow {
                                     ui.heading("Description");
                                     ui.end_row();
 
-                                    let opcode_row = |ui: &mut Ui, idx, current| {
-                                        let value = chip8.memory[idx as usize];
+                                    let pc_color =
+                                        theme_color(ui, Color32::LIGHT_RED, Color32::DARK_RED);
+                                    let target_color =
+                                        theme_color(ui, Color32::LIGHT_BLUE, Color32::DARK_BLUE);
+                                    let address_color =
+                                        theme_color(ui, Color32::GRAY, Color32::DARK_GRAY);
 
-                                        let opcode_str = OpCode::from_opcode(
-                                            extract_opcode_from_array(&chip8.memory, idx as usize),
-                                        )
-                                        .get_opcode_str();
+                                    for line in &self.listing.lines {
+                                        let breakpoint = chip8.breakpoints.contains_key(&line.addr);
+                                        let is_target = self.listing.targets.contains(&line.addr);
+                                        let label = if is_target {
+                                            format!(" {}:", self.labels.name(line.addr))
+                                        } else {
+                                            String::new()
+                                        };
 
-                                        ui.monospace(RichText::new(format!("{:X}", idx)).color(
-                                            if current {
-                                                Color32::LIGHT_RED
-                                            } else {
-                                                Color32::GRAY
-                                            },
-                                        ));
+                                        let location = ui
+                                            .add(
+                                                egui::Label::new(
+                                                    RichText::new(format!(
+                                                        "{}{:X}{}",
+                                                        if breakpoint { "● " } else { "" },
+                                                        line.addr,
+                                                        label
+                                                    ))
+                                                    .monospace()
+                                                    .color(if line.addr == chip8.pc {
+                                                        pc_color
+                                                    } else if is_target {
+                                                        target_color
+                                                    } else {
+                                                        address_color
+                                                    }),
+                                                )
+                                                .sense(Sense::click()),
+                                            )
+                                            .context_menu(|ui| {
+                                                if ui.button("Run to here").clicked() {
+                                                    self.row_action =
+                                                        Some(RowAction::RunTo(line.addr));
+                                                    ui.close_menu();
+                                                }
 
-                                        ui.monospace(format!("{:X}", value));
-                                        ui.monospace(format!("{}", opcode_str.0));
-                                        ui.monospace(format!("{}", opcode_str.1));
-                                        ui.end_row();
-                                    };
+                                                if ui
+                                                    .button(if breakpoint {
+                                                        "Remove breakpoint"
+                                                    } else {
+                                                        "Add breakpoint"
+                                                    })
+                                                    .clicked()
+                                                {
+                                                    self.row_action = Some(
+                                                        RowAction::ToggleBreakpoint(line.addr),
+                                                    );
+                                                    ui.close_menu();
+                                                }
+                                            });
 
-                                    for i in chip8.pc - 11..chip8.pc {
-                                        opcode_row(ui, i, false);
+                                        if self.scroll_to == Some(line.addr) {
+                                            location.scroll_to_me(Some(Align::Center));
+                                            self.scroll_to = None;
+                                        } else if self.follow_pc && line.addr == chip8.pc {
+                                            location.scroll_to_me(Some(Align::Center));
+                                        }
+
+                                        ui.monospace(format!("{:04X}", line.word));
+
+                                        // JP0 can only be resolved with the live value of V0.
+                                        let target = match line.opcode {
+                                            OpCode::JP0(addr) => {
+                                                Some(addr + chip8.registers[0] as C8Addr)
+                                            }
+                                            _ => line.opcode.branch_target(line.addr),
+                                        };
+
+                                        let mnemonic = if self.octo_syntax {
+                                            line.opcode.to_octo()
+                                        } else {
+                                            line.mnemonic.clone()
+                                        };
+
+                                        match target {
+                                            Some(target) => {
+                                                if ui
+                                                    .link(RichText::new(&mnemonic).monospace())
+                                                    .on_hover_text(format!(
+                                                        "Go to {}",
+                                                        self.labels.name(target)
+                                                    ))
+                                                    .clicked()
+                                                {
+                                                    self.scroll_to = Some(target);
+                                                    self.follow_pc = false;
+                                                }
+
+                                                ui.monospace(format!(
+                                                    "{} (-> {})",
+                                                    line.verbose,
+                                                    self.labels.name(target)
+                                                ));
+                                            }
+                                            None => {
+                                                ui.monospace(&mnemonic);
+                                                ui.monospace(&line.verbose);
+                                            }
+                                        }
+
+                                        ui.end_row();
                                     }
+                                });
+                        }
+                        DebuggerTab::Memory => {
+                            egui::Grid::new("debug_memory")
+                                .num_columns(MEMORY_ROW + 2)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    let pc_color =
+                                        theme_color(ui, Color32::LIGHT_RED, Color32::DARK_RED);
+                                    let index_color =
+                                        theme_color(ui, Color32::LIGHT_GREEN, Color32::DARK_GREEN);
+                                    let match_color = theme_color(
+                                        ui,
+                                        Color32::YELLOW,
+                                        Color32::from_rgb(170, 110, 0),
+                                    );
+                                    let address_color =
+                                        theme_color(ui, Color32::GRAY, Color32::DARK_GRAY);
+
+                                    for (row, bytes) in chip8.memory.chunks(MEMORY_ROW).enumerate()
+                                    {
+                                        let start = (row * MEMORY_ROW) as C8Addr;
+
+                                        let region = MEMORY_MAP
+                                            .iter()
+                                            .find(|(_, range)| range.contains(&(start as usize)));
+
+                                        let mut location = ui.monospace(
+                                            RichText::new(format!("{:03X}", start))
+                                                .color(address_color),
+                                        );
+
+                                        if let Some((name, _)) = region {
+                                            location = location.on_hover_text(*name);
+                                        }
 
-                                    opcode_row(ui, chip8.pc, true);
+                                        if let Some(addr) = self.memory_scroll_to {
+                                            let row = start as usize..start as usize + MEMORY_ROW;
+                                            if row.contains(&(addr as usize)) {
+                                                location.scroll_to_me(Some(Align::Center));
+                                                self.memory_scroll_to = None;
+                                            }
+                                        }
 
-                                    for i in chip8.pc..(chip8.pc + 10) {
-                                        opcode_row(ui, i, false);
+                                        for (offset, byte) in bytes.iter().enumerate() {
+                                            let addr = start + offset as C8Addr;
+                                            let text = RichText::new(format!("{:02X}", byte));
+
+                                            let indexed = addr >= chip8.index
+                                                && ((addr - chip8.index) as usize) < INDEX_SPAN;
+
+                                            ui.monospace(if self.search.is_selected(addr) {
+                                                text.color(match_color)
+                                            } else if addr == chip8.pc {
+                                                text.color(pc_color)
+                                            } else if addr == chip8.index {
+                                                text.color(index_color).strong()
+                                            } else if indexed {
+                                                text.color(index_color)
+                                            } else {
+                                                text
+                                            });
+                                        }
+
+                                        // Name regions on the row they start.
+                                        match region {
+                                            Some((name, range))
+                                                if range.start / MEMORY_ROW == row =>
+                                            {
+                                                ui.label(RichText::new(*name).color(address_color))
+                                            }
+                                            _ => ui.label(""),
+                                        };
+
+                                        ui.end_row();
                                     }
                                 });
                         }
                     });
             });
+
+            if let Some(action) = self.row_action.take() {
+                match action {
+                    RowAction::RunTo(addr) => {
+                        self.chip8.write().unwrap().set_temporary_breakpoint(addr);
+                        self.chip8.set_running(true);
+                    }
+                    RowAction::ToggleBreakpoint(addr) => {
+                        let mut chip8 = self.chip8.write().unwrap();
+                        if chip8.remove_breakpoint(addr).is_none() {
+                            chip8.set_breakpoint(addr, Condition::Always);
+                        }
+                    }
+                }
+            }
         });
 
         egui_ctx.draw(ctx);
@@ -192,3 +1096,45 @@ impl Window for DebuggerWindow {
         }
     }
 }
+
+/// Pick the highlight color readable on the current theme's background.
+/// Draw the samples last played as a line, flat while nothing is playing.
+fn draw_scope(ui: &mut Ui, playing: bool) {
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), SCOPE_HEIGHT),
+        Sense::hover(),
+    );
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let samples = if playing { audio::scope() } else { Vec::new() };
+    let stroke = egui::Stroke::new(1.0, ui.visuals().text_color());
+
+    if samples.len() < 2 {
+        painter.hline(rect.x_range(), rect.center().y, stroke);
+        return;
+    }
+
+    let step = rect.width() / (samples.len() - 1) as f32;
+    let points = samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            egui::pos2(
+                rect.left() + i as f32 * step,
+                rect.center().y - sample.clamp(-1.0, 1.0) * rect.height() / 2.0,
+            )
+        })
+        .collect();
+
+    painter.add(egui::Shape::line(points, stroke));
+}
+
+fn theme_color(ui: &Ui, dark: Color32, light: Color32) -> Color32 {
+    if ui.visuals().dark_mode {
+        dark
+    } else {
+        light
+    }
+}
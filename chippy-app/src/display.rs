@@ -0,0 +1,113 @@
+use egui::TextureId;
+use miniquad as mq;
+use mq::{Texture, TextureParams};
+
+/// Presents emulated frames, so the app doesn't depend on how they end up on screen.
+pub trait DisplayBackend: Send + Sync {
+    /// Show a `width` by `height` frame of RGBA pixels, replacing the last one.
+    fn present(&mut self, rgba: &[u8], width: u32, height: u32);
+
+    /// Texture egui draws the last frame with, `None` if nothing can be drawn.
+    /// `ctx` lets backends upload frames to the GPU right before they are drawn.
+    fn texture(&mut self, _ctx: &mut mq::Context) -> Option<TextureId> {
+        None
+    }
+}
+
+/// Presents frames through a miniquad texture, uploaded when the frame is next drawn.
+#[derive(Default)]
+pub struct MiniquadDisplay {
+    texture: Option<Texture>,
+    // Last frame presented and its dimensions.
+    rgba: Vec<u8>,
+    size: (u32, u32),
+    // Frame changed since it was uploaded.
+    pending: bool,
+}
+
+impl DisplayBackend for MiniquadDisplay {
+    fn present(&mut self, rgba: &[u8], width: u32, height: u32) {
+        self.rgba.clear();
+        self.rgba.extend_from_slice(rgba);
+        self.size = (width, height);
+        self.pending = true;
+    }
+
+    fn texture(&mut self, ctx: &mut mq::Context) -> Option<TextureId> {
+        if std::mem::take(&mut self.pending) {
+            match self.texture {
+                Some(texture) if (texture.width, texture.height) == self.size => {
+                    texture.update(ctx, &self.rgba);
+                }
+                _ => {
+                    if let Some(texture) = self.texture.take() {
+                        texture.delete();
+                    }
+
+                    self.texture = Some(screen_texture(ctx, &self.rgba, self.size));
+                }
+            }
+        }
+
+        self.texture
+            .map(|texture| TextureId::User(texture.gl_internal_id() as u64))
+    }
+}
+
+/// Texture the screen is drawn from, sized to the screen's dimensions.
+fn screen_texture(ctx: &mut mq::Context, rgba: &[u8], (width, height): (u32, u32)) -> Texture {
+    Texture::from_data_and_format(
+        ctx,
+        rgba,
+        TextureParams {
+            format: mq::TextureFormat::RGBA8,
+            wrap: mq::TextureWrap::Clamp,
+            filter: mq::FilterMode::Nearest,
+            width,
+            height,
+        },
+    )
+}
+
+/// Only keeps the last frame presented, for checking frames without a window.
+#[cfg(test)]
+#[derive(Default)]
+pub struct HeadlessDisplay {
+    pub frame: Option<(Vec<u8>, u32, u32)>,
+}
+
+#[cfg(test)]
+impl DisplayBackend for HeadlessDisplay {
+    fn present(&mut self, rgba: &[u8], width: u32, height: u32) {
+        self.frame = Some((rgba.to_vec(), width, height));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chippy_core::Chip8;
+
+    use super::*;
+
+    const WHITE: [u8; 4] = [255; 4];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    #[test]
+    fn headless_keeps_last_frame() {
+        let mut chip8 = Chip8::new();
+        let mut display = HeadlessDisplay::default();
+
+        let (rgba, width, height) = chip8.render_to_rgba(WHITE, BLACK, 1);
+        display.present(&rgba, width, height);
+
+        chip8.screen[0][0] = true;
+        let (rgba, width, height) = chip8.render_to_rgba(WHITE, BLACK, 1);
+        display.present(&rgba, width, height);
+
+        let (frame, width, height) = display.frame.unwrap();
+        assert_eq!((width, height), (64, 32));
+        assert_eq!(frame.len(), 64 * 32 * 4);
+        assert_eq!(frame[..4], WHITE);
+        assert_eq!(frame[4..8], BLACK);
+    }
+}
@@ -0,0 +1,65 @@
+//! Downloading ROMs from the web, e.g. raw GitHub links.
+
+use std::{
+    io::{self, Read},
+    time::Duration,
+};
+
+use chippy_core::{MemorySize, PROGRAM_ADDR};
+
+/// Largest ROM which fits in extended memory.
+const MAX_ROM_SIZE: u64 = (MemorySize::Extended.bytes() - PROGRAM_ADDR) as u64;
+
+/// Give up on servers which stop responding.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Download a ROM, following redirects. Responses other than 2xx are errors.
+pub fn fetch_rom(url: &str) -> io::Result<Vec<u8>> {
+    let too_large = |size| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "ROM is {} bytes, at most {} fit in memory",
+                size, MAX_ROM_SIZE
+            ),
+        )
+    };
+
+    let response = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .and_then(|client| client.get(url).send())
+        .and_then(|response| response.error_for_status())
+        .map_err(io::Error::other)?;
+
+    if let Some(length) = response
+        .content_length()
+        .filter(|length| *length > MAX_ROM_SIZE)
+    {
+        return Err(too_large(length));
+    }
+
+    // The length header is optional, so don't read much past the limit either way.
+    let mut rom = Vec::new();
+    response.take(MAX_ROM_SIZE + 1).read_to_end(&mut rom)?;
+
+    if rom.len() as u64 > MAX_ROM_SIZE {
+        return Err(too_large(rom.len() as u64));
+    }
+
+    Ok(rom)
+}
+
+/// Name shown for a ROM downloaded from `url`, the last part of its path.
+pub fn rom_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path);
+
+    file.rsplit_once('.')
+        .map_or(file, |(stem, _)| stem)
+        .to_string()
+}
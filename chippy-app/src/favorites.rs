@@ -0,0 +1,69 @@
+//! ROMs starred by the user, recognized by hash so they survive being moved.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Favorite {
+    pub name: String,
+    /// Where the ROM was last seen.
+    pub path: PathBuf,
+    /// `rom::hash` of the ROM.
+    pub hash: u64,
+}
+
+impl Favorite {
+    /// Can the ROM still be loaded from its path.
+    pub fn available(&self) -> bool {
+        self.path.is_file()
+    }
+}
+
+/// Favorite ROMs in the order they were added, at most one per hash.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Favorites {
+    entries: Vec<Favorite>,
+}
+
+impl Favorites {
+    pub fn id() -> egui::Id {
+        egui::Id::new("favorites")
+    }
+
+    pub fn entries(&self) -> &[Favorite] {
+        &self.entries
+    }
+
+    pub fn contains(&self, hash: u64) -> bool {
+        self.entries.iter().any(|favorite| favorite.hash == hash)
+    }
+
+    /// Add a ROM, or update where it's found if it's already a favorite.
+    pub fn add(&mut self, path: &Path, hash: u64) {
+        let name = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+
+        match self
+            .entries
+            .iter_mut()
+            .find(|favorite| favorite.hash == hash)
+        {
+            Some(favorite) => {
+                favorite.name = name;
+                favorite.path = path.to_owned();
+            }
+            None => self.entries.push(Favorite {
+                name,
+                path: path.to_owned(),
+                hash,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, hash: u64) {
+        self.entries.retain(|favorite| favorite.hash != hash);
+    }
+}
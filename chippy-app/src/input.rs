@@ -1,41 +1,122 @@
+use std::collections::HashMap;
+
 use chippy_core::Keypad;
-use miniquad::KeyCode;
+use egui::{Pos2, Rect, RichText, Ui};
+use miniquad::{KeyCode, TouchPhase};
+use serde::{Deserialize, Serialize};
+
+/// Keys of the COSMAC VIP keypad, row by row.
+const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Side of an on-screen keypad button in points, large enough to hit with a finger.
+const BUTTON_SIZE: f32 = 48.0;
 
 pub(crate) trait InputHandler {
     /// Returns whether the key event was caught or not.
-    fn key_event(&mut self, event: KeyEvent, keycode: KeyCode) -> bool;
+    fn key_event(&mut self, layout: KeyLayout, event: KeyEvent, keycode: KeyCode) -> bool;
 }
 
 impl InputHandler for Keypad {
     /// Returns whether the key event was caught or not.
-    fn key_event(&mut self, event: KeyEvent, keycode: KeyCode) -> bool {
-        let key = match keycode {
-            KeyCode::Key1 => 0x1,
-            KeyCode::Key2 => 0x2,
-            KeyCode::Key3 => 0x3,
-            KeyCode::Key4 => 0xC,
-            KeyCode::Q => 0x4,
-            KeyCode::W => 0x5,
-            KeyCode::E => 0x6,
-            KeyCode::R => 0xD,
-            KeyCode::A => 0x7,
-            KeyCode::S => 0x8,
-            KeyCode::D => 0x9,
-            KeyCode::F => 0xE,
-            KeyCode::Z => 0xA,
-            KeyCode::X => 0x0,
-            KeyCode::C => 0xB,
-            KeyCode::V => 0xF,
-            _ => return false,
+    fn key_event(&mut self, layout: KeyLayout, event: KeyEvent, keycode: KeyCode) -> bool {
+        let Some(key) = layout.key(keycode) else {
+            return false;
         };
 
-        self.keys[key] = bool::from(event);
-        self.last_pressed = key as u8;
+        self.set(key, bool::from(event));
 
         true
     }
 }
 
+/// Which keyboard keys press which CHIP-8 keys.
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum KeyLayout {
+    /// The left side of the keyboard laid out like the COSMAC VIP keypad,
+    /// `1234`, `QWER`, `ASDF` and `ZXCV` row by row.
+    #[default]
+    Cosmac,
+    /// Arrow keys and WASD press 2, 4, 6 and 8, which most games move with.
+    /// Space presses 5, the usual fire button, and digits press their own key.
+    Positional,
+}
+
+impl KeyLayout {
+    /// CHIP-8 key pressed by a keyboard key, if any.
+    pub fn key(self, keycode: KeyCode) -> Option<u8> {
+        let key = match self {
+            Self::Cosmac => match keycode {
+                KeyCode::Key1 => 0x1,
+                KeyCode::Key2 => 0x2,
+                KeyCode::Key3 => 0x3,
+                KeyCode::Key4 => 0xC,
+                KeyCode::Q => 0x4,
+                KeyCode::W => 0x5,
+                KeyCode::E => 0x6,
+                KeyCode::R => 0xD,
+                KeyCode::A => 0x7,
+                KeyCode::S => 0x8,
+                KeyCode::D => 0x9,
+                KeyCode::F => 0xE,
+                KeyCode::Z => 0xA,
+                KeyCode::X => 0x0,
+                KeyCode::C => 0xB,
+                KeyCode::V => 0xF,
+                _ => return None,
+            },
+            Self::Positional => match keycode {
+                KeyCode::Up | KeyCode::W => 0x2,
+                KeyCode::Left | KeyCode::A => 0x4,
+                KeyCode::Right | KeyCode::D => 0x6,
+                KeyCode::Down | KeyCode::S => 0x8,
+                KeyCode::Space => 0x5,
+                KeyCode::Key0 => 0x0,
+                KeyCode::Key1 => 0x1,
+                KeyCode::Key2 => 0x2,
+                KeyCode::Key3 => 0x3,
+                KeyCode::Key4 => 0x4,
+                KeyCode::Key5 => 0x5,
+                KeyCode::Key6 => 0x6,
+                KeyCode::Key7 => 0x7,
+                KeyCode::Key8 => 0x8,
+                KeyCode::Key9 => 0x9,
+                _ => return None,
+            },
+        };
+
+        Some(key)
+    }
+}
+
+/// What an OS key repeat does to the held key.
+///
+/// Games read held keys with `SKP`, `SKNP` and `Fx0A`, so holding a key already
+/// keeps reporting it and repeats aren't needed for those to see it.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum KeyRepeat {
+    /// Keep the key held.
+    #[default]
+    Ignore,
+    /// Release the key until the next frame, then press it again as if it was tapped.
+    /// Helps games which wait for a key to be released before accepting it again.
+    Pulse,
+}
+
+impl KeyRepeat {
+    /// Event to apply for a repeat, the key is pressed again next frame if it's a `KeyUp`.
+    pub fn translate(self) -> Option<KeyEvent> {
+        match self {
+            Self::Ignore => None,
+            Self::Pulse => Some(KeyEvent::KeyUp),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum KeyEvent {
     KeyUp,
@@ -50,3 +131,148 @@ impl From<KeyEvent> for bool {
         }
     }
 }
+
+/// On-screen keypad, held with the mouse or any amount of fingers at once.
+///
+/// Touches are handled before egui sees them so several keys can be held,
+/// egui only knows a single pointer.
+#[derive(Default)]
+pub struct VirtualKeypad {
+    // Where each key's button was last drawn, in points.
+    buttons: Vec<(u8, Rect)>,
+    // Key held by each touch point.
+    touches: HashMap<u64, u8>,
+    // Key held with the mouse.
+    mouse_key: Option<u8>,
+}
+
+impl VirtualKeypad {
+    /// Draw the keypad, pressing the key held down with the mouse.
+    pub fn draw(&mut self, ui: &mut Ui, keypad: &mut Keypad) {
+        self.buttons.clear();
+        let mut mouse_key = None;
+
+        egui::Grid::new("virtual_keypad").show(ui, |ui| {
+            for row in KEYPAD_LAYOUT {
+                for key in row {
+                    let mut button = egui::Button::new(
+                        RichText::new(format!("{:X}", key)).monospace().size(20.0),
+                    )
+                    .min_size(egui::vec2(BUTTON_SIZE, BUTTON_SIZE));
+                    if keypad.keys[key as usize] {
+                        button = button.fill(ui.visuals().selection.bg_fill);
+                    }
+
+                    let response = ui.add(button);
+                    if response.is_pointer_button_down_on() {
+                        mouse_key = Some(key);
+                    }
+
+                    self.buttons.push((key, response.rect));
+                }
+
+                ui.end_row();
+            }
+        });
+
+        if mouse_key != self.mouse_key {
+            if let Some(key) = self.mouse_key.take() {
+                self.release(key, keypad);
+            }
+
+            if let Some(key) = mouse_key {
+                keypad.set(key, true);
+            }
+
+            self.mouse_key = mouse_key;
+        }
+    }
+
+    /// Press and release keys for a touch point at `pos`, in points.
+    /// A finger sliding to another key switches to it, lifting it anywhere releases its key.
+    ///
+    /// Returns whether the touch was on the keypad, otherwise it should go to egui.
+    pub fn touch(&mut self, phase: TouchPhase, id: u64, pos: Pos2, keypad: &mut Keypad) -> bool {
+        let held = self.touches.remove(&id);
+        let key = match phase {
+            TouchPhase::Started => self.key_at(pos),
+            // Only touches which started on the keypad are followed.
+            TouchPhase::Moved if held.is_some() => self.key_at(pos),
+            _ => None,
+        };
+
+        if let Some(key) = key {
+            self.touches.insert(id, key);
+            keypad.set(key, true);
+        }
+
+        if let Some(old) = held.filter(|old| Some(*old) != key) {
+            self.release(old, keypad);
+        }
+
+        held.is_some() || key.is_some()
+    }
+
+    /// Forget held keys, e.g. when the keypad was hidden or key up events can no longer arrive.
+    pub fn reset(&mut self) {
+        self.touches.clear();
+        self.mouse_key = None;
+    }
+
+    fn key_at(&self, pos: Pos2) -> Option<u8> {
+        self.buttons
+            .iter()
+            .find(|(_, rect)| rect.contains(pos))
+            .map(|(key, _)| *key)
+    }
+
+    /// Release a key unless another finger or the mouse still holds it.
+    fn release(&self, key: u8, keypad: &mut Keypad) {
+        if self.mouse_key != Some(key) && !self.touches.values().any(|held| *held == key) {
+            keypad.set(key, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positional_arrows_press_directions() {
+        let mut keypad = Keypad::default();
+
+        let arrows = [
+            (KeyCode::Up, 0x2),
+            (KeyCode::Left, 0x4),
+            (KeyCode::Right, 0x6),
+            (KeyCode::Down, 0x8),
+        ];
+
+        for (keycode, key) in arrows {
+            assert!(keypad.key_event(KeyLayout::Positional, KeyEvent::KeyDown, keycode));
+            assert!(keypad.keys[key]);
+
+            keypad.key_event(KeyLayout::Positional, KeyEvent::KeyUp, keycode);
+            assert!(!keypad.keys[key]);
+        }
+
+        assert!(!keypad.key_event(KeyLayout::Cosmac, KeyEvent::KeyDown, KeyCode::Up));
+        assert_eq!(keypad.keys, [false; 16]);
+    }
+
+    #[test]
+    fn repeats_hold_or_pulse_the_key() {
+        let mut keypad = Keypad::default();
+        keypad.key_event(KeyLayout::Cosmac, KeyEvent::KeyDown, KeyCode::W);
+
+        // Ignoring leaves the key held.
+        assert!(KeyRepeat::Ignore.translate().is_none());
+        assert!(keypad.keys[0x5]);
+
+        let event = KeyRepeat::Pulse.translate().unwrap();
+        assert!(matches!(event, KeyEvent::KeyUp));
+        keypad.key_event(KeyLayout::Cosmac, event, KeyCode::W);
+        assert!(!keypad.keys[0x5]);
+    }
+}
@@ -1,14 +1,24 @@
-#![feature(slice_flatten)]
-
 use app::MainApp;
 
 use window::WindowContainer;
 
 mod app;
+mod audio;
+mod browser;
+mod console;
 mod debugger;
+mod display;
+#[cfg(feature = "url-rom")]
+mod download;
+mod favorites;
 mod input;
+#[cfg(feature = "remote-debug")]
+mod remote;
+mod storage;
+mod watcher;
 mod window;
 
 fn main() {
+    console::init();
     WindowContainer::new(MainApp::new()).open().join().unwrap();
 }
@@ -0,0 +1,389 @@
+//! Debug server speaking a minimal subset of the GDB remote serial protocol.
+//!
+//! Enabled with the `remote-debug` feature and started with `--gdb-port <port>`.
+//! Only one client is served at a time, execution is paused while a client is connected.
+//!
+//! Supported packets:
+//!
+//! * `?` - Reason execution stopped.
+//! * `g` - Read registers: V0-VF, I and PC (little endian), SP, DT, ST.
+//! * `m addr,length` - Read memory.
+//! * `M addr,length:bytes` - Write memory.
+//! * `Z0,addr,kind` / `z0,addr,kind` - Add/remove a breakpoint.
+//! * `s` - Step a single instruction.
+//! * `c` - Continue until a breakpoint is hit or the client interrupts (0x03).
+//!
+//! Anything else gets an empty reply, meaning unsupported.
+
+use std::{
+    env,
+    fmt::Write as _,
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use chippy_core::{breakpoint::Condition, types::C8Addr, ExecutingChip8};
+
+/// Reply to report execution stopped (SIGTRAP).
+const STOPPED: &str = "S05";
+/// Reply to report a malformed or out of range request.
+const ERROR: &str = "E01";
+
+/// Port passed with `--gdb-port`, if any.
+pub fn port_from_args() -> Option<u16> {
+    let mut args = env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--gdb-port" {
+            return args.next().and_then(|port| port.parse().ok());
+        }
+    }
+
+    None
+}
+
+/// Listen for debug clients on localhost on a separate thread.
+pub fn start(chip8: Arc<ExecutingChip8>, port: u16) {
+    thread::spawn(move || {
+        let listener = match TcpListener::bind(("127.0.0.1", port)) {
+            Ok(listener) => listener,
+            Err(err) => {
+                log::error!("Unable to start debug server: {}", err);
+                return;
+            }
+        };
+
+        log::info!("Debug server listening on port {}", port);
+
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| Session::new(&chip8, stream).run());
+            if let Err(err) = result {
+                log::info!("Debug client disconnected: {}", err);
+            }
+        }
+    });
+}
+
+struct Session<'a> {
+    chip8: &'a ExecutingChip8,
+    stream: TcpStream,
+}
+
+impl<'a> Session<'a> {
+    fn new(chip8: &'a ExecutingChip8, stream: TcpStream) -> Self {
+        Self { chip8, stream }
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        // Clients expect a stopped target when attaching.
+        self.chip8.set_running(false);
+
+        while let Some(packet) = self.read_packet()? {
+            let reply = self.handle(&packet)?;
+            self.write_packet(&reply)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read and acknowledge the next packet, `None` once the client disconnects.
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            // Skip acknowledgements and stray interrupts until a packet starts.
+            match self.read_byte()? {
+                Some(b'$') => {}
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    Some(b'#') => break,
+                    Some(byte) => data.push(byte),
+                    None => return Ok(None),
+                }
+            }
+
+            let mut checksum = [0; 2];
+            self.stream.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|checksum| u8::from_str_radix(checksum, 16).ok())
+                == Some(self::checksum(&data));
+
+            if valid {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(String::from_utf8_lossy(&data).into_owned()));
+            }
+
+            // Ask for a retransmission.
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        Ok(match self.stream.read(&mut byte)? {
+            0 => None,
+            _ => Some(byte[0]),
+        })
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        write!(self.stream, "${}#{:02x}", data, checksum(data.as_bytes()))
+    }
+
+    fn handle(&mut self, packet: &str) -> io::Result<String> {
+        let mut chars = packet.chars();
+        let command = chars.next();
+        let args = chars.as_str();
+
+        Ok(match command {
+            Some('?') => STOPPED.into(),
+            Some('g') => self.read_registers(),
+            Some('m') => self.read_memory(args).unwrap_or_else(|| ERROR.into()),
+            Some('M') => self.write_memory(args).unwrap_or_else(|| ERROR.into()),
+            Some('Z') => self.breakpoint(args, true).unwrap_or_default(),
+            Some('z') => self.breakpoint(args, false).unwrap_or_default(),
+            Some('s') => {
+                if let Err(err) = self.chip8.write().unwrap().interpreter() {
+                    self.chip8.report_error(err);
+                }
+                STOPPED.into()
+            }
+            Some('c') => {
+                self.resume()?;
+                STOPPED.into()
+            }
+            _ => String::new(),
+        })
+    }
+
+    fn read_registers(&self) -> String {
+        let chip8 = self.chip8.read().unwrap();
+
+        let mut bytes = chip8.registers.to_vec();
+        bytes.extend(chip8.index.to_le_bytes());
+        bytes.extend(chip8.pc.to_le_bytes());
+        bytes.extend([chip8.sp as u8, chip8.delay_timer, chip8.sound_timer]);
+
+        encode_hex(&bytes)
+    }
+
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let (addr, length) = parse_range(args)?;
+        let end = addr.checked_add(length)?;
+        let chip8 = self.chip8.read().unwrap();
+
+        Some(encode_hex(chip8.memory.get(addr..end)?))
+    }
+
+    fn write_memory(&self, args: &str) -> Option<String> {
+        let (range, data) = args.split_once(':')?;
+        let (addr, length) = parse_range(range)?;
+        let data = decode_hex(data)?;
+
+        if data.len() != length {
+            return None;
+        }
+
+        let mut chip8 = self.chip8.write().unwrap();
+        if addr.checked_add(length)? > chip8.memory.len() {
+            return None;
+        }
+
+        // Debuggers patch code, so decoded instructions have to go.
+        chip8.memory_mut(addr, length).copy_from_slice(&data);
+
+        Some("OK".into())
+    }
+
+    /// Only software breakpoints (type 0) are supported.
+    fn breakpoint(&self, args: &str, insert: bool) -> Option<String> {
+        let mut parts = args.split(',');
+        if parts.next()? != "0" {
+            return None;
+        }
+
+        let addr = C8Addr::from_str_radix(parts.next()?, 16).ok()?;
+
+        let mut chip8 = self.chip8.write().unwrap();
+        if insert {
+            chip8.set_breakpoint(addr, Condition::Always);
+        } else {
+            chip8.remove_breakpoint(addr);
+        }
+
+        Some("OK".into())
+    }
+
+    /// Run until a breakpoint stops execution or the client sends an interrupt.
+    fn resume(&mut self) -> io::Result<()> {
+        self.chip8.set_running(true);
+        self.stream.set_nonblocking(true)?;
+
+        let result = loop {
+            if !self.chip8.is_running() {
+                break Ok(());
+            }
+
+            let mut byte = [0];
+            match self.stream.read(&mut byte) {
+                // Disconnects are picked up by the next read.
+                Ok(0) => break Ok(()),
+                Ok(_) if byte[0] == 0x03 => break Ok(()),
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                Err(err) => break Err(err),
+            }
+        };
+
+        self.chip8.set_running(false);
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+/// Parse `addr,length` in hex.
+fn parse_range(args: &str) -> Option<(usize, usize)> {
+    let (addr, length) = args.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => u8::from_str_radix(std::str::from_utf8(&[*high, *low]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `LD V0, 2A`, `ADD V0, 01` then `JP 0202` back to the addition.
+    const ROM: [u8; 6] = [0x60, 0x2A, 0x70, 0x01, 0x12, 0x02];
+
+    /// Client of a session served on another thread.
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn connect(chip8: Arc<ExecutingChip8>) -> Self {
+            let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let _ = Session::new(&chip8, stream).run();
+            });
+
+            let stream = TcpStream::connect(addr).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+
+            Self { stream }
+        }
+
+        /// Send a packet and return the reply.
+        fn send(&mut self, packet: &str) -> String {
+            write!(
+                self.stream,
+                "${}#{:02x}",
+                packet,
+                checksum(packet.as_bytes())
+            )
+            .unwrap();
+
+            // Skip the acknowledgement up to the start of the reply.
+            while self.read_byte() != b'$' {}
+
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte() {
+                    b'#' => break,
+                    byte => data.push(byte),
+                }
+            }
+
+            let sum = [self.read_byte(), self.read_byte()];
+            assert_eq!(sum, format!("{:02x}", checksum(&data)).as_bytes());
+
+            String::from_utf8(data).unwrap()
+        }
+
+        fn read_byte(&mut self) -> u8 {
+            let mut byte = [0];
+            self.stream.read_exact(&mut byte).unwrap();
+            byte[0]
+        }
+    }
+
+    /// Reply to `g` with the given V0 and PC, and everything else zero.
+    fn registers(v0: u8, pc: u16) -> String {
+        let mut bytes = vec![0; 16];
+        bytes[0] = v0;
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(pc.to_le_bytes());
+        bytes.extend([0, 0, 0]);
+
+        encode_hex(&bytes)
+    }
+
+    #[test]
+    fn client_steps_patches_and_continues() {
+        let chip8 = Arc::new(ExecutingChip8::new());
+        chip8.load_rom(ROM.to_vec());
+        let mut client = Client::connect(chip8);
+
+        assert_eq!(client.send("g"), registers(0, 0x200));
+        assert_eq!(client.send("m200,4"), "602a7001");
+
+        assert_eq!(client.send("s"), STOPPED);
+        assert_eq!(client.send("g"), registers(0x2A, 0x202));
+
+        // `ADD V0, 02` instead.
+        assert_eq!(client.send("M203,1:02"), "OK");
+        assert_eq!(client.send("m202,2"), "7002");
+
+        assert_eq!(client.send("Z0,204,2"), "OK");
+        assert_eq!(client.send("c"), STOPPED);
+        assert_eq!(client.send("g"), registers(0x2C, 0x204));
+    }
+
+    #[test]
+    fn ranges_past_memory_are_refused() {
+        let mut client = Client::connect(Arc::new(ExecutingChip8::new()));
+        let max = format!("{:x}", usize::MAX);
+
+        assert_eq!(client.send(&format!("m{},2", max)), ERROR);
+        assert_eq!(client.send(&format!("M{},1:00", max)), ERROR);
+        assert_eq!(client.send("mfff,2"), ERROR);
+        assert_eq!(client.send("Mfff,2:0000"), ERROR);
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+};
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+
+static STORAGE: Lazy<Mutex<Storage>> = Lazy::new(|| Mutex::new(Storage::load()));
+
+/// Storage shared by every window.
+pub fn storage() -> MutexGuard<'static, Storage> {
+    STORAGE.lock().unwrap()
+}
+
+/// Key-value store persisted across runs.
+/// Values are kept as RON in the user's config directory.
+pub struct Storage {
+    path: Option<PathBuf>,
+    values: BTreeMap<String, String>,
+}
+
+impl Storage {
+    fn load() -> Self {
+        let path = dirs::config_dir().map(|dir| dir.join("chippyverse").join("storage.ron"));
+
+        let values = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| ron::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Self { path, values }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.values
+            .get(key)
+            .and_then(|value| ron::from_str(value).ok())
+    }
+
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) {
+        match ron::to_string(value) {
+            Ok(value) => {
+                self.values.insert(key.to_owned(), value);
+            }
+            Err(err) => log::error!("Unable to serialize {}: {}", key, err),
+        }
+    }
+
+    /// Write all values to disk.
+    pub fn flush(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        let result = ron::ser::to_string_pretty(&self.values, Default::default())
+            .map_err(|err| err.to_string())
+            .and_then(|contents| fs::write(path, contents).map_err(|err| err.to_string()));
+
+        if let Err(err) = result {
+            log::error!("Unable to save storage: {}", err);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the file is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the file must stay unchanged before reloading,
+/// so an assembler writing in several steps only triggers one reload.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watches a ROM file for changes by polling its modification time.
+pub struct RomWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // When a change was last seen which hasn't been reloaded yet.
+    changed_at: Option<Instant>,
+    last_poll: Instant,
+}
+
+impl RomWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            modified: modified(&path),
+            path,
+            changed_at: None,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Check for changes, returning the new contents once the file has settled.
+    /// The file missing (e.g. while being rewritten) is not treated as a change.
+    pub fn poll(&mut self) -> Option<Vec<u8>> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+
+        self.last_poll = Instant::now();
+
+        let modified = modified(&self.path);
+        if modified.is_some() && modified != self.modified {
+            self.modified = modified;
+            self.changed_at = Some(Instant::now());
+            return None;
+        }
+
+        match self.changed_at {
+            Some(changed_at) if changed_at.elapsed() >= DEBOUNCE => {
+                // Retry on the next poll if the file can't be read yet.
+                let rom = fs::read(&self.path).ok()?;
+                self.changed_at = None;
+                Some(rom)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
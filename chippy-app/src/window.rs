@@ -10,22 +10,31 @@ use std::{
 };
 
 use egui::mutex::RwLock;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 
-/// Internal wrapper window for handling miniquad events.
-struct InternalWindow<T: Window> {
-    /// Window wrapper object
-    window: Arc<RwLock<T>>,
-    running: Arc<AtomicBool>,
-    egui_ctx: egui_mq::EguiMq,
-}
+use crate::storage::{self, Storage};
 
-impl<T: Window> InternalWindow<T> {
-    fn new(ctx: &mut mq::Context, running: Arc<AtomicBool>, window: Arc<RwLock<T>>) -> Self {
-        let mut egui_ctx = egui_mq::EguiMq::new(ctx);
+const THEME_KEY: &str = "theme";
 
-        window.write().on_open(ctx, &mut egui_ctx);
+static THEME: Lazy<RwLock<Theme>> =
+    Lazy::new(|| RwLock::new(storage::storage().get(THEME_KEY).unwrap_or_default()));
+
+/// egui theme shared by every window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    fn visuals(&self) -> egui::Visuals {
+        let mut visuals = match self {
+            Self::Dark => egui::Visuals::dark(),
+            Self::Light => egui::Visuals::light(),
+        };
 
-        let mut visuals = egui::Visuals::dark();
         let rounding = egui::Rounding::same(2.0);
 
         visuals.widgets.noninteractive.rounding = rounding;
@@ -42,23 +51,87 @@ impl<T: Window> InternalWindow<T> {
         visuals.widgets.active.expansion = expansion;
         visuals.widgets.open.expansion = expansion;
 
-        egui_ctx.egui_ctx().set_visuals(visuals);
+        visuals
+    }
+}
+
+pub fn theme() -> Theme {
+    *THEME.read()
+}
+
+/// Change the theme of all windows, this is persisted across runs.
+pub fn set_theme(theme: Theme) {
+    *THEME.write() = theme;
+    storage::storage().set(THEME_KEY, &theme);
+}
+
+/// Internal wrapper window for handling miniquad events.
+struct InternalWindow<T: Window> {
+    /// Window wrapper object
+    window: Arc<RwLock<T>>,
+    running: Arc<AtomicBool>,
+    egui_ctx: egui_mq::EguiMq,
+    // Theme currently applied to the egui context.
+    theme: Theme,
+}
+
+impl<T: Window> InternalWindow<T> {
+    fn new(ctx: &mut mq::Context, running: Arc<AtomicBool>, window: Arc<RwLock<T>>) -> Self {
+        let mut egui_ctx = egui_mq::EguiMq::new(ctx);
+
+        // Restore egui memory before the window can read any state from it.
+        if let Some(id) = window.read().persistence_id() {
+            if let Some(memory) = storage::storage().get(&memory_key(id)) {
+                *egui_ctx.egui_ctx().memory() = memory;
+            }
+        }
+
+        window.write().on_open(ctx, &mut egui_ctx);
+
+        let theme = theme();
+        egui_ctx.egui_ctx().set_visuals(theme.visuals());
 
         Self {
             window,
             running,
             egui_ctx,
+            theme,
         }
     }
+
+    /// Persist egui memory and any window state.
+    fn save(&mut self, ctx: &mut mq::Context) {
+        let mut storage = storage::storage();
+        let mut window = self.window.write();
+
+        if let Some(id) = window.persistence_id() {
+            storage.set(&memory_key(id), &*self.egui_ctx.egui_ctx().memory());
+        }
+
+        window.save(ctx, &mut storage);
+        storage.flush();
+    }
+}
+
+fn memory_key(id: &str) -> String {
+    format!("{}/egui_memory", id)
 }
 
 impl<T: Window> mq::EventHandler for InternalWindow<T> {
     fn update(&mut self, ctx: &mut mq::Context) {
         if !self.running.load(Ordering::Relaxed) {
+            self.save(ctx);
             ctx.quit();
             return;
         }
 
+        // Pick up theme changes made from any window.
+        let theme = theme();
+        if theme != self.theme {
+            self.egui_ctx.egui_ctx().set_visuals(theme.visuals());
+            self.theme = theme;
+        }
+
         self.window.write().update(ctx);
     }
 
@@ -67,10 +140,29 @@ impl<T: Window> mq::EventHandler for InternalWindow<T> {
         self.window.write().draw(ctx, &mut self.egui_ctx);
     }
 
-    fn quit_requested_event(&mut self, _ctx: &mut mq::Context) {
+    fn quit_requested_event(&mut self, ctx: &mut mq::Context) {
+        self.save(ctx);
         self.running.store(false, Ordering::Relaxed);
     }
 
+    fn window_minimized_event(&mut self, ctx: &mut mq::Context) {
+        self.window
+            .write()
+            .on_event(ctx, Event::WindowFocus { focused: false });
+    }
+
+    fn window_restored_event(&mut self, ctx: &mut mq::Context) {
+        self.window
+            .write()
+            .on_event(ctx, Event::WindowFocus { focused: true });
+    }
+
+    fn resize_event(&mut self, ctx: &mut mq::Context, width: f32, height: f32) {
+        self.window
+            .write()
+            .on_event(ctx, Event::Resized { width, height });
+    }
+
     fn mouse_motion_event(&mut self, ctx: &mut mq::Context, x: f32, y: f32) {
         if self
             .window
@@ -123,6 +215,35 @@ impl<T: Window> mq::EventHandler for InternalWindow<T> {
         }
     }
 
+    fn touch_event(
+        &mut self,
+        ctx: &mut mq::Context,
+        phase: mq::TouchPhase,
+        id: u64,
+        x: f32,
+        y: f32,
+    ) {
+        if self
+            .window
+            .write()
+            .on_event(ctx, Event::Touch { phase, id, x, y })
+        {
+            // Same as miniquad's default, egui only sees the touch as a mouse.
+            match phase {
+                mq::TouchPhase::Started => {
+                    self.egui_ctx.mouse_motion_event(x, y);
+                    self.egui_ctx
+                        .mouse_button_down_event(ctx, mq::MouseButton::Left, x, y);
+                }
+                mq::TouchPhase::Moved => self.egui_ctx.mouse_motion_event(x, y),
+                mq::TouchPhase::Ended | mq::TouchPhase::Cancelled => {
+                    self.egui_ctx
+                        .mouse_button_up_event(ctx, mq::MouseButton::Left, x, y);
+                }
+            }
+        }
+    }
+
     fn char_event(
         &mut self,
         ctx: &mut mq::Context,
@@ -205,6 +326,24 @@ pub enum Event {
         x: f32,
         y: f32,
     },
+    /// Finger touching the screen, `id` tells simultaneous touches apart.
+    Touch {
+        phase: mq::TouchPhase,
+        id: u64,
+        x: f32,
+        y: f32,
+    },
+    /// Window lost or regained focus.
+    /// Miniquad reports this through minimize/restore, so it isn't sent on every platform.
+    WindowFocus {
+        focused: bool,
+    },
+    /// Window was resized, in physical pixels.
+    /// Also sent when the DPI scale changes, e.g. when moved to another monitor.
+    Resized {
+        width: f32,
+        height: f32,
+    },
 }
 
 pub trait Window: Send + Sync {
@@ -222,6 +361,14 @@ pub trait Window: Send + Sync {
     fn on_event(&mut self, _ctx: &mut mq::Context, _event: Event) -> bool {
         true
     }
+
+    /// Key to persist egui memory under across runs, `None` to not persist it.
+    fn persistence_id(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Called before the window closes to persist any state.
+    fn save(&mut self, _ctx: &mut mq::Context, _storage: &mut Storage) {}
 }
 
 /// A window which can be started on it's own thread.
@@ -0,0 +1,82 @@
+//! Interpreter and JIT throughput, reported as instructions per second.
+//!
+//! Usage: `cargo bench -p chippy-core`
+
+use chippy_core::{Backend, Chip8};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const BACKENDS: [(&str, Backend); 2] =
+    [("interpreter", Backend::Interpreter), ("jit", Backend::Jit)];
+
+/// Instructions executed per iteration.
+const CYCLES: usize = 10_000;
+
+const INSTRUCTION_TEST: &[u8] = include_bytes!("../../chippy-app/src/Instruction-test.ch8");
+
+/// Loop of register arithmetic setting VF.
+const ARITHMETIC: &[u8] = &[
+    0x70, 0x03, // 200: ADD V0, 03
+    0x81, 0x04, // 202: ADD V1, V0
+    0x82, 0x15, // 204: SUB V2, V1
+    0x83, 0x26, // 206: SHR V3, V2
+    0x84, 0x33, // 208: XOR V4, V3
+    0x12, 0x00, // 20A: JP 200
+];
+
+/// Loop drawing the font's "0" sprite at a moving position.
+const DRAW: &[u8] = &[
+    0xA0, 0x00, // 200: LD I, 000
+    0x70, 0x01, // 202: ADD V0, 01
+    0x71, 0x03, // 204: ADD V1, 03
+    0xD0, 0x15, // 206: DRW V0, V1, 5
+    0x12, 0x02, // 208: JP 202
+];
+
+fn run(c: &mut Criterion, name: &str, rom: &[u8]) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(CYCLES as u64));
+
+    for (backend_name, backend) in BACKENDS {
+        let mut chip8 = Chip8::new();
+        chip8.set_backend(backend);
+        chip8.load_rom(rom.to_vec());
+
+        group.bench_function(backend_name, |b| {
+            b.iter(|| black_box(chip8.run_cycles(CYCLES)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn arithmetic(c: &mut Criterion) {
+    run(c, "arithmetic", ARITHMETIC);
+}
+
+fn draw(c: &mut Criterion) {
+    run(c, "draw", DRAW);
+}
+
+/// The test ROM ends in an infinite loop, so later iterations measure that loop.
+/// Each iteration reloads it to measure the whole program instead.
+fn instruction_test(c: &mut Criterion) {
+    let mut group = c.benchmark_group("instruction_test");
+    group.throughput(Throughput::Elements(CYCLES as u64));
+
+    for (backend_name, backend) in BACKENDS {
+        let mut chip8 = Chip8::new();
+        chip8.set_backend(backend);
+
+        group.bench_function(backend_name, |b| {
+            b.iter(|| {
+                chip8.load_rom(INSTRUCTION_TEST.to_vec());
+                black_box(chip8.run_cycles(CYCLES)).unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, arithmetic, draw, instruction_test);
+criterion_main!(benches);
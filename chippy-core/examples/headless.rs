@@ -0,0 +1,40 @@
+//! Run the bundled instruction test ROM without a window and print the result.
+//!
+//! `RND` is seeded so every run prints the same screen and registers.
+//!
+//! Usage: `cargo run --example headless`
+
+use chippy_core::{breakpoint::RunOutcome, Chip8};
+use rand::{rngs::StdRng, SeedableRng};
+
+const INSTRUCTION_TEST: &[u8] = include_bytes!("../../chippy-app/src/Instruction-test.ch8");
+
+/// Most instructions executed before printing, in case the ROM never halts.
+const MAX_CYCLES: usize = 100_000;
+const SEED: u64 = 1234;
+
+fn main() {
+    let mut chip8 = Chip8::with_rng(StdRng::seed_from_u64(SEED));
+    chip8.load_rom(INSTRUCTION_TEST.to_vec());
+
+    match chip8.run_until_halt(MAX_CYCLES) {
+        RunOutcome::Halted => {}
+        RunOutcome::Idle => println!("Waiting for a key at {:X}", chip8.pc),
+        RunOutcome::CycleLimit => println!("Still running after {} cycles", MAX_CYCLES),
+        RunOutcome::Error(err) => println!("{}", err),
+        RunOutcome::Breakpoint(_) => unreachable!("breakpoints are passed over"),
+    }
+
+    print!("{}", chip8.render_to_ascii('#', ' '));
+
+    println!(
+        "PC {:04X}  I {:04X}  SP {:X}",
+        chip8.pc,
+        chip8.index(),
+        chip8.sp
+    );
+    for register in 0..16 {
+        print!("V{:X} {:02X} ", register, chip8.register(register));
+    }
+    println!();
+}
@@ -0,0 +1,44 @@
+//! Drive a `Chip8` from the caller's own loop without `ExecutingChip8`.
+//!
+//! This is the same pacing as the vsync execution mode: a fixed amount of
+//! instructions is run and the timers tick once every frame, so no thread
+//! or lock is needed.
+//!
+//! Usage: `cargo run --example single_threaded -- <rom>`
+
+use std::{env, fs, thread, time::Duration};
+
+use chippy_core::Chip8;
+
+/// Instructions executed per frame.
+const IPF: usize = 10;
+/// Amount of 60 Hz frames to run before printing the screen.
+const FRAMES: usize = 120;
+
+fn main() {
+    let path = env::args().nth(1).expect("Usage: single_threaded <rom>");
+
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(fs::read(path).expect("Unable to read ROM"));
+
+    for _ in 0..FRAMES {
+        match chip8.run_cycles(IPF) {
+            Ok(spent) if spent < IPF => {
+                println!("Stopped on a breakpoint at {:X}", chip8.pc);
+                break;
+            }
+            Ok(_) => chip8.tick_timers(),
+            Err(err) => {
+                println!("{}", err);
+                break;
+            }
+        }
+
+        thread::sleep(Duration::from_micros(16666));
+    }
+
+    for row in chip8.screen.iter() {
+        let line: String = row.iter().map(|on| if *on { '#' } else { ' ' }).collect();
+        println!("{}", line);
+    }
+}
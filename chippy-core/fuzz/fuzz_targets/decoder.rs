@@ -0,0 +1,17 @@
+//! Decode arbitrary instruction words.
+//!
+//! Run with `cargo fuzz run decoder` from `chippy-core`.
+
+#![no_main]
+
+use chippy_core::opcode::OpCode;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let [high, low, ..] = *data {
+        let opcode = OpCode::from_opcode(u16::from_be_bytes([high, low]));
+        let (mnemonic, _) = opcode.get_opcode_str();
+
+        assert!(!mnemonic.is_empty());
+    }
+});
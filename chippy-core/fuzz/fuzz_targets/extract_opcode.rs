@@ -0,0 +1,21 @@
+//! Read instruction words from arbitrary memory at arbitrary positions.
+//!
+//! The first two bytes are the position, the rest is the memory.
+//! Run with `cargo fuzz run extract_opcode` from `chippy-core`.
+
+#![no_main]
+
+use chippy_core::opcode::extract_opcode_from_array;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let [high, low, ref memory @ ..] = *data {
+        let ptr = u16::from_be_bytes([high, low]) as usize;
+        let word = extract_opcode_from_array(memory, ptr);
+
+        // Incomplete words read as 0.
+        if ptr + 1 >= memory.len() {
+            assert_eq!(word, 0);
+        }
+    }
+});
@@ -0,0 +1,86 @@
+use std::f32::consts::TAU;
+
+use rand::RngCore;
+
+use crate::Chip8;
+
+/// Shape of the beep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Waveform {
+    #[default]
+    Square,
+    Sine,
+    Triangle,
+}
+
+/// Tone generator for the sound timer.
+#[derive(Debug, Clone)]
+pub struct Beeper {
+    /// Tone frequency in Hz.
+    pub frequency: f32,
+    pub waveform: Waveform,
+    /// Loudness from 0 to 1.
+    pub volume: f32,
+    /// Output silence regardless of the sound timer, e.g. while the window is in the background.
+    pub muted: bool,
+    // Position within the current period, kept across calls so changing
+    // the frequency or pausing the beep doesn't restart the wave.
+    phase: f32,
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 1.0,
+            muted: false,
+            phase: 0.0,
+        }
+    }
+}
+
+impl Beeper {
+    /// Produce the next sample in the range [-1, 1].
+    pub fn next_sample(&mut self, sample_rate: u32) -> f32 {
+        let sample = match self.waveform {
+            Waveform::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (self.phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+        };
+
+        self.phase = (self.phase + self.frequency / sample_rate as f32).fract();
+        sample
+    }
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Fill an interleaved audio buffer, beeping while the sound timer is active and the beeper isn't muted.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Output samples.
+    /// * `sample_rate` - Output sample rate in Hz.
+    /// * `channels` - Amount of interleaved channels, every channel gets the same sample.
+    ///
+    pub fn audio_samples(&mut self, buffer: &mut [f32], sample_rate: u32, channels: usize) {
+        let playing = self.sound_timer > 0 && !self.beeper.muted;
+
+        for frame in buffer.chunks_mut(channels.max(1)) {
+            let sample = if playing {
+                self.beeper.next_sample(sample_rate) * self.beeper.volume
+            } else {
+                0.0
+            };
+
+            frame.fill(sample);
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use rand::RngCore;
+
+use crate::{
+    opcode::{extract_opcode_from_array, OpCode},
+    types::{C8Addr, C8Byte, C8RegIdx},
+    Chip8, Chip8Error, DEFAULT_IPF,
+};
+
+/// Condition evaluated before executing the instruction at a breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// Always break.
+    Always,
+    /// Break when register Vx holds the value.
+    RegEquals(C8RegIdx, C8Byte),
+    /// Break when I holds the address.
+    IndexEquals(C8Addr),
+}
+
+impl Condition {
+    /// Does the condition hold for the current state.
+    pub fn holds<R: RngCore>(&self, chip8: &Chip8<R>) -> bool {
+        match *self {
+            Self::Always => true,
+            Self::RegEquals(reg, value) => chip8.registers[reg as usize & 0xF] == value,
+            Self::IndexEquals(addr) => chip8.index == addr,
+        }
+    }
+}
+
+/// Why `Chip8::run_until_halt` stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// Reached a jump to itself, see `Chip8::is_halted`.
+    Halted,
+    /// Waiting for a key, so nothing will change without input, see `Chip8::is_idle`.
+    Idle,
+    /// Ran the maximum amount of cycles without halting.
+    CycleLimit,
+    /// Stopped before the instruction at a breakpoint.
+    /// Only sent by `ExecutingChip8`, `run_until_halt` passes over breakpoints.
+    Breakpoint(C8Addr),
+    /// An instruction failed, the PC is left on it.
+    Error(Chip8Error),
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Set a breakpoint at an address, replacing any existing one.
+    pub fn set_breakpoint(&mut self, addr: C8Addr, condition: Condition) {
+        self.breakpoints.insert(addr, condition);
+    }
+
+    /// Remove the breakpoint at an address.
+    pub fn remove_breakpoint(&mut self, addr: C8Addr) -> Option<Condition> {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Set a one-shot breakpoint, removed the next time execution stops on any breakpoint.
+    pub fn set_temporary_breakpoint(&mut self, addr: C8Addr) {
+        self.temporary_breakpoints.insert(addr);
+    }
+
+    /// Is there a breakpoint at the current PC whose condition holds.
+    pub fn at_breakpoint(&self) -> bool {
+        self.temporary_breakpoints.contains(&self.pc)
+            || matches!(self.breakpoints.get(&self.pc), Some(condition) if condition.holds(self))
+    }
+
+    /// Check whether execution should stop at the current PC.
+    /// Stopping consumes all temporary breakpoints, so a "run to" is cancelled by hitting
+    /// a persistent breakpoint first.
+    pub fn break_requested(&mut self) -> bool {
+        let hit = self.at_breakpoint();
+        if hit {
+            self.temporary_breakpoints.clear();
+        }
+
+        hit
+    }
+
+    /// Execute up to `cycles` worth of instructions, stopping before an instruction whose breakpoint triggers.
+    /// The breakpoint execution last stopped at is passed over so execution can be resumed.
+    ///
+    /// Every instruction costs one cycle unless `cycle_costs` is enabled.
+    ///
+    /// Returns the amount of cycles spent, which is less than `cycles` if a breakpoint stopped execution
+    /// or a `DRW` waits for the next frame, see `is_waiting_for_frame`.
+    /// This can overshoot `cycles` by the cost of the last instruction.
+    pub fn run_cycles(&mut self, cycles: usize) -> Result<usize, Chip8Error> {
+        let mut resume_from = self.stopped_at.take();
+        let mut spent = 0;
+
+        while spent < cycles {
+            // Checked before breakpoints, so stopping on one is never mistaken for waiting.
+            if self.is_waiting_for_frame() {
+                self.stopped_at = resume_from;
+                return Ok(spent);
+            }
+
+            if resume_from.take() != Some(self.pc) && self.break_requested() {
+                self.stopped_at = Some(self.pc);
+
+                #[cfg(feature = "scripting")]
+                self.script_breakpoint();
+
+                return Ok(spent);
+            }
+
+            #[cfg(target_arch = "x86_64")]
+            if self.backend == crate::Backend::Jit && !self.tracing() {
+                if let Some(cost) = self.run_block(cycles - spent) {
+                    spent += cost;
+                    continue;
+                }
+            }
+
+            spent += self.next_cycle_cost() as usize;
+            self.interpreter()?;
+        }
+
+        Ok(spent)
+    }
+
+    /// Execute instructions until a breakpoint triggers or `max_cycles` have run.
+    ///
+    /// Returns true if execution stopped on a breakpoint.
+    pub fn run_until_break(&mut self, max_cycles: usize) -> Result<bool, Chip8Error> {
+        Ok(self.run_cycles(max_cycles)? < max_cycles && !self.is_waiting_for_frame())
+    }
+
+    /// Execute up to `count` instructions, stopping early before a breakpoint, once the program halts
+    /// or at a `DRW` waiting for the next frame.
+    ///
+    /// Returns the amount of instructions executed.
+    pub fn step(&mut self, count: usize) -> Result<usize, Chip8Error> {
+        let start = self.cycle_count;
+
+        for _ in 0..count {
+            let before = self.cycle_count;
+            if self.is_halted() {
+                break;
+            }
+
+            self.run_cycles(1)?;
+            if self.cycle_count == before {
+                break;
+            }
+        }
+
+        Ok((self.cycle_count - start) as usize)
+    }
+
+    /// Execute instructions until the program halts, goes idle or `max_cycles` have run.
+    ///
+    /// Breakpoints are passed over. Meant for running ROMs without a frontend,
+    /// e.g. in tests, where nothing would resume execution after a breakpoint or press a key.
+    ///
+    /// The timers tick after every `DEFAULT_IPF` cycles, or sooner when a `DRW` waits for
+    /// the next frame, so runs are deterministic and programs waiting on the timers finish.
+    pub fn run_until_halt(&mut self, max_cycles: usize) -> RunOutcome {
+        let mut spent = 0;
+        let mut frame_spent = 0;
+
+        while spent < max_cycles {
+            if self.is_halted() {
+                return RunOutcome::Halted;
+            }
+
+            if self.is_idle() {
+                return RunOutcome::Idle;
+            }
+
+            match self.run_cycles(1) {
+                Ok(cost) => {
+                    spent += cost;
+                    frame_spent += cost;
+                }
+                Err(err) => return RunOutcome::Error(err),
+            }
+
+            if frame_spent >= DEFAULT_IPF as usize || self.is_waiting_for_frame() {
+                self.tick_timers();
+                frame_spent = 0;
+            }
+        }
+
+        RunOutcome::CycleLimit
+    }
+
+    /// Is the current instruction a jump to itself, which programs use to stop.
+    pub fn is_halted(&self) -> bool {
+        self.peek_next_opcode().1 == OpCode::JP(self.pc)
+    }
+
+    /// Is the current instruction `LD Vx, K` with no key pressed.
+    pub fn is_waiting_for_key(&self) -> bool {
+        matches!(self.peek_next_opcode().1, OpCode::LDGetKey(_))
+            && self.keypad.any_pressed().is_none()
+    }
+
+    /// Is the program looping on `SKP Vx` or `SKNP Vx` followed by a jump back to it,
+    /// which only a change of the key can leave.
+    pub fn is_polling_key(&self) -> bool {
+        // The PC may be on either instruction of the loop.
+        let start = match self.peek_next_opcode().1 {
+            OpCode::JP(target) if target.wrapping_add(2) == self.pc => target,
+            _ => self.pc,
+        };
+
+        let decode = |addr: C8Addr| {
+            OpCode::from_opcode(extract_opcode_from_array(&self.memory, addr as usize))
+        };
+
+        if decode(start.wrapping_add(2)) != OpCode::JP(start) {
+            return false;
+        }
+
+        let pressed = |reg: C8RegIdx| self.keypad.keys[self.registers[reg as usize] as usize & 0xF];
+        match decode(start) {
+            OpCode::SKP(reg) => !pressed(reg),
+            OpCode::SKNP(reg) => pressed(reg),
+            _ => false,
+        }
+    }
+
+    /// Is the program waiting for input, either in `LD Vx, K` or polling a key.
+    /// Nothing but a key press or release changes the machine state, besides the timers.
+    pub fn is_idle(&self) -> bool {
+        self.is_waiting_for_key() || self.is_polling_key()
+    }
+
+    /// Raw word and decoded instruction at the PC, the one executed next.
+    pub fn peek_next_opcode(&self) -> (u16, OpCode) {
+        let word = extract_opcode_from_array(&self.memory, self.pc as usize);
+        (word, OpCode::from_opcode(word))
+    }
+}
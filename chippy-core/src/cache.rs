@@ -1,179 +1,74 @@
-// // use dynasmrt::{dynasm, mmap::ExecutableBuffer, x64::Assembler, AssemblyOffset, DynasmApi};
-
-// // pub struct Cache {
-// //     pub pc: u16,
-// //     _called_buf: ExecutableBuffer, // Store it so its memory isn't freed.
-// //     caller_buf: ExecutableBuffer,  // Store it so its memory isn't freed.
-// //     caller: extern "win64" fn(&mut u32),
-// // }
-
-// // impl Cache {
-// //     pub fn new(pc: u16, called_buf: ExecutableBuffer) -> Self {
-// //         let mut caller_buf = Assembler::new().expect("Failed to create new assembler");
-// //         let called = called_buf.ptr(AssemblyOffset(0));
-
-// //         extern "win64" fn dummy(_: &mut u32) {}
-// //         let mut cache = Self {
-// //             pc,
-// //             _called_buf: called_buf,
-// //             caller_buf: Assembler::new()
-// //                 .expect("Dummy assembler")
-// //                 .finalize()
-// //                 .unwrap(),
-// //             caller: dummy,
-// //         };
-
-// //         #[cfg(debug_assertions)]
-// //         println!(
-// //             "New cache at {:#X} (size {}, {:?})",
-// //             pc,
-// //             cache._called_buf.size(),
-// //             called
-// //         );
-
-// //         // Saves the caller-saved registers RAX, RCX and RDX.
-// //         // Call the cached code.
-// //         // Move the return value to the `ret` variable.
-// //         // Restore the registers and lend control back to the function.
-// //         dynasm!(caller_buf
-// //             ; .arch x64
-// //             ; push rax
-// //             ; push rcx
-// //             ; push rdx
-// //             ; mov rax, QWORD called as i64
-// //             ; call rax
-// //             ; pop rdx
-// //             ; pop rcx
-// //             ; mov DWORD [rcx], eax
-// //             ; pop rax
-// //             ; ret
-// //         );
-
-// //         cache.caller_buf = caller_buf.finalize().unwrap();
-// //         unsafe {
-// //             cache.caller = std::mem::transmute::<*const u8, extern "win64" fn(&mut u32)>(
-// //                 cache.caller_buf.ptr(AssemblyOffset(0)),
-// //             )
-// //         }
-
-// //         cache
-// //     }
-
-// //     pub fn run(&mut self) -> u32 {
-// //         #[cfg(debug_assertions)]
-// //         println!("Executing cache at {:#X}", self.pc);
-// //         let mut ret = 0;
-// //         (self.caller)(&mut ret);
-// //         #[cfg(debug_assertions)]
-// //         println!("Cache execution returned with value {:#X}", ret);
-// //         ret
-// //     }
-// // }
-
-// // pub struct Caches {
-// //     caches: Vec<Cache>,
-// // }
-
-// // impl Caches {
-// //     pub fn new() -> Self {
-// //         Self { caches: Vec::new() }
-// //     }
-
-// //     pub fn add(&mut self, pc: u16, exec: ExecutableBuffer) {
-// //         self.caches.push(Cache::new(pc, exec));
-// //     }
-
-// //     pub fn get(&mut self, pc: u16) -> Option<&mut Cache> {
-// //         self.caches.iter_mut().find(|el| el.pc == pc)
-// //     }
-// // }
-
-// use dynasmrt::{dynasm, mmap::ExecutableBuffer, x64::Assembler, AssemblyOffset, DynasmApi};
-
-// pub struct Cache {
-//     pub pc: u16,
-//     _called_buf: ExecutableBuffer, // Store it so its memory isn't freed.
-//     caller_buf: ExecutableBuffer,  // Store it so its memory isn't freed.
-//     caller: extern "win64" fn(&mut u32),
-// }
-
-// impl Cache {
-//     pub fn new(pc: u16, called_buf: ExecutableBuffer) -> Self {
-//         let mut caller_buf = Assembler::new().expect("Failed to create new assembler");
-//         let called = called_buf.ptr(AssemblyOffset(0));
-
-//         extern "win64" fn dummy(_: &mut u32) {}
-//         let mut cache = Self {
-//             pc,
-//             _called_buf: called_buf,
-//             caller_buf: Assembler::new()
-//                 .expect("Dummy assembler")
-//                 .finalize()
-//                 .unwrap(),
-//             caller: dummy,
-//         };
-
-//         #[cfg(debug_assertions)]
-//         println!(
-//             "New cache at {:#X} (size {}, {:?})",
-//             pc,
-//             cache._called_buf.size(),
-//             called
-//         );
-
-//         // Saves the caller-saved registers RAX, RCX and RDX.
-//         // Call the cached code.
-//         // Move the return value to the `ret` variable.
-//         // Restore the registers and lend control back to the function.
-//         dynasm!(caller_buf
-//             ; .arch x64
-//             ; push rax
-//             ; push rcx
-//             ; push rdx
-//             ; mov rax, QWORD called as i64
-//             ; call rax
-//             ; pop rdx
-//             ; pop rcx
-//             ; mov DWORD [rcx], eax
-//             ; pop rax
-//             ; ret
-//         );
-
-//         cache.caller_buf = caller_buf.finalize().unwrap();
-//         unsafe {
-//             cache.caller = std::mem::transmute::<*const u8, extern "win64" fn(&mut u32)>(
-//                 cache.caller_buf.ptr(AssemblyOffset(0)),
-//             )
-//         }
-
-//         cache
-//     }
-
-//     pub fn run(&mut self) -> u32 {
-//         #[cfg(debug_assertions)]
-//         println!("Executing cache at {:#X}", self.pc);
-//         let mut ret = 0;
-//         (self.caller)(&mut ret);
-//         #[cfg(debug_assertions)]
-//         println!("Cache execution returned with value {:#X}", ret);
-//         ret
-//     }
-// }
-
-// pub struct Caches {
-//     caches: Vec<Cache>,
-// }
-
-// impl Caches {
-//     pub fn new() -> Self {
-//         Self { caches: Vec::new() }
-//     }
-
-//     pub fn add(&mut self, pc: u16, exec: ExecutableBuffer) {
-//         self.caches.push(Cache::new(pc, exec));
-//     }
-
-//     pub fn get(&mut self, pc: u16) -> Option<&mut Cache> {
-//         self.caches.iter_mut().find(|el| el.pc == pc)
-//     }
-// }
+use std::{mem, ops::Range};
+
+use dynasmrt::{mmap::ExecutableBuffer, AssemblyOffset};
+
+/// Signature of compiled code, called with pointers to V0 and the index register.
+type BlockFn = extern "sysv64" fn(*mut u8, *mut u16);
+
+/// Run of straight-line instructions compiled to native code.
+pub(crate) struct Block {
+    /// Memory the block was compiled from, the block is stale once it changes.
+    source: Vec<u8>,
+    /// Amount of instructions compiled, 0 if the first one can't be compiled.
+    pub len: usize,
+    code: Option<ExecutableBuffer>,
+}
+
+impl Block {
+    pub fn new(source: Vec<u8>, len: usize, code: Option<ExecutableBuffer>) -> Self {
+        Self { source, len, code }
+    }
+
+    /// Was memory the block was compiled from written since.
+    pub fn is_stale(&self, memory: &[u8], addr: usize) -> bool {
+        memory.get(addr..addr + self.source.len()) != Some(&self.source[..])
+    }
+
+    /// # Safety
+    ///
+    /// `registers` must point to the 16 registers and `index` to the index register.
+    pub unsafe fn run(&self, registers: *mut u8, index: *mut u16) {
+        if let Some(code) = &self.code {
+            let block: BlockFn = mem::transmute(code.ptr(AssemblyOffset(0)));
+            block(registers, index);
+        }
+    }
+}
+
+/// Compiled blocks keyed by the address they start at.
+#[derive(Default)]
+pub(crate) struct BlockCache {
+    // Allocated on the first insert so machines using the interpreter don't pay for it.
+    blocks: Vec<Option<Block>>,
+}
+
+impl BlockCache {
+    pub fn get(&self, addr: usize) -> Option<&Block> {
+        self.blocks.get(addr)?.as_ref()
+    }
+
+    pub fn insert(&mut self, addr: usize, block: Block) {
+        if addr >= self.blocks.len() {
+            self.blocks.resize_with(addr + 1, || None);
+        }
+
+        self.blocks[addr] = Some(block);
+    }
+
+    /// Drop blocks compiled from any byte of `range`.
+    pub fn invalidate(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.blocks.len());
+        for (addr, block) in self.blocks[..end].iter_mut().enumerate() {
+            if block
+                .as_ref()
+                .is_some_and(|block| addr + block.source.len() > range.start)
+            {
+                *block = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks = Vec::new();
+    }
+}
@@ -0,0 +1,84 @@
+//! Source of time for the timers and timed runs, replaceable so tests don't depend on the wall clock.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::RngCore;
+
+use crate::{Chip8, Chip8Error, FRAME};
+
+/// Monotonic time source, see `Chip8::set_clock`.
+pub trait Clock: Send + Sync {
+    /// Time elapsed since an arbitrary fixed point.
+    fn now(&self) -> Duration;
+
+    /// Wait for `duration` to pass, fake clocks can advance themselves instead.
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Wall clock, used unless another clock is set.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Cycles a machine running at `frequency` Hz executes in `duration`.
+fn cycles_in(duration: Duration, frequency: u32) -> usize {
+    (duration.as_nanos() * frequency as u128 / 1_000_000_000) as usize
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Replace the clock driving `run_for`.
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Execute at `frequency` Hz for `duration`, stopping early before an instruction
+    /// whose breakpoint triggers. The timers tick at 60 Hz meanwhile.
+    ///
+    /// Each frame's cycles run at once, then the clock is slept on until the frame is over.
+    /// The cycles and ticks only depend on `duration`, so a fake clock can't stall the run.
+    /// Returns the amount of cycles spent.
+    pub fn run_for(&mut self, duration: Duration, frequency: u32) -> Result<usize, Chip8Error> {
+        let start = self.clock.now();
+        let mut ticks = 0;
+        let mut spent = 0;
+
+        loop {
+            let until = (FRAME * (ticks + 1)).min(duration);
+            let budget = cycles_in(until, frequency).saturating_sub(spent);
+            let ran = self.run_cycles(budget)?;
+            spent += ran;
+
+            self.clock
+                .sleep((start + until).saturating_sub(self.clock.now()));
+
+            // Stopped on a breakpoint rather than waiting for the next frame.
+            if (ran < budget && !self.is_waiting_for_frame()) || until == duration {
+                break;
+            }
+
+            self.tick_timers();
+            ticks += 1;
+        }
+
+        Ok(spent)
+    }
+}
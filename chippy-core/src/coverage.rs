@@ -0,0 +1,44 @@
+//! Which instructions have run, e.g. to tell code from data when reverse engineering,
+//! see `disassembler::coverage_listing`.
+//!
+//! Only the address an instruction starts at is marked.
+
+use rand::RngCore;
+
+use crate::{types::C8Addr, Chip8};
+
+impl<R: RngCore> Chip8<R> {
+    /// Start recording which instructions are executed, or stop and drop the record.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.coverage = enabled.then(|| vec![false; self.memory.len()]);
+    }
+
+    pub fn coverage_enabled(&self) -> bool {
+        self.coverage.is_some()
+    }
+
+    /// Was an instruction at `addr` executed since coverage was enabled or cleared.
+    pub fn executed(&self, addr: C8Addr) -> bool {
+        self.coverage
+            .as_ref()
+            .and_then(|coverage| coverage.get(addr as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// Forget the instructions executed so far, loading a ROM also does this.
+    pub fn clear_coverage(&mut self) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.fill(false);
+        }
+    }
+
+    /// Mark `len` consecutive instructions starting at `addr` as executed.
+    pub(crate) fn cover(&mut self, addr: usize, len: usize) {
+        if let Some(coverage) = &mut self.coverage {
+            for executed in coverage.iter_mut().skip(addr).step_by(2).take(len) {
+                *executed = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::{collections::BTreeSet, fmt, fmt::Write};
+
+use rand::RngCore;
+
+use crate::{
+    opcode::{extract_opcode_from_array, OpCode},
+    rom,
+    types::C8Addr,
+    Chip8, PROGRAM_ADDR,
+};
+
+/// Single disassembled instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisasmLine {
+    /// Address of the instruction.
+    pub addr: C8Addr,
+    /// Raw instruction word.
+    pub word: u16,
+    /// Decoded opcode.
+    pub opcode: OpCode,
+    /// Assembly output, e.g. `JP 0208`.
+    pub mnemonic: String,
+    /// Verbose description of the instruction.
+    pub verbose: String,
+}
+
+impl DisasmLine {
+    /// Decode the instruction located at `addr`.
+    pub fn decode(memory: &[u8], addr: C8Addr) -> Self {
+        let word = extract_opcode_from_array(memory, addr as usize);
+        let opcode = OpCode::from_opcode(word);
+        let (mnemonic, verbose) = opcode.get_opcode_str();
+
+        Self {
+            addr,
+            word,
+            opcode,
+            mnemonic,
+            verbose,
+        }
+    }
+}
+
+impl fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04X}  {:04X}  {:<20} ; {}",
+            self.addr, self.word, self.mnemonic, self.verbose
+        )
+    }
+}
+
+/// Iterator decoding a memory region two bytes at a time, without collecting the listing.
+///
+/// ```
+/// use chippy_core::disassembler::Disassembly;
+///
+/// let rom = [0x00, 0xE0, 0x12, 0x00];
+/// let mnemonics: Vec<String> = Disassembly::new(&rom, 0, 4)
+///     .map(|line| line.mnemonic)
+///     .collect();
+///
+/// assert_eq!(mnemonics, ["CLS", "JP 0200"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Disassembly<'a> {
+    memory: &'a [u8],
+    addr: usize,
+    end: usize,
+}
+
+impl<'a> Disassembly<'a> {
+    /// # Arguments
+    ///
+    /// * `memory` - Memory to read instructions from.
+    /// * `start` - First address to decode.
+    /// * `end` - Address to stop at (exclusive), up to the size of memory.
+    ///
+    pub fn new(memory: &'a [u8], start: C8Addr, end: usize) -> Self {
+        Self {
+            memory,
+            addr: start as usize,
+            end,
+        }
+    }
+}
+
+impl Iterator for Disassembly<'_> {
+    type Item = DisasmLine;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.addr >= self.end {
+            return None;
+        }
+
+        let line = DisasmLine::decode(self.memory, self.addr as C8Addr);
+        self.addr += 2;
+        Some(line)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end.saturating_sub(self.addr).div_ceil(2);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Disassembly<'_> {}
+
+/// Disassemble a memory region two bytes at a time.
+/// See `Disassembly` to decode lazily.
+///
+/// # Arguments
+///
+/// * `memory` - Memory to read instructions from.
+/// * `start` - First address to decode.
+/// * `end` - Address to stop at (exclusive), up to the size of memory.
+///
+pub fn disassemble(memory: &[u8], start: C8Addr, end: usize) -> Vec<DisasmLine> {
+    Disassembly::new(memory, start, end).collect()
+}
+
+/// Collect every address which is the destination of a `JP` or `CALL`.
+/// These are good candidates for labels.
+pub fn jump_targets(lines: &[DisasmLine]) -> BTreeSet<C8Addr> {
+    lines
+        .iter()
+        .filter_map(|line| match line.opcode {
+            OpCode::JP(addr) | OpCode::CALL(addr) => Some(addr),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Produce a text listing of the loaded ROM, headed by its hash and detected variant.
+pub fn rom_listing<R: RngCore>(chip8: &Chip8<R>) -> String {
+    let mut listing = format!(
+        "; ROM hash: {:016X}\n; Variant: {}\n\n",
+        chip8.rom_hash(),
+        rom::detect_variant(chip8.rom())
+    );
+
+    let end = PROGRAM_ADDR + chip8.rom().len();
+    for line in Disassembly::new(&chip8.memory, 0x200, end) {
+        let _ = writeln!(listing, "{}", line);
+    }
+
+    listing
+}
+
+/// Line of `coverage_listing`, a decoded instruction and how the program used it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageLine {
+    pub line: DisasmLine,
+    /// Executed while coverage was recorded, otherwise likely data.
+    pub executed: bool,
+    /// Destination of a `JP` or `CALL`, labeled in the listing.
+    pub is_target: bool,
+}
+
+/// Name given to jump and call targets in `coverage_listing`.
+pub fn label(addr: C8Addr) -> String {
+    format!("L_{:04X}", addr)
+}
+
+/// Disassemble the loaded ROM along with the coverage recorded by `Chip8::set_coverage`.
+pub fn coverage_lines<R: RngCore>(chip8: &Chip8<R>) -> Vec<CoverageLine> {
+    let end = PROGRAM_ADDR + chip8.rom().len();
+    let lines = disassemble(&chip8.memory, 0x200, end);
+    let targets = jump_targets(&lines);
+
+    lines
+        .into_iter()
+        .map(|line| CoverageLine {
+            executed: chip8.executed(line.addr),
+            is_target: targets.contains(&line.addr),
+            line,
+        })
+        .collect()
+}
+
+/// Produce a text listing of the loaded ROM marking each line as code or data,
+/// with jump and call targets labeled. Run the ROM through its paths with coverage
+/// enabled first, lines which never ran are marked as data.
+pub fn coverage_listing<R: RngCore>(chip8: &Chip8<R>) -> String {
+    let lines = coverage_lines(chip8);
+    let executed = lines.iter().filter(|line| line.executed).count();
+
+    let mut listing = format!(
+        "; ROM hash: {:016X}\n; Variant: {}\n; Executed {} of {} words\n",
+        chip8.rom_hash(),
+        rom::detect_variant(chip8.rom()),
+        executed,
+        lines.len()
+    );
+
+    for CoverageLine {
+        line,
+        executed,
+        is_target,
+    } in &lines
+    {
+        if *is_target {
+            let _ = write!(listing, "\n{}:\n", label(line.addr));
+        }
+
+        let target = match line.opcode {
+            OpCode::JP(addr) | OpCode::CALL(addr) => format!(" (-> {})", label(addr)),
+            OpCode::JP0(addr) => format!(" (-> {:04X} + V0)", addr),
+            _ => String::new(),
+        };
+
+        let _ = writeln!(
+            listing,
+            "{:04X}  {:04X}  {:<20} ; {:<4}  {}{}",
+            line.addr,
+            line.word,
+            line.mnemonic,
+            if *executed { "code" } else { "data" },
+            line.verbose,
+            target
+        );
+    }
+
+    listing
+}
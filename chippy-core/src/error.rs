@@ -0,0 +1,36 @@
+use std::{error::Error, fmt};
+
+use crate::types::C8Addr;
+
+/// Error raised by an instruction, holding the PC of the instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// `CALL` with a full stack.
+    StackOverflow(C8Addr),
+    /// `RET` with an empty stack.
+    StackUnderflow(C8Addr),
+    /// `0000` followed by another, usually from running past the end of the program.
+    /// Only raised with `Chip8::trap_empty_memory` set.
+    EmptyMemory(C8Addr),
+}
+
+impl Chip8Error {
+    /// Address of the instruction which failed.
+    pub fn pc(&self) -> C8Addr {
+        match self {
+            Self::StackOverflow(pc) | Self::StackUnderflow(pc) | Self::EmptyMemory(pc) => *pc,
+        }
+    }
+}
+
+impl Error for Chip8Error {}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StackOverflow(pc) => write!(f, "Stack overflow at PC 0x{:X}", pc),
+            Self::StackUnderflow(pc) => write!(f, "Stack underflow at PC 0x{:X}", pc),
+            Self::EmptyMemory(pc) => write!(f, "Ran into empty memory at PC 0x{:X}", pc),
+        }
+    }
+}
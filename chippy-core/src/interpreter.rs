@@ -1,55 +1,78 @@
 use crate::opcode::{extract_opcode_from_array, OpCode};
-use crate::types::C8Addr;
-use crate::Chip8;
+use crate::types::{C8Addr, C8RegIdx};
+use crate::{Chip8, Chip8Error, StackPolicy, BIG_FONT_ADDR, MAX_CALL_DEPTH};
 
-use rand::Rng;
+use rand::{Rng, RngCore};
 
-impl Chip8 {
+impl<R: RngCore> Chip8<R> {
     /// Executes a single instruction using the interpreter.
-    pub fn interpreter(&mut self) {
+    /// On error the instruction has no effect and the program counter stays on it.
+    pub fn interpreter(&mut self) -> Result<(), Chip8Error> {
         // Should this advance the program counter by 2
         let mut advance_pointer = true;
 
-        let opcode = OpCode::from_opcode(extract_opcode_from_array(&self.memory, self.pc as usize));
+        let pc = self.pc;
+        let opcode = self.decoded.decode(&self.memory, pc as usize);
+
+        // Read before executing since the instruction may overwrite itself.
+        let word = self
+            .tracing()
+            .then(|| extract_opcode_from_array(&self.memory, pc as usize));
 
         match opcode {
+            OpCode::SYS(addr) => {
+                // Take the handler out so it can borrow the machine.
+                if let Some(mut handler) = self.sys_handler.take() {
+                    handler(addr, self);
+                    self.sys_handler.get_or_insert(handler);
+                }
+            }
             OpCode::CLS => self.clear_screen(),
+            // A single 0000 may well be data, a run of them is most likely unused memory.
+            OpCode::EMPTY
+                if self.trap_empty_memory
+                    && extract_opcode_from_array(&self.memory, pc as usize + 2) == 0 =>
+            {
+                return Err(Chip8Error::EmptyMemory(self.pc));
+            }
             OpCode::RET => {
+                if self.sp == 0 {
+                    match self.stack_policy {
+                        StackPolicy::Error => return Err(Chip8Error::StackUnderflow(self.pc)),
+                        StackPolicy::Wrap => self.sp = MAX_CALL_DEPTH,
+                        StackPolicy::Saturate => {}
+                    }
+                }
+
                 if self.sp > 0 {
                     self.sp -= 1;
-                    self.pc = self.stack[self.sp as usize];
-                } else {
-                    println!("Stack underflow (RET 0x00EE)");
+                    self.pc = self.stack[self.sp];
                 }
             }
             OpCode::JP(addr) => {
-                self.pc = addr;
+                self.pc = self.jump_target(addr);
                 advance_pointer = false;
             }
             OpCode::CALL(addr) => {
-                if self.sp < 15 {
-                    self.stack[self.sp as usize] = self.pc;
+                if self.sp >= MAX_CALL_DEPTH {
+                    match self.stack_policy {
+                        StackPolicy::Error => return Err(Chip8Error::StackOverflow(self.pc)),
+                        StackPolicy::Wrap => self.sp = 0,
+                        StackPolicy::Saturate => {}
+                    }
+                }
+
+                if self.sp < MAX_CALL_DEPTH {
+                    self.stack[self.sp] = self.pc;
                     self.sp += 1;
-                    self.pc = addr;
+                    self.pc = self.jump_target(addr);
                     advance_pointer = false;
-                } else {
-                    println!("Stack overflow (CALL 0x2nnn)");
-                }
-            }
-            OpCode::SEByte(reg, byte) => {
-                if self.registers[reg as usize] == byte {
-                    self.pc += 2;
-                }
-            }
-            OpCode::SNEByte(reg, byte) => {
-                if self.registers[reg as usize] != byte {
-                    self.pc += 2;
                 }
             }
+            OpCode::SEByte(reg, byte) => self.skip_if(self.registers[reg as usize] == byte),
+            OpCode::SNEByte(reg, byte) => self.skip_if(self.registers[reg as usize] != byte),
             OpCode::SE(reg1, reg2) => {
-                if self.registers[reg1 as usize] == self.registers[reg2 as usize] {
-                    self.pc += 2;
-                }
+                self.skip_if(self.registers[reg1 as usize] == self.registers[reg2 as usize]);
             }
             OpCode::LDByte(reg, byte) => {
                 self.registers[reg as usize] = byte;
@@ -62,12 +85,21 @@ impl Chip8 {
             }
             OpCode::OR(reg1, reg2) => {
                 self.registers[reg1 as usize] |= self.registers[reg2 as usize];
+                if self.quirks.reset_vf_on_logic {
+                    self.registers[15] = 0;
+                }
             }
             OpCode::AND(reg1, reg2) => {
                 self.registers[reg1 as usize] &= self.registers[reg2 as usize];
+                if self.quirks.reset_vf_on_logic {
+                    self.registers[15] = 0;
+                }
             }
             OpCode::XOR(reg1, reg2) => {
                 self.registers[reg1 as usize] ^= self.registers[reg2 as usize];
+                if self.quirks.reset_vf_on_logic {
+                    self.registers[15] = 0;
+                }
             }
             OpCode::ADD(reg1, reg2) => {
                 let r1 = self.registers[reg1 as usize];
@@ -88,7 +120,7 @@ impl Chip8 {
                 let r2 = self.registers[reg2 as usize];
                 let res = r1.wrapping_sub(r2);
 
-                if r1 > r2 {
+                if r1 > r2 || (r1 == r2 && !self.quirks.strict_sub_flag) {
                     self.registers[15] = 1;
                 } else {
                     self.registers[15] = 0;
@@ -96,8 +128,8 @@ impl Chip8 {
 
                 self.registers[reg1 as usize] = res;
             }
-            OpCode::SHR(reg, _) => {
-                let r = self.registers[reg as usize];
+            OpCode::SHR(reg, reg2) => {
+                let r = self.registers[self.shift_source(reg, reg2) as usize];
 
                 if r & 1 == 1 {
                     self.registers[15] = 1
@@ -112,7 +144,7 @@ impl Chip8 {
                 let r2 = self.registers[reg2 as usize];
                 let res = r2.wrapping_sub(r1);
 
-                if r2 > r1 {
+                if r2 > r1 || (r1 == r2 && !self.quirks.strict_sub_flag) {
                     self.registers[15] = 1
                 } else {
                     self.registers[15] = 0;
@@ -120,8 +152,8 @@ impl Chip8 {
 
                 self.registers[reg1 as usize] = res;
             }
-            OpCode::SHL(reg, _) => {
-                let r = self.registers[reg as usize];
+            OpCode::SHL(reg, reg2) => {
+                let r = self.registers[self.shift_source(reg, reg2) as usize];
                 let msb = 1 << 7;
 
                 if r & msb == msb {
@@ -136,45 +168,41 @@ impl Chip8 {
                 let r1 = self.registers[reg1 as usize];
                 let r2 = self.registers[reg2 as usize];
 
-                if r1 != r2 {
-                    self.pc += 2;
-                }
+                self.skip_if(r1 != r2);
             }
             OpCode::LDI(addr) => self.index = addr,
-            OpCode::JP0(addr) => self.pc = addr + self.registers[0] as C8Addr,
+            OpCode::JP0(addr) => {
+                let reg = if self.quirks.jump_with_vx {
+                    (addr >> 8) as usize & 0xF
+                } else {
+                    0
+                };
+
+                // The target can pass the end of memory, wrap it like I.
+                let target = addr + self.registers[reg] as C8Addr;
+                self.pc = self.jump_target(target) & self.index_mask();
+            }
             OpCode::RND(reg, byte) => {
-                self.registers[reg as usize] = rand::thread_rng().gen_range(0..256) as u8 & byte;
+                self.registers[reg as usize] = self.rng.gen_range(0..256) as u8 & byte;
             }
             OpCode::DRW(reg1, reg2, byte) => {
                 self.draw_sprite(reg1 as usize, reg2 as usize, byte);
+                self.frame_draws += 1;
             }
             OpCode::SKP(reg) => {
-                if self.keypad.keys[self.registers[reg as usize] as usize] {
-                    self.pc += 2;
-                }
+                self.skip_if(self.keypad.keys[self.registers[reg as usize] as usize]);
             }
             OpCode::SKNP(reg) => {
-                if !self.keypad.keys[self.registers[reg as usize] as usize] {
-                    self.pc += 2;
-                }
+                self.skip_if(!self.keypad.keys[self.registers[reg as usize] as usize]);
             }
             OpCode::LDGetDelayTimer(reg) => {
                 self.registers[reg as usize] = self.delay_timer;
             }
-            OpCode::LDGetKey(reg) => {
-                let mut pressed = false;
-                for i in 0..15 {
-                    if self.keypad.keys[i as usize] {
-                        self.registers[reg as usize] = i as u8;
-                        pressed = true;
-                    }
-                }
-
-                if !pressed {
-                    // We don't want to iterate until key was pressed.
-                    advance_pointer = false;
-                }
-            }
+            OpCode::LDGetKey(reg) => match self.keypad.any_pressed() {
+                Some(key) => self.registers[reg as usize] = key,
+                // We don't want to iterate until key was pressed.
+                None => advance_pointer = false,
+            },
             OpCode::LDSetDelayTimer(reg) => {
                 self.delay_timer = self.registers[reg as usize];
             }
@@ -182,29 +210,43 @@ impl Chip8 {
                 self.sound_timer = self.registers[reg as usize];
             }
             OpCode::ADDI(reg) => {
-                self.index += self.registers[reg as usize] as C8Addr;
+                let sum = self.index as usize + self.registers[reg as usize] as usize;
+                let mask = self.index_mask() as usize;
+                self.index = (sum & mask) as C8Addr;
+
+                if self.quirks.index_overflow_vf {
+                    self.registers[15] = (sum > mask) as u8;
+                }
             }
             OpCode::LDSprite(reg) => {
                 self.index = self.registers[reg as usize] as u16 * 5;
             }
+            OpCode::LDBigSprite(reg) => {
+                self.index = BIG_FONT_ADDR as u16 + self.registers[reg as usize] as u16 * 10;
+            }
             OpCode::LDBCD(reg) => {
                 let reg = self.registers[reg as usize];
 
-                self.memory[self.index as usize] = reg / 100;
-                self.memory[(self.index + 1) as usize] =
-                    (reg - self.memory[self.index as usize] * 100) / 10;
-                self.memory[(self.index + 2) as usize] = (reg
-                    - self.memory[self.index as usize] * 100)
-                    - self.memory[(self.index + 1) as usize] * 10;
+                self.write_memory(self.index_addr(0), reg / 100);
+                self.write_memory(self.index_addr(1), reg / 10 % 10);
+                self.write_memory(self.index_addr(2), reg % 10);
             }
             OpCode::LDS(reg) => {
                 for i in 0..=reg as usize {
-                    self.memory[self.index as usize + i] = self.registers[i];
+                    self.write_memory(self.index_addr(i), self.registers[i]);
+                }
+
+                if self.quirks.load_store_increments_i {
+                    self.index = self.index_addr(reg as usize + 1) as C8Addr;
                 }
             }
             OpCode::LDR(reg) => {
                 for i in 0..=reg as usize {
-                    self.registers[i] = self.memory[self.index as usize + i];
+                    self.registers[i] = self.read_memory(self.index_addr(i));
+                }
+
+                if self.quirks.load_store_increments_i {
+                    self.index = self.index_addr(reg as usize + 1) as C8Addr;
                 }
             }
             _ => {
@@ -213,9 +255,55 @@ impl Chip8 {
         };
 
         if advance_pointer {
-            self.pc += 2;
+            self.advance();
+        }
+
+        // Programs may do this on purpose, so it's only reported.
+        if self.warn_misaligned_pc && pc.is_multiple_of(2) && !self.pc.is_multiple_of(2) {
+            log::warn!(
+                "Instruction at {:X} moved the PC to odd address {:X}",
+                pc,
+                self.pc
+            );
+            self.misaligned_jumps += 1;
+        }
+
+        self.cycle_count += 1;
+        self.cover(pc as usize, 1);
+        if let Some(word) = word {
+            self.trace(pc, word, opcode);
+        }
+
+        Ok(())
+    }
+
+    /// Move the PC to the next instruction, wrapping at the end of memory.
+    fn advance(&mut self) {
+        self.pc = self.pc.wrapping_add(2) & self.index_mask();
+    }
+
+    /// Skip the next instruction if `condition` holds.
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.advance();
         }
+    }
 
-        self.timer();
+    /// Register `SHR` and `SHL` read from, see `Quirks::shift_uses_vy`.
+    fn shift_source(&self, x: C8RegIdx, y: C8RegIdx) -> C8RegIdx {
+        if self.quirks.shift_uses_vy {
+            y
+        } else {
+            x
+        }
+    }
+
+    /// Address a jump to `addr` lands on, see `Quirks::align_jumps`.
+    fn jump_target(&self, addr: C8Addr) -> C8Addr {
+        if self.quirks.align_jumps {
+            addr & !1
+        } else {
+            addr
+        }
     }
 }
@@ -0,0 +1,63 @@
+//! Memory mapped I/O for experimenting with custom peripherals.
+//!
+//! Instructions which read or write memory (`DRW`, `LD B, Vx`, `LD [I], Vx` and `LD Vx, [I]`)
+//! go through the handler for addresses in its range instead of the backing memory.
+//! Instruction fetches always read the backing memory.
+//!
+//! Every access made by those instructions checks the range, so mapping has a small cost
+//! even for addresses outside of it. Nothing is checked while no handler is set.
+
+use std::ops::RangeInclusive;
+
+use rand::RngCore;
+
+use crate::{
+    types::{C8Addr, C8Byte},
+    Chip8,
+};
+
+/// Peripheral mapped to a range of memory, see `Chip8::set_io_handler`.
+pub trait IoHandler: Send + Sync {
+    /// Value read from a mapped address.
+    fn read(&mut self, addr: C8Addr) -> C8Byte;
+    /// Value written to a mapped address.
+    fn write(&mut self, addr: C8Addr, value: C8Byte);
+}
+
+/// Handler with the addresses it's mapped to.
+pub(crate) struct MappedIo {
+    range: RangeInclusive<C8Addr>,
+    handler: Box<dyn IoHandler>,
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Map `range` to `handler`, replacing any previous handler. `None` unmaps it.
+    pub fn set_io_handler(&mut self, io: Option<(RangeInclusive<C8Addr>, Box<dyn IoHandler>)>) {
+        self.io = io.map(|(range, handler)| MappedIo { range, handler });
+    }
+
+    /// Read memory the way instructions do, including mapped I/O.
+    pub fn read_memory(&mut self, addr: usize) -> C8Byte {
+        match &mut self.io {
+            Some(io) if io.range.contains(&(addr as C8Addr)) => io.handler.read(addr as C8Addr),
+            _ => self.memory[addr],
+        }
+    }
+
+    /// Write memory the way instructions do, including mapped I/O.
+    pub fn write_memory(&mut self, addr: usize, value: C8Byte) {
+        if self.tracing() {
+            self.traced_writes.push((addr as C8Addr, value));
+        }
+
+        match &mut self.io {
+            Some(io) if io.range.contains(&(addr as C8Addr)) => {
+                io.handler.write(addr as C8Addr, value)
+            }
+            _ => {
+                self.log_write(addr, value);
+                self.memory[addr] = value;
+            }
+        }
+    }
+}
@@ -1,600 +1,252 @@
-// use crate::opcode::Opcode;
-// use crate::Address;
-// use crate::Chip8;
-
-// use dynasmrt::{dynasm, x64::Assembler, DynasmApi, DynasmLabelApi};
-
-// #[derive(Debug)]
-// pub enum Interrupts {
-//     UseInterpreter = 1,
-//     Jump = 2,
-// }
-
-// impl Interrupts {
-//     pub fn make(int: Interrupts, arg: u16) -> u32 {
-//         (int as u32) << 16 | arg as u32
-//     }
-// }
-
-// impl From<u32> for Interrupts {
-//     fn from(i: u32) -> Self {
-//         match i {
-//             1 => Self::UseInterpreter,
-//             2 => Self::Jump,
-//             _ => panic!(),
-//         }
-//     }
-// }
-
-// impl Chip8 {
-//     /// Executes a block of instructions using the JIT compiler.
-//     pub fn jit(&mut self) {
-//         if self.caches.get(self.pc).is_none() {
-//             self.compile_block(self.pc);
-//         }
-
-//         let ret = self.caches.get(self.pc).unwrap().run();
-//         match Interrupts::from(ret >> 16) {
-//             Interrupts::UseInterpreter => {
-//                 self.pc = ret as u16;
-//                 self.interpreter();
-//             }
-//             Interrupts::Jump => self.pc = ret as u16,
-//         }
-//     }
-
-//     // TODO: for self-modifying code, store the memory range used by each cache, and when writing to this location,
-//     // invalidate the cache with an interrupt, perform the assignment in interpreter, then recompile the block.
-//     /// Uses the RAX, RCX and RDX (caller-saved) registers.
-//     ///
-//     /// EAX contains the return value of the block. RAX, RCX and RDX are used internally by the compiled code.
-//     fn compile_block(&mut self, mut pc: u16) {
-//         let block_pc = pc;
-//         let mut asm = Assembler::new().expect("Failed to create new assembler");
-
-//         let timer = Self::timer as *const ();
-//         let this = self as *mut Chip8;
-
-//         #[cfg(target_os = "windows")]
-//         dynasm!(asm
-//             ; .arch x64
-//             ; mov rax, QWORD timer as i64
-//             ; mov rcx, QWORD this as i64
-//             ; call rax
-//         );
-
-//         #[cfg(not(target_os = "windows"))]
-//         dynasm!(asm
-//             ; .arch x64
-//             ; mov rax, QWORD timer as i64
-//             ; push rdi
-//             ; mov rdi, QWORD this as i64
-//             ; call rax
-//             ; pop rdi
-//         );
-
-//         'outer: loop {
-//             let opcode = Opcode::from(
-//                 (self.memory[pc as usize] as u16) << 8 | self.memory[pc as usize + 1] as u16,
-//             );
-
-//             #[cfg(debug_assertions)]
-//             println!("Compiling opcode {:#04X} at {:#X}", opcode, pc);
-
-//             match opcode.u16() >> 12 & 0xF {
-//                 0x0 => match opcode.u16() {
-//                     0x00E0 => {
-//                         let addr_screen = self.screen.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addr_screen
-//                             ; mov rax, rdx
-//                             ; add rax, 64 * 32
-//                             ; lbl:
-//                             ; mov QWORD [rdx], 0
-//                             ; add rdx, 8
-//                             ; cmp rdx, rax
-//                             ; jb <lbl
-//                         );
-//                     }
-//                     0x00EE => {
-//                         let sp = self.sp.address(0);
-//                         let stack = self.stack.address(0);
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD sp as i64
-//                             ; cmp QWORD [rdx], 0
-//                             ; ja >lbl
-//                             ; mov eax, DWORD Interrupts::make(Interrupts::UseInterpreter, pc) as i32
-//                             ; ret
-//                             ; lbl:
-//                             ; dec QWORD [rdx]
-//                             ; mov rax, QWORD [rdx]
-//                             ; shl rax, 1
-//                             ; mov rcx, QWORD stack as i64
-//                             ; add rcx, rax
-//                             ; mov eax, DWORD Interrupts::make(Interrupts::Jump, 0) as i32
-//                             ; mov ax, WORD [rcx]
-//                         );
-//                         break 'outer;
-//                     }
-//                     _ => panic!("Unknown opcode {:04X}", opcode),
-//                 },
-//                 0x1 => {
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, opcode.nnn()) as i32
-//                     );
-//                     break 'outer;
-//                 }
-//                 0x2 => {
-//                     let sp = self.sp.address(0);
-//                     let stack = self.stack.address(0);
-//                     let nnn = opcode.nnn();
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD sp as i64
-//                         ; mov rax, QWORD [rdx]
-//                         ; cmp rax, 15
-//                         ; jb >lbl
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::UseInterpreter, pc) as i32
-//                         ; ret
-//                         ; lbl:
-//                         ; shl rax, 1
-//                         ; mov rcx, QWORD stack as i64
-//                         ; add rcx, rax
-//                         ; mov WORD [rcx], (pc + 2) as i16
-//                         ; inc QWORD [rdx]
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, 0) as i32
-//                         ; mov ax, WORD nnn as i16
-//                     );
-//                     break 'outer;
-//                 }
-//                 0x3 => {
-//                     let (x, kk) = opcode.xkk();
-//                     let addrx = self.registers.address(x) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addrx
-//                         ; mov al, BYTE [rdx]
-//                         ; cmp al, kk as i8
-//                         ; jne >lbl
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, pc + 4) as i32
-//                         ; ret
-//                         ; lbl:
-//                     );
-//                 }
-//                 0x4 => {
-//                     let (x, kk) = opcode.xkk();
-//                     let addrx = self.registers.address(x) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addrx
-//                         ; mov al, BYTE [rdx]
-//                         ; cmp al, kk as i8
-//                         ; je >lbl
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, pc + 4) as i32
-//                         ; ret
-//                         ; lbl:
-//                     );
-//                 }
-//                 0x5 => {
-//                     let (x, y) = opcode.xy();
-//                     let addrx = self.registers.address(x) as i64;
-//                     let addry = self.registers.address(y) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addry
-//                         ; mov al, BYTE [rdx]
-//                         ; mov rdx, QWORD addrx
-//                         ; cmp BYTE [rdx], al
-//                         ; jne >lbl
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, pc + 4) as i32
-//                         ; ret
-//                         ; lbl:
-//                     );
-//                 }
-//                 0x6 => {
-//                     let (x, kk) = opcode.xkk();
-//                     let addr = self.registers.address(x) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addr
-//                         ; mov BYTE [rdx], kk as i8
-//                     );
-//                 }
-//                 0x7 => {
-//                     let (x, kk) = opcode.xkk();
-//                     let addr = self.registers.address(x) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addr
-//                         ; add BYTE [rdx], kk as i8
-//                     );
-//                 }
-//                 0x8 => {
-//                     match opcode.u16() & 0xF00F {
-//                         0x8000 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; mov BYTE [rdx], al
-//                             );
-//                         }
-//                         0x8001 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; or BYTE [rdx], al
-//                             );
-//                         }
-//                         0x8002 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; and BYTE [rdx], al
-//                             );
-//                         }
-//                         0x8003 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; xor BYTE [rdx], al
-//                             );
-//                         }
-//                         0x8004 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             let addrf = self.registers.address(0xF) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; add BYTE [rdx], al
-//                                 ; mov rdx, QWORD addrf
-//                                 ; setc BYTE [rdx]
-//                             );
-//                         }
-//                         0x8005 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             let addrf = self.registers.address(0xF) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; sub BYTE [rdx], al
-//                                 ; mov rdx, QWORD addrf
-//                                 ; setnc BYTE [rdx]
-//                             );
-//                         }
-//                         0x8006 => {
-//                             let x = opcode.x();
-//                             // let y = opcode.y();
-//                             let addrx = self.registers.address(x) as i64;
-//                             // let addry = self.registers.address(y) as i64;
-//                             let addrf = self.registers.address(0xF) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addrx
-//                                 ; shr BYTE [rdx], 1
-//                                 ; mov rdx, QWORD addrf
-//                                 ; setc BYTE [rdx]
-//                             );
-//                         }
-//                         0x8007 => {
-//                             let (x, y) = opcode.xy();
-//                             let addrx = self.registers.address(x) as i64;
-//                             let addry = self.registers.address(y) as i64;
-//                             let addrf = self.registers.address(0xF) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addry
-//                                 ; mov al, BYTE [rdx]
-//                                 ; mov rdx, QWORD addrx
-//                                 ; mov ah, BYTE [rdx]
-//                                 ; sub al, ah
-//                                 ; mov BYTE [rdx], al
-//                                 ; mov rdx, QWORD addrf
-//                                 ; setnc BYTE [rdx]
-//                             );
-//                         }
-//                         0x800E => {
-//                             let x = opcode.x();
-//                             // let y = opcode.y();
-//                             let addrx = self.registers.address(x) as i64;
-//                             // let addry = self.registers.address(y) as i64;
-//                             let addrf = self.registers.address(0xF) as i64;
-//                             dynasm!(asm
-//                                 ; .arch x64
-//                                 ; mov rdx, QWORD addrx
-//                                 ; shl BYTE [rdx], 1
-//                                 ; mov rdx, QWORD addrf
-//                                 ; setc BYTE [rdx]
-//                             );
-//                         }
-//                         _ => panic!("Unknown opcode {:04X}", opcode),
-//                     }
-//                 }
-//                 0x9 => {
-//                     let (x, y) = opcode.xy();
-//                     let addrx = self.registers.address(x) as i64;
-//                     let addry = self.registers.address(y) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addry
-//                         ; mov al, BYTE [rdx]
-//                         ; mov rdx, QWORD addrx
-//                         ; cmp BYTE [rdx], al
-//                         ; je >lbl
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, pc + 4) as i32
-//                         ; ret
-//                         ; lbl:
-//                     );
-//                 }
-//                 0xA => {
-//                     let nnn = opcode.nnn();
-//                     let addr = self.index.address(0) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov rdx, QWORD addr
-//                         ; mov WORD [rdx], nnn as i16
-//                     );
-//                 }
-//                 0xB => {
-//                     let nnn = opcode.nnn();
-//                     let addr0 = self.registers.address(0) as i64;
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::Jump, nnn) as i32
-//                         ; mov rdx, QWORD addr0
-//                         ; movzx edx, BYTE [rdx]
-//                         ; add eax, edx
-//                     );
-//                     break 'outer;
-//                 }
-//                 0xC => {
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::UseInterpreter, pc) as i32
-//                     );
-//                     break 'outer;
-//                 }
-//                 0xD => {
-//                     dynasm!(asm
-//                         ; .arch x64
-//                         ; mov eax, DWORD Interrupts::make(Interrupts::UseInterpreter, pc) as i32
-//                     );
-//                     break 'outer;
-//                 }
-//                 0xE => match opcode.u16() & 0xF0FF {
-//                     0xE09E => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addr_keys = self.keypad.keys.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrx
-//                             ; movzx rax, BYTE [rdx]
-//                             ; mov rdx, QWORD addr_keys
-//                             ; add rdx, rax
-//                             ; mov al, BYTE [rdx]
-//                             ; cmp al, 0
-//                             ; je >lbl
-//                             ; mov eax, DWORD Interrupts::make(Interrupts::Jump, pc + 4) as i32
-//                             ; ret
-//                             ; lbl:
-//                         );
-//                     }
-//                     0xE0A1 => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addr_keys = self.keypad.keys.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrx
-//                             ; movzx rax, BYTE [rdx]
-//                             ; mov rdx, QWORD addr_keys
-//                             ; add rdx, rax
-//                             ; mov al, BYTE [rdx]
-//                             ; cmp al, 0
-//                             ; jne >lbl
-//                             ; mov eax, DWORD Interrupts::make(Interrupts::Jump, pc + 4) as i32
-//                             ; ret
-//                             ; lbl:
-//                         );
-//                     }
-//                     _ => panic!("Unknown opcode {:04X}", opcode),
-//                 },
-//                 0xF => match opcode.u16() & 0xF0FF {
-//                     0xF007 => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addrdt = self.delay_timer.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrdt
-//                             ; mov al, BYTE [rdx]
-//                             ; mov rdx, QWORD addrx
-//                             ; mov BYTE [rdx], al
-//                         );
-//                     }
-//                     0xF00A => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addr_last_key = self.keypad.last_pressed.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addr_last_key
-//                             ; mov BYTE [rdx], 255 as _
-//                             ; lbl:
-//                             ; cmp BYTE [rdx], 15
-//                             ; ja <lbl
-//                             ; mov al, BYTE [rdx]
-//                             ; mov rdx, QWORD addrx
-//                             ; mov BYTE [rdx], al
-//                         );
-//                     }
-//                     0xF015 => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addrdt = self.delay_timer.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrx
-//                             ; mov al, BYTE [rdx]
-//                             ; mov rdx, QWORD addrdt
-//                             ; mov BYTE [rdx], al
-//                         );
-//                     }
-//                     0xF018 => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addr_sound_timer = self.sound_timer.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrx
-//                             ; mov al, BYTE [rdx]
-//                             ; mov rdx, QWORD addr_sound_timer
-//                             ; mov BYTE [rdx], al
-//                         );
-//                     }
-//                     0xF01E => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addri = self.index.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrx
-//                             ; movzx ax, BYTE [rdx]
-//                             ; mov rdx, QWORD addri
-//                             ; add WORD [rdx], ax
-//                         );
-//                     }
-//                     0xF029 => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addri = self.index.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addrx
-//                             ; mov al, BYTE [rdx]
-//                             ; mov dl, 5
-//                             ; mul dl
-//                             ; mov rdx, QWORD addri
-//                             ; mov WORD [rdx], ax
-//                         );
-//                     }
-//                     0xF033 => {
-//                         let x = opcode.x();
-//                         let addrx = self.registers.address(x) as i64;
-//                         let addri = self.index.address(0) as i64;
-//                         let addrmem = self.memory.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addri
-//                             ; movzx rdx, WORD [rdx]
-//                             ; mov rax, QWORD addrmem
-//                             ; add rdx, rax
-//                             ; mov rax, QWORD addrx
-//                             ; movzx ax, BYTE [rax]
-//                             ; mov cl, 100
-//                             ; div cl
-//                             ; mov BYTE [rdx], al
-//                             ; movzx ax, ah
-//                             ; mov cl, 10
-//                             ; div cl
-//                             ; mov BYTE [rdx + 1], al
-//                             ; mov BYTE [rdx + 2], ah
-//                         );
-//                     }
-//                     0xF055 => {
-//                         let x = opcode.x();
-//                         let addr0 = self.registers.address(0) as i64;
-//                         let addrlast = self.registers.address(x) as i64;
-//                         let addri = self.index.address(0) as i64;
-//                         let addrmem = self.memory.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addri
-//                             ; movzx rdx, WORD [rdx]
-//                             ; mov rax, QWORD addrmem
-//                             ; add rdx, rax
-//                             ; mov rax, QWORD addr0
-//                             ; lbl:
-//                             ; mov cl, BYTE [rax]
-//                             ; mov BYTE [rdx], cl
-//                             ; mov rcx, QWORD addrlast
-//                             ; cmp rax, rcx
-//                             ; jae >end
-//                             ; inc rax
-//                             ; inc rdx
-//                             ; jmp <lbl
-//                             ; end:
-//                         );
-//                     }
-//                     0xF065 => {
-//                         let x = opcode.x();
-//                         let addr0 = self.registers.address(0) as i64;
-//                         let addrlast = self.registers.address(x) as i64;
-//                         let addri = self.index.address(0) as i64;
-//                         let addrmem = self.memory.address(0) as i64;
-//                         dynasm!(asm
-//                             ; .arch x64
-//                             ; mov rdx, QWORD addri
-//                             ; movzx rdx, WORD [rdx]
-//                             ; mov rax, QWORD addrmem
-//                             ; add rdx, rax
-//                             ; mov rax, QWORD addr0
-//                             ; lbl:
-//                             ; mov cl, BYTE [rdx]
-//                             ; mov BYTE [rax], cl
-//                             ; mov rcx, QWORD addrlast
-//                             ; cmp rax, rcx
-//                             ; jae >end
-//                             ; inc rax
-//                             ; inc rdx
-//                             ; jmp <lbl
-//                             ; end:
-//                         );
-//                     }
-//                     _ => panic!("Unknown opcode {:04X}", opcode),
-//                 },
-//                 _ => panic!("Unknown opcode {:04X}", opcode),
-//             };
-
-//             pc += 2;
-//         }
-
-//         dynasm!(asm
-//             ; .arch x64
-//             ; ret
-//         );
-
-//         self.caches.add(block_pc, asm.finalize().unwrap());
-//     }
-// }
+//! Basic block JIT for x86-64.
+//!
+//! Straight-line runs of register arithmetic and index loads are compiled to native code.
+//! A block ends at the first instruction which branches, touches memory, the screen, timers,
+//! the keypad or randomness, which the interpreter then executes.
+//!
+//! Blocks keep a copy of the memory they were compiled from and are recompiled when it changes,
+//! so self-modifying code and writes from the debugger are picked up.
+
+use dynasmrt::{dynasm, x64::Assembler, DynasmApi};
+use rand::RngCore;
+
+use crate::{
+    cache::Block,
+    opcode::{extract_opcode_from_array, OpCode},
+    types::{C8Addr, C8RegIdx},
+    Chip8, Quirks,
+};
+
+/// Longest block compiled, so blocks still fit small cycle budgets.
+const MAX_BLOCK: usize = 16;
+
+impl<R: RngCore> Chip8<R> {
+    /// Run the compiled block at the program counter, compiling it first if needed.
+    ///
+    /// Returns the cycles spent, `None` if the instruction should be interpreted instead.
+    /// This is the case when the block doesn't fit in `budget` or a breakpoint is inside it.
+    pub(crate) fn run_block(&mut self, budget: usize) -> Option<usize> {
+        let pc = self.pc as usize;
+
+        if self
+            .blocks
+            .get(pc)
+            .is_none_or(|block| block.is_stale(&self.memory, pc))
+        {
+            self.blocks
+                .insert(pc, compile(&self.memory, pc, self.quirks));
+        }
+
+        let block = self.blocks.get(pc)?;
+        let len = block.len;
+        let end = pc + len * 2;
+        if len == 0 || len > budget || end > self.memory.len() {
+            return None;
+        }
+
+        // The breakpoint at the program counter itself was already checked.
+        let inside = |addr: &C8Addr| (*addr as usize) < end;
+        if self
+            .breakpoints
+            .range(self.pc + 1..)
+            .next()
+            .is_some_and(|(addr, _)| inside(addr))
+            || self
+                .temporary_breakpoints
+                .range(self.pc + 1..)
+                .next()
+                .is_some_and(inside)
+        {
+            return None;
+        }
+
+        // SAFETY: Both pointers are to fields of `self`, which outlives the call.
+        unsafe { block.run(self.registers.as_mut_ptr(), &mut self.index) };
+
+        // A block ending with the last word of memory wraps the PC around.
+        self.pc = (end & self.index_mask() as usize) as C8Addr;
+        self.cycle_count += len as u64;
+        self.cover(pc, len);
+
+        Some(len)
+    }
+}
+
+/// Compile instructions starting at `addr` until one which can't be compiled
+/// or the end of memory.
+fn compile(memory: &[u8], addr: usize, quirks: Quirks) -> Block {
+    let mut ops = Assembler::new().expect("Unable to create assembler");
+    let mut len = 0;
+
+    while len < MAX_BLOCK
+        && addr + len * 2 + 2 <= memory.len()
+        && emit(
+            &mut ops,
+            OpCode::from_opcode(extract_opcode_from_array(memory, addr + len * 2)),
+            quirks,
+            (memory.len() - 1) as u16,
+        )
+    {
+        len += 1;
+    }
+
+    // Keep the first instruction even if it can't be compiled so changes to it are noticed.
+    let source = memory[addr..(addr + len.max(1) * 2).min(memory.len())].to_vec();
+
+    if len == 0 {
+        return Block::new(source, 0, None);
+    }
+
+    dynasm!(ops
+        ; .arch x64
+        ; ret
+    );
+
+    Block::new(
+        source,
+        len,
+        Some(ops.finalize().expect("Unable to finalize block")),
+    )
+}
+
+/// Emit native code for an instruction, following the interpreter's behavior.
+/// `rdi` points to V0 and `rsi` to the index register, which wraps at `index_mask`.
+///
+/// Returns false if the instruction can't be compiled.
+fn emit(ops: &mut Assembler, opcode: OpCode, quirks: Quirks, index_mask: u16) -> bool {
+    match opcode {
+        OpCode::LDByte(x, kk) => dynasm!(ops
+            ; .arch x64
+            ; mov BYTE [rdi + x as i32], kk as i8
+        ),
+        OpCode::ADDByte(x, kk) => dynasm!(ops
+            ; .arch x64
+            ; add BYTE [rdi + x as i32], kk as i8
+        ),
+        OpCode::LD(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + y as i32]
+            ; mov BYTE [rdi + x as i32], al
+        ),
+        OpCode::OR(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + y as i32]
+            ; or BYTE [rdi + x as i32], al
+        ),
+        OpCode::AND(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + y as i32]
+            ; and BYTE [rdi + x as i32], al
+        ),
+        OpCode::XOR(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + y as i32]
+            ; xor BYTE [rdi + x as i32], al
+        ),
+        // VF is written before the result, so the result wins when x is F.
+        OpCode::ADD(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + x as i32]
+            ; add al, BYTE [rdi + y as i32]
+            ; setc BYTE [rdi + 0xF]
+            ; mov BYTE [rdi + x as i32], al
+        ),
+        OpCode::SUB(x, y) => emit_sub(ops, x, x, y, quirks),
+        OpCode::SUBN(x, y) => emit_sub(ops, x, y, x, quirks),
+        OpCode::SHR(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + shift_source(x, y, quirks) as i32]
+            ; mov cl, al
+            ; and cl, 1
+            ; mov BYTE [rdi + 0xF], cl
+            ; shr al, 1
+            ; mov BYTE [rdi + x as i32], al
+        ),
+        OpCode::SHL(x, y) => dynasm!(ops
+            ; .arch x64
+            ; mov al, BYTE [rdi + shift_source(x, y, quirks) as i32]
+            ; mov cl, al
+            ; shr cl, 7
+            ; mov BYTE [rdi + 0xF], cl
+            ; shl al, 1
+            ; mov BYTE [rdi + x as i32], al
+        ),
+        OpCode::LDI(nnn) => dynasm!(ops
+            ; .arch x64
+            ; mov WORD [rsi], nnn as i16
+        ),
+        // VF is written after I is summed, so Vx is read first even when x is F.
+        OpCode::ADDI(x) if quirks.index_overflow_vf => dynasm!(ops
+            ; .arch x64
+            ; movzx eax, BYTE [rdi + x as i32]
+            ; movzx ecx, WORD [rsi]
+            ; add eax, ecx
+            ; cmp eax, index_mask as i32
+            ; seta BYTE [rdi + 0xF]
+            ; and eax, index_mask as i32
+            ; mov WORD [rsi], ax
+        ),
+        OpCode::ADDI(x) => dynasm!(ops
+            ; .arch x64
+            ; movzx ax, BYTE [rdi + x as i32]
+            ; add WORD [rsi], ax
+            ; and WORD [rsi], index_mask as i16
+        ),
+        OpCode::LDSprite(x) => dynasm!(ops
+            ; .arch x64
+            ; movzx ax, BYTE [rdi + x as i32]
+            ; imul ax, ax, 5
+            ; mov WORD [rsi], ax
+        ),
+        _ => return false,
+    }
+
+    // After the result, so VF is cleared even when x is F.
+    if quirks.reset_vf_on_logic
+        && matches!(opcode, OpCode::OR(..) | OpCode::AND(..) | OpCode::XOR(..))
+    {
+        dynasm!(ops
+            ; .arch x64
+            ; mov BYTE [rdi + 0xF], 0
+        );
+    }
+
+    true
+}
+
+/// Register `SHR` and `SHL` read from, see `Quirks::shift_uses_vy`.
+fn shift_source(x: C8RegIdx, y: C8RegIdx, quirks: Quirks) -> C8RegIdx {
+    if quirks.shift_uses_vy {
+        y
+    } else {
+        x
+    }
+}
+
+/// Emit `dest = left - right`, setting VF the way `SUB` and `SUBN` do.
+fn emit_sub(ops: &mut Assembler, dest: C8RegIdx, left: C8RegIdx, right: C8RegIdx, quirks: Quirks) {
+    dynasm!(ops
+        ; .arch x64
+        ; mov al, BYTE [rdi + left as i32]
+        ; mov cl, BYTE [rdi + right as i32]
+        ; cmp al, cl
+    );
+
+    if quirks.strict_sub_flag {
+        dynasm!(ops
+            ; .arch x64
+            ; seta BYTE [rdi + 0xF]
+        );
+    } else {
+        dynasm!(ops
+            ; .arch x64
+            ; setae BYTE [rdi + 0xF]
+        );
+    }
+
+    dynasm!(ops
+        ; .arch x64
+        ; sub al, cl
+        ; mov BYTE [rdi + dest as i32], al
+    );
+}
@@ -3,3 +3,24 @@ pub struct Keypad {
     pub keys: [bool; 16],
     pub last_pressed: u8,
 }
+
+impl Keypad {
+    /// Press or release a key, `key` is 0-F.
+    pub fn set(&mut self, key: u8, pressed: bool) {
+        self.keys[key as usize & 0xF] = pressed;
+        self.last_pressed = key & 0xF;
+    }
+
+    /// Lowest pressed key, `None` if no key is pressed.
+    pub fn any_pressed(&self) -> Option<u8> {
+        self.keys
+            .iter()
+            .position(|pressed| *pressed)
+            .map(|key| key as u8)
+    }
+
+    /// Release all keys, for when key up events can no longer arrive.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -1,26 +1,76 @@
+pub mod audio;
+pub mod breakpoint;
+#[cfg(target_arch = "x86_64")]
+mod cache;
+pub mod clock;
+pub mod coverage;
+pub mod disassembler;
+mod error;
 mod interpreter;
+pub mod io;
+#[cfg(target_arch = "x86_64")]
+mod jit;
 pub mod keypad;
 pub mod opcode;
+pub mod quirks;
+pub mod render;
+pub mod replay;
+pub mod rom;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod snapshot;
+pub mod trace;
 pub mod types;
+pub mod write_log;
 
 use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
     ops::Deref,
     sync::{
         atomic::{AtomicBool, AtomicI32, Ordering},
-        Arc, RwLock,
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, RwLock,
     },
     thread::{self},
     time::{Duration, Instant},
 };
 
+use audio::Beeper;
+use breakpoint::{Condition, RunOutcome};
+use clock::{Clock, SystemClock};
+pub use error::Chip8Error;
 pub use keypad::Keypad;
-use types::C8Byte;
+pub use quirks::{QuirkProfile, Quirks};
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+pub use snapshot::Snapshot;
+use types::{C8Addr, C8Byte};
 
 /// Create a shared chip8 executing on its own thread.
 pub struct ExecutingChip8 {
     chip8: Arc<RwLock<Chip8>>,
     frequency: Arc<AtomicI32>,
+    ipf: Arc<AtomicI32>,
+    // Most instructions run per frame while holding the lock, see `set_max_cycles`.
+    max_cycles: Arc<AtomicI32>,
+    // The last frame was cut short by `max_cycles`.
+    capped: Arc<AtomicBool>,
+    use_ipf: Arc<AtomicBool>,
+    vsync: Arc<AtomicBool>,
+    // Sleep instead of executing while the program waits for input, see `set_skip_idle`.
+    skip_idle: Arc<AtomicBool>,
     running: Arc<AtomicBool>,
+    // Error which last stopped execution.
+    error: Arc<Mutex<Option<Chip8Error>>>,
+    // When to pause, see `run_for`.
+    deadline: Arc<Mutex<Option<Instant>>>,
+    events: Arc<Events>,
+}
+
+impl Default for ExecutingChip8 {
+    /// Same as `ExecutingChip8::new`, which starts the managed thread.
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Deref for ExecutingChip8 {
@@ -31,34 +81,105 @@ impl Deref for ExecutingChip8 {
     }
 }
 
+/// Length of a 60 Hz frame.
+pub(crate) const FRAME: Duration = Duration::from_micros(16666);
+/// How often the managed thread checks whether to resume while idle.
+const IDLE_POLL: Duration = Duration::from_millis(1);
+/// Default for `ExecutingChip8::set_max_cycles`.
+pub const DEFAULT_MAX_CYCLES: i32 = 5000;
+/// Default for `ExecutingChip8::set_ipf`, also the frame length of `Chip8::run_until_halt`.
+pub const DEFAULT_IPF: i32 = 10;
+
 impl ExecutingChip8 {
     pub fn new() -> Self {
         let chip8 = Arc::new(RwLock::new(Chip8::new()));
         let running = Arc::new(AtomicBool::new(false));
         let frequency = Arc::new(AtomicI32::new(600));
+        let ipf = Arc::new(AtomicI32::new(DEFAULT_IPF));
+        let max_cycles = Arc::new(AtomicI32::new(DEFAULT_MAX_CYCLES));
+        let capped = Arc::new(AtomicBool::new(false));
+        let use_ipf = Arc::new(AtomicBool::new(false));
+        let vsync = Arc::new(AtomicBool::new(false));
+        let skip_idle = Arc::new(AtomicBool::new(false));
+        let error = Arc::new(Mutex::new(None));
+        let deadline = Arc::new(Mutex::new(None));
+        let events = Arc::new(Events::new());
 
         let chip8_clone = chip8.clone();
         let running_clone = running.clone();
         let frequency_clone = frequency.clone();
+        let ipf_clone = ipf.clone();
+        let max_cycles_clone = max_cycles.clone();
+        let capped_clone = capped.clone();
+        let use_ipf_clone = use_ipf.clone();
+        let vsync_clone = vsync.clone();
+        let skip_idle_clone = skip_idle.clone();
+        let error_clone = error.clone();
+        let deadline_clone = deadline.clone();
+        let events_clone = events.clone();
         thread::spawn(move || {
+            let mut last_tick = Instant::now();
+
             loop {
-                // Wait while running is disabled.
-                while !running_clone.load(Ordering::Relaxed) {}
+                // Wait while running is disabled or the render loop is driving execution.
+                // Sleeping briefly keeps this from occupying a core while idle.
+                while !running_clone.load(Ordering::Relaxed) || vsync_clone.load(Ordering::Relaxed)
+                {
+                    thread::sleep(IDLE_POLL);
+                    // Timers don't run while paused.
+                    last_tick = Instant::now();
+                }
+
+                // Only a key press can end an idle loop, so there is no need to spin through it.
+                if skip_idle_clone.load(Ordering::Relaxed) && chip8_clone.read().unwrap().is_idle()
+                {
+                    thread::sleep(IDLE_POLL);
+                    tick_due_timers(&chip8_clone, &mut last_tick);
+                    pause_at_deadline(&deadline_clone, &running_clone);
+                    continue;
+                }
 
                 let init_time = Instant::now();
 
-                chip8_clone.write().unwrap().interpreter();
+                // Either run a batch of cycles per frame,
+                // or a single cycle per period of the frequency.
+                let use_ipf = use_ipf_clone.load(Ordering::Relaxed);
+                let cycles = if use_ipf {
+                    frame_cycles(&ipf_clone, &max_cycles_clone, &capped_clone)
+                } else {
+                    1
+                };
 
-                let end_time = Instant::now();
+                let spent = match run_or_stop(
+                    &chip8_clone,
+                    cycles,
+                    &running_clone,
+                    &error_clone,
+                    &events_clone,
+                ) {
+                    Some(spent) => spent,
+                    None => continue,
+                };
+
+                // Costly instructions take several periods at a fixed frequency.
+                let period = if use_ipf {
+                    FRAME
+                } else {
+                    Duration::from_nanos(
+                        spent as u64 * 1000000000 / frequency_clone.load(Ordering::Relaxed) as u64,
+                    )
+                };
+
+                // A held back `DRW` only runs once the timers tick, so sleep until they do.
+                if spent < cycles {
+                    thread::sleep((last_tick + FRAME).saturating_duration_since(Instant::now()));
+                }
 
                 // Wait here til time for more cycles
-                while Instant::now()
-                    < end_time
-                        + Duration::from_nanos(
-                            1000000000 / frequency_clone.load(Ordering::Relaxed) as u64,
-                        )
-                        - (end_time - init_time)
-                {}
+                while Instant::now() < init_time + period {}
+
+                tick_due_timers(&chip8_clone, &mut last_tick);
+                pause_at_deadline(&deadline_clone, &running_clone);
             }
         });
 
@@ -66,38 +187,405 @@ impl ExecutingChip8 {
             chip8,
             running,
             frequency,
+            ipf,
+            max_cycles,
+            capped,
+            use_ipf,
+            vsync,
+            skip_idle,
+            error,
+            deadline,
+            events,
         }
     }
 
+    /// Run at a fixed instruction frequency in Hz.
     pub fn set_frequency(&self, frequency: i32) {
         self.frequency.store(frequency, Ordering::Relaxed);
+        self.use_ipf.store(false, Ordering::Relaxed);
     }
 
     pub fn get_frequency(&self) -> i32 {
         self.frequency.load(Ordering::Relaxed)
     }
 
-    /// Should the managed thread be executing.
+    /// Run a fixed amount of instructions per 60 Hz frame instead of a frequency.
+    pub fn set_ipf(&self, ipf: i32) {
+        self.ipf.store(ipf, Ordering::Relaxed);
+        self.use_ipf.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get_ipf(&self) -> i32 {
+        self.ipf.load(Ordering::Relaxed)
+    }
+
+    /// Limit the instructions run per frame, so huge IPF values can't hold the lock
+    /// long enough to starve rendering and input. Frames beyond the limit run it instead.
+    pub fn set_max_cycles(&self, max_cycles: i32) {
+        self.max_cycles.store(max_cycles, Ordering::Relaxed);
+    }
+
+    pub fn get_max_cycles(&self) -> i32 {
+        self.max_cycles.load(Ordering::Relaxed)
+    }
+
+    /// Was the last frame limited by `set_max_cycles`, meaning execution is slower than requested.
+    pub fn is_capped(&self) -> bool {
+        self.capped.load(Ordering::Relaxed)
+    }
+
+    /// Is execution paced by instructions per frame rather than frequency.
+    pub fn uses_ipf(&self) -> bool {
+        self.use_ipf.load(Ordering::Relaxed)
+    }
+
+    /// Drive execution from the render loop through `run_frame` instead of the managed thread.
+    ///
+    /// Timing follows the display refresh rate, however nothing executes
+    /// while the render loop isn't running, e.g. when the window is minimized.
+    pub fn set_vsync(&self, vsync: bool) {
+        self.vsync.store(vsync, Ordering::Relaxed);
+    }
+
+    pub fn uses_vsync(&self) -> bool {
+        self.vsync.load(Ordering::Relaxed)
+    }
+
+    /// Sleep instead of executing while the program only waits for input (see `Chip8::is_idle`),
+    /// saving power. Execution resumes once a key changes, the timers keep ticking meanwhile.
+    pub fn set_skip_idle(&self, skip_idle: bool) {
+        self.skip_idle.store(skip_idle, Ordering::Relaxed);
+    }
+
+    pub fn skips_idle(&self) -> bool {
+        self.skip_idle.load(Ordering::Relaxed)
+    }
+
+    /// Run one frame worth of instructions (see `set_ipf`) when in vsync mode.
+    /// This should be called once per rendered frame.
+    pub fn run_frame(&self) {
+        if !self.uses_vsync() || !self.is_running() {
+            return;
+        }
+
+        if self.skips_idle() && self.chip8.read().unwrap().is_idle() {
+            self.chip8.write().unwrap().tick_timers();
+            pause_at_deadline(&self.deadline, &self.running);
+            return;
+        }
+
+        let ran = run_or_stop(
+            &self.chip8,
+            frame_cycles(&self.ipf, &self.max_cycles, &self.capped),
+            &self.running,
+            &self.error,
+            &self.events,
+        );
+
+        // Each rendered frame counts as a 60 Hz tick, like the instructions per frame.
+        if ran.is_some() {
+            self.chip8.write().unwrap().tick_timers();
+            pause_at_deadline(&self.deadline, &self.running);
+        }
+    }
+
+    /// Take the events sent as execution halted, went idle or failed since the last call,
+    /// oldest first. Each frame of a frontend should drain these.
+    ///
+    /// Halting and going idle are only sent when entered, not while they last.
+    /// `RunOutcome::CycleLimit` is never sent.
+    pub fn drain_events(&self) -> Vec<RunOutcome> {
+        self.events.receiver.lock().unwrap().try_iter().collect()
+    }
+
+    /// Error which last stopped execution, if any.
+    pub fn last_error(&self) -> Option<Chip8Error> {
+        *self.error.lock().unwrap()
+    }
+
+    /// Clear the last error, e.g. once the user has seen it.
+    pub fn clear_error(&self) {
+        *self.error.lock().unwrap() = None;
+    }
+
+    /// Stop execution on an error raised outside the managed thread, e.g. when stepping.
+    pub fn report_error(&self, error: Chip8Error) {
+        *self.error.lock().unwrap() = Some(error);
+        self.events.send(RunOutcome::Error(error));
+        self.set_running(false);
+    }
+
+    /// Load a ROM, see `Chip8::load_rom`.
+    pub fn load_rom(&self, rom: Vec<u8>) {
+        self.chip8.write().unwrap().load_rom(rom);
+    }
+
+    /// Restart the loaded ROM, see `Chip8::reset`.
+    pub fn reset(&self, kind: ResetKind) {
+        self.chip8.write().unwrap().reset(kind);
+    }
+
+    /// Restart `RND` from a seed, see `Chip8::reseed`.
+    pub fn reseed(&self, seed: u64) {
+        self.chip8.write().unwrap().reseed(seed);
+    }
+
+    /// Press or release a key, `key` is 0-F.
+    pub fn set_key(&self, key: u8, pressed: bool) {
+        self.chip8.write().unwrap().keypad.set(key, pressed);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.chip8.read().unwrap().snapshot()
+    }
+
+    pub fn restore(&self, snapshot: &Snapshot) -> Result<(), snapshot::SnapshotError> {
+        self.chip8.write().unwrap().restore(snapshot)
+    }
+
+    /// Should the managed thread be executing. Cancels any pause set by `run_for`.
     pub fn set_running(&self, start: bool) {
+        *self.deadline.lock().unwrap() = None;
         self.running.store(start, Ordering::Relaxed)
     }
 
+    /// Run at the current speed for `duration`, then pause.
+    /// Execution stops early on a breakpoint or error like it does while running.
+    ///
+    /// Unlike `Chip8::run_for` this returns right away, the run is over once `is_running` is false.
+    pub fn run_for(&self, duration: Duration) {
+        self.set_running(true);
+        *self.deadline.lock().unwrap() = Some(Instant::now() + duration);
+    }
+
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::Relaxed)
     }
 }
 
+impl<R: RngCore + SeedableRng> Chip8<R> {
+    /// Restart the generator used by `RND` from `seed`,
+    /// so the same seed always produces the same random numbers from here on.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = R::seed_from_u64(seed);
+        self.seed = Some(seed);
+    }
+}
+
+/// Cycles to run in a frame, the IPF limited by `max_cycles`.
+fn frame_cycles(ipf: &AtomicI32, max_cycles: &AtomicI32, capped: &AtomicBool) -> usize {
+    let ipf = ipf.load(Ordering::Relaxed);
+    let max_cycles = max_cycles.load(Ordering::Relaxed);
+
+    capped.store(ipf > max_cycles, Ordering::Relaxed);
+    ipf.min(max_cycles).max(0) as usize
+}
+
+/// Channel of `RunOutcome`s from the driving thread, see `ExecutingChip8::drain_events`.
+struct Events {
+    sender: Mutex<Sender<RunOutcome>>,
+    receiver: Mutex<Receiver<RunOutcome>>,
+    // Halted or idle state last sent, so it's only sent again once left.
+    state: Mutex<Option<RunOutcome>>,
+}
+
+impl Events {
+    fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            sender: Mutex::new(sender),
+            receiver: Mutex::new(receiver),
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Send an event if the state after running changed.
+    fn update(&self, chip8: &Chip8, result: &Result<usize, Chip8Error>) {
+        let mut last = self.state.lock().unwrap();
+
+        if let Err(err) = result {
+            *last = None;
+            self.send(RunOutcome::Error(*err));
+            return;
+        }
+
+        let state = if chip8.is_halted() {
+            Some(RunOutcome::Halted)
+        } else if chip8.is_idle() {
+            Some(RunOutcome::Idle)
+        } else {
+            None
+        };
+
+        if state != *last {
+            *last = state;
+            if let Some(outcome) = state {
+                self.send(outcome);
+            }
+        }
+    }
+
+    fn send(&self, outcome: RunOutcome) {
+        // The receiver lives as long as the sender, so this can't fail.
+        let _ = self.sender.lock().unwrap().send(outcome);
+    }
+}
+
+/// Tick the timers once for every 60 Hz frame since `last_tick`,
+/// so they keep their pace however fast instructions run.
+fn tick_due_timers(chip8: &RwLock<Chip8>, last_tick: &mut Instant) {
+    if last_tick.elapsed() < FRAME {
+        return;
+    }
+
+    let mut chip8 = chip8.write().unwrap();
+    while last_tick.elapsed() >= FRAME {
+        chip8.tick_timers();
+        *last_tick += FRAME;
+    }
+}
+
+/// Stop running once the deadline set by `ExecutingChip8::run_for` passed.
+fn pause_at_deadline(deadline: &Mutex<Option<Instant>>, running: &AtomicBool) {
+    let mut deadline = deadline.lock().unwrap();
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        *deadline = None;
+        running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Run cycles, stopping execution on a breakpoint or error.
+/// Returns the cycles spent if execution should continue.
+fn run_or_stop(
+    chip8: &RwLock<Chip8>,
+    cycles: usize,
+    running: &AtomicBool,
+    error: &Mutex<Option<Chip8Error>>,
+    events: &Events,
+) -> Option<usize> {
+    let mut chip8 = chip8.write().unwrap();
+    let result = chip8.run_cycles(cycles);
+    events.update(&chip8, &result);
+
+    match result {
+        Ok(spent) if spent >= cycles || chip8.is_waiting_for_frame() => return Some(spent),
+        // Stopped on a breakpoint.
+        Ok(_) => events.send(RunOutcome::Breakpoint(chip8.pc)),
+        Err(err) => *error.lock().unwrap() = Some(err),
+    }
+
+    running.store(false, Ordering::Relaxed);
+    None
+}
+
+/// Region of memory for dumping and restoring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRegion {
+    /// All of memory.
+    Full,
+    /// Program region starting at 0x200.
+    Program,
+}
+
+/// Size of the address space, see `Chip8::set_memory_size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemorySize {
+    /// 4 KB, I wraps at 12 bits as on the original interpreters.
+    #[default]
+    Classic,
+    /// 64 KB as in XO-CHIP, I uses all 16 bits.
+    Extended,
+}
+
+impl MemorySize {
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::Classic => 0x1000,
+            Self::Extended => 0x10000,
+        }
+    }
+}
+
+/// Address the font is loaded at.
+pub const FONT_ADDR: usize = 0x000;
+/// Size of the font, 5 bytes for each hex digit.
+pub const FONT_SIZE: usize = 80;
+/// Address the SUPER-CHIP big font is loaded at, right after the font.
+pub const BIG_FONT_ADDR: usize = 0x050;
+/// Size of the big font, 10 bytes for each decimal digit.
+pub const BIG_FONT_SIZE: usize = 100;
+/// Address programs are loaded and start executing at.
+pub const PROGRAM_ADDR: usize = 0x200;
+/// Most nested subroutine calls, a `CALL` beyond this is a stack overflow.
+pub const MAX_CALL_DEPTH: usize = 15;
+/// Call depth from which `Chip8::stack_near_limit` warns.
+pub const STACK_WARNING_DEPTH: usize = 12;
+
+impl MemoryRegion {
+    fn start(&self) -> usize {
+        match self {
+            Self::Full => 0,
+            Self::Program => PROGRAM_ADDR,
+        }
+    }
+}
+
+/// How instructions are executed, see `Chip8::set_backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Decode and execute one instruction at a time.
+    #[default]
+    Interpreter,
+    /// Compile straight-line runs of instructions to native code.
+    /// Only available on x86-64, elsewhere this behaves as `Interpreter`.
+    Jit,
+}
+
+/// What `CALL` with a full stack and `RET` with an empty one do, see `Chip8::set_stack_policy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StackPolicy {
+    /// Fail with `Chip8Error::StackOverflow` or `Chip8Error::StackUnderflow`.
+    #[default]
+    Error,
+    /// Skip the instruction, leaving the stack as it is.
+    Saturate,
+    /// Wrap the stack pointer around, overwriting the oldest return address on `CALL`
+    /// and returning to the deepest slot on `RET`.
+    Wrap,
+}
+
+/// How much of the state `Chip8::reset` clears.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Clear everything and restore the ROM, as if it was just loaded.
+    #[default]
+    Cold,
+    /// Keep V0-VF, `I` and memory, only restarting execution:
+    /// the program counter, stack, timers, keypad and screen.
+    Warm,
+}
+
+/// Handler for sound starting (true) and stopping (false), see `Chip8::set_sound_handler`.
+pub type SoundHandler = Box<dyn FnMut(bool) + Send + Sync>;
+
+/// Handler for `SYS addr`, called with the routine's address.
+pub type SysHandler<R = StdRng> = Box<dyn FnMut(C8Addr, &mut Chip8<R>) + Send + Sync>;
+
 /// Chip8 emulator with both JIT and interpreter.
 /// Members are only public for debugging purposes.
-pub struct Chip8 {
+///
+/// `R` is the source of randomness used by `RND`,
+/// e.g. a deterministic generator when testing.
+pub struct Chip8<R: RngCore = StdRng> {
     // Program counter, first 200 bits reserved.
     pub pc: u16,
     // Stack pointer.
     pub sp: usize,
-    // Current index, 12 bit.
+    // Current index, wraps at the end of memory.
     pub index: u16,
-    // 4 kilobyte memory.
-    pub memory: [C8Byte; 4096],
+    // Memory, 4 kilobytes unless changed with `set_memory_size`.
+    pub memory: Vec<C8Byte>,
     // Stack of size 16 with 16-bit values.
     pub stack: [u16; 16],
     // 16 8-bit registers (VX) where X is 0-F
@@ -106,60 +594,364 @@ pub struct Chip8 {
     pub delay_timer: u8,
     // Sound timer, counts down while beeping until 0.
     pub sound_timer: u8,
+    // Tone played while the sound timer is active.
+    pub beeper: Beeper,
 
     // Video memory, 64 height, 32 length
     pub screen: [[bool; 64]; 32],
+    // Screen changed since `take_screen_dirty` was last called.
+    screen_dirty: bool,
 
     pub keypad: Keypad,
-    timer: Instant,
+    // Budget execution by `opcode::cycle_cost` instead of one cycle per instruction.
+    pub cycle_costs: bool,
+    // Most `DRW`s per 60 Hz frame, see `is_waiting_for_frame`.
+    pub max_draws_per_frame: Option<u32>,
+    // `DRW`s executed since the timers last ticked.
+    frame_draws: u32,
+    // Log instructions which move the PC from an even to an odd address, see `misaligned_jumps`.
+    pub warn_misaligned_pc: bool,
+    // Fail on a `0000` followed by another instead of skipping it, see `Chip8Error::EmptyMemory`.
+    pub trap_empty_memory: bool,
+    // Breakpoints keyed by address.
+    pub breakpoints: BTreeMap<C8Addr, Condition>,
+    // One-shot breakpoints, such as "run to here".
+    pub temporary_breakpoints: BTreeSet<C8Addr>,
+    rom: Vec<u8>,
+    // Breakpoint execution last stopped at.
+    stopped_at: Option<C8Addr>,
+    clock: Box<dyn Clock>,
+    rng: R,
+    // Seed last passed to `reseed`.
+    seed: Option<u64>,
+    quirks: Quirks,
+    stack_policy: StackPolicy,
+    decoded: opcode::DecodeCache,
+    backend: Backend,
+    #[cfg(target_arch = "x86_64")]
+    blocks: cache::BlockCache,
+    sys_handler: Option<SysHandler<R>>,
+    sound_handler: Option<SoundHandler>,
+    // Sound timer was active at the last tick.
+    sounding: bool,
+    io: Option<io::MappedIo>,
+    trace_hook: Option<trace::TraceHook<R>>,
+    // Memory written by the instruction being traced.
+    traced_writes: Vec<(C8Addr, C8Byte)>,
+    // Instructions executed since creation.
+    cycle_count: u64,
+    // Recent memory writes, see `set_write_log`.
+    write_log: VecDeque<write_log::MemoryWrite>,
+    // Most writes kept in `write_log`, 0 while not recording.
+    write_log_capacity: usize,
+    // Instructions executed by address, `None` while not recording, see `set_coverage`.
+    coverage: Option<Vec<bool>>,
+    // Times `warn_misaligned_pc` reported a jump to an odd address.
+    misaligned_jumps: u64,
+    #[cfg(feature = "scripting")]
+    script: Option<script::Script>,
 }
 
 impl Chip8 {
     /// Create a chip8 emulator without a driving thread.
     pub fn new() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Create a chip8 emulator using `rng` for `RND`.
+    pub fn with_rng(rng: R) -> Self {
         let mut state = Self {
             pc: 0x200, // First 200 bits reserved usually
             sp: 0,
             index: 0,
-            memory: [0; 4096],
+            memory: vec![0; MemorySize::default().bytes()],
             stack: [0; 16],
             registers: [0; 16],
             delay_timer: 0,
             sound_timer: 0,
+            beeper: Beeper::default(),
             screen: [[false; 64]; 32],
-            timer: Instant::now(),
+            screen_dirty: true,
+            clock: Box::new(SystemClock::default()),
             keypad: Keypad::default(),
+            cycle_costs: false,
+            max_draws_per_frame: None,
+            frame_draws: 0,
+            warn_misaligned_pc: false,
+            trap_empty_memory: false,
+            breakpoints: BTreeMap::new(),
+            temporary_breakpoints: BTreeSet::new(),
+            rom: Vec::new(),
+            stopped_at: None,
+            rng,
+            seed: None,
+            quirks: Quirks::default(),
+            stack_policy: StackPolicy::default(),
+            decoded: opcode::DecodeCache::default(),
+            backend: Backend::Interpreter,
+            #[cfg(target_arch = "x86_64")]
+            blocks: cache::BlockCache::default(),
+            sys_handler: None,
+            sound_handler: None,
+            sounding: false,
+            io: None,
+            trace_hook: None,
+            traced_writes: Vec::new(),
+            cycle_count: 0,
+            write_log: VecDeque::new(),
+            write_log_capacity: 0,
+            coverage: None,
+            misaligned_jumps: 0,
+            #[cfg(feature = "scripting")]
+            script: None,
         };
 
         state.load_font();
         state
     }
 
+    /// Seed last passed to `reseed`, `None` while `RND` uses the generator the emulator was created with.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     /// Reset the state of the emulator.
     pub fn reset_state(&mut self) {
+        self.index = 0;
+        self.registers.fill(0);
+        self.load_font();
+        self.restart();
+    }
+
+    /// Start executing from the beginning, leaving registers and memory alone.
+    fn restart(&mut self) {
         self.delay_timer = 0;
         self.sound_timer = 0;
         self.pc = 0x200;
-        self.index = 0;
+        self.sp = 0;
         self.stack.fill(0);
-        self.registers.fill(0);
-        self.load_font();
+        self.keypad.reset();
         self.clear_screen();
     }
 
     /// Load rom into memory.
-    /// This will call `reset_state`
-    pub fn load_rom(&mut self, rom: Vec<u8>) {
-        self.reset_state();
+    /// This will call `reset` with `ResetKind::Cold`.
+    pub fn load_rom(&mut self, mut rom: Vec<u8>) {
+        rom.truncate(self.memory.len() - PROGRAM_ADDR);
+        self.rom = rom;
+        self.clear_coverage();
+        self.reset(ResetKind::Cold);
+    }
+
+    /// Restart the ROM which was last loaded.
+    /// A cold reset also clears the state and restores the ROM,
+    /// undoing anything the program wrote to memory.
+    pub fn reset(&mut self, kind: ResetKind) {
+        match kind {
+            ResetKind::Cold => {
+                self.reset_state();
+
+                self.memory[PROGRAM_ADDR..].fill(0);
+                self.memory[PROGRAM_ADDR..PROGRAM_ADDR + self.rom.len()].copy_from_slice(&self.rom)
+            }
+            ResetKind::Warm => self.restart(),
+        }
+    }
+
+    /// Copy a region of memory, e.g. to write it to a file.
+    pub fn dump_memory(&self, region: MemoryRegion) -> Vec<u8> {
+        self.memory[region.start()..].to_vec()
+    }
+
+    /// Restore a dump produced by `dump_memory` for the same region.
+    /// Bytes which don't fit in memory are ignored.
+    pub fn load_memory_dump(&mut self, region: MemoryRegion, dump: &[u8]) {
+        let memory = &mut self.memory[region.start()..];
+        let len = dump.len().min(memory.len());
+        memory[..len].copy_from_slice(&dump[..len]);
+    }
+
+    /// Value of register V`register`, 0-F.
+    pub fn register(&self, register: usize) -> u8 {
+        self.registers[register]
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Jumps to odd addresses seen while `warn_misaligned_pc` was enabled.
+    pub fn misaligned_jumps(&self) -> u64 {
+        self.misaligned_jumps
+    }
+
+    /// Subroutines currently being executed.
+    pub fn call_depth(&self) -> usize {
+        self.sp
+    }
+
+    /// Is the call depth close to `MAX_CALL_DEPTH`, e.g. from runaway recursion.
+    pub fn stack_near_limit(&self) -> bool {
+        self.call_depth() >= STACK_WARNING_DEPTH
+    }
+
+    /// `len` bytes of memory starting at `start`.
+    ///
+    /// # Panics
+    ///
+    /// If the range is outside of memory.
+    pub fn memory_range(&self, start: usize, len: usize) -> &[u8] {
+        &self.memory[start..start + len]
+    }
+
+    /// Writable `len` bytes of memory starting at `start`, e.g. to patch code or apply cheats.
+    /// Instructions decoded or compiled from the range are dropped,
+    /// unlike writing to `memory` directly which relies on them noticing the change.
+    ///
+    /// # Panics
+    ///
+    /// If the range is outside of memory.
+    pub fn memory_mut(&mut self, start: usize, len: usize) -> &mut [u8] {
+        let range = start..start + len;
+        self.decoded.invalidate(range.clone());
+        #[cfg(target_arch = "x86_64")]
+        self.blocks.invalidate(range.clone());
+
+        &mut self.memory[range]
+    }
+
+    /// Hundreds, tens and ones digits stored at `addr`, as written by `LD B, Vx`.
+    ///
+    /// # Panics
+    ///
+    /// If the digits are outside of memory.
+    pub fn bcd_at(&self, addr: usize) -> (u8, u8, u8) {
+        let digits = self.memory_range(addr, 3);
+        (digits[0], digits[1], digits[2])
+    }
+
+    /// ROM which was last loaded.
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    /// Switch how instructions are executed by `run_cycles`.
+    /// Single steps through `interpreter` always use the interpreter.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+
+        #[cfg(target_arch = "x86_64")]
+        self.blocks.clear();
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Change what happens when a `CALL` or `RET` is past the ends of the stack.
+    pub fn set_stack_policy(&mut self, policy: StackPolicy) {
+        self.stack_policy = policy;
+    }
+
+    pub fn stack_policy(&self) -> StackPolicy {
+        self.stack_policy
+    }
 
-        let mut rom = rom.clone();
-        rom.resize(4096 - 512, 0);
-        self.memory[512..4096].copy_from_slice(&rom)
+    /// Change disputed instruction behavior, taking effect from the next instruction.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+
+        // Compiled blocks bake in the quirks.
+        #[cfg(target_arch = "x86_64")]
+        self.blocks.clear();
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Grow or shrink memory, which also sets where I wraps.
+    /// Memory beyond a smaller size is lost, as is the end of a ROM which no longer fits.
+    pub fn set_memory_size(&mut self, size: MemorySize) {
+        self.memory.resize(size.bytes(), 0);
+        self.rom.truncate(size.bytes() - PROGRAM_ADDR);
+        self.index &= self.index_mask();
+        self.pc &= self.index_mask();
+        self.decoded.resize(size.bytes());
+
+        // Compiled blocks bake in the wrapping of I.
+        #[cfg(target_arch = "x86_64")]
+        self.blocks.clear();
+    }
+
+    pub fn memory_size(&self) -> MemorySize {
+        if self.memory.len() > MemorySize::Classic.bytes() {
+            MemorySize::Extended
+        } else {
+            MemorySize::Classic
+        }
+    }
+
+    /// Scan a ROM for likely problems before loading it into this machine, see `rom::validate`.
+    pub fn validate_rom(&self, rom: &[u8]) -> Vec<rom::RomWarning> {
+        rom::validate(rom, self.memory_size())
+    }
+
+    /// Mask applied to I, the highest address.
+    pub(crate) fn index_mask(&self) -> u16 {
+        (self.memory.len() - 1) as u16
+    }
+
+    /// Address `offset` bytes past I, wrapping around the end of memory.
+    pub(crate) fn index_addr(&self, offset: usize) -> usize {
+        (self.index as usize + offset) & self.index_mask() as usize
     }
 
-    /// Loaded font to the first 80 bytes of memory
+    /// Run `handler` on `SYS addr` instead of ignoring it.
+    ///
+    /// Modern interpreters treat `SYS` as a NOP since it jumped to machine code on the original hardware.
+    /// This is meant for authenticity or experimentation, e.g. implementing a machine specific routine.
+    pub fn set_sys_handler(&mut self, handler: Option<SysHandler<R>>) {
+        self.sys_handler = handler;
+    }
+
+    /// Width and height of the screen in pixels.
+    /// Anything drawing or exporting the screen should size itself from this.
+    pub fn screen_dimensions(&self) -> (usize, usize) {
+        (self.screen[0].len(), self.screen.len())
+    }
+
+    /// Whether the screen changed since the last call, e.g. to only upload changed frames.
+    /// Only changes made by instructions or `reset_state` are tracked.
+    pub fn take_screen_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.screen_dirty)
+    }
+
+    /// Hash identifying the loaded ROM.
+    pub fn rom_hash(&self) -> u64 {
+        rom::hash(&self.rom)
+    }
+
+    /// Cost of the instruction at the program counter.
+    fn next_cycle_cost(&mut self) -> u32 {
+        if self.cycle_costs {
+            opcode::cycle_cost(&self.decoded.decode(&self.memory, self.pc as usize))
+        } else {
+            1
+        }
+    }
+
+    /// Load the font to the first 80 bytes of memory, followed by the big font.
     fn load_font(&mut self) {
-        self.memory[0..80].copy_from_slice(&[
+        self.memory[FONT_ADDR..FONT_ADDR + FONT_SIZE].copy_from_slice(&[
             0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
             0x20, 0x60, 0x20, 0x20, 0x70, // 1
             0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -177,60 +969,135 @@ impl Chip8 {
             0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
             0xF0, 0x80, 0xF0, 0x80, 0x80, // F
         ]);
+
+        // 8x10 digits for `LD HF, Vx`.
+        self.memory[BIG_FONT_ADDR..BIG_FONT_ADDR + BIG_FONT_SIZE].copy_from_slice(&[
+            0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+            0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+            0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+            0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+            0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+            0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+            0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+            0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+            0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+        ]);
     }
 
     /// Clear all video memory.
     fn clear_screen(&mut self) {
         self.screen = [[false; 64]; 32];
+        self.screen_dirty = true;
     }
 
-    // #[cfg(target_os = "windows")]
-    fn timer(&mut self) {
-        if self.timer.elapsed() >= Duration::from_micros(16666) {
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
-            }
+    /// Call `handler` with true when the sound timer starts beeping and false when it stops,
+    /// as seen by `tick_timers`. `None` removes the handler.
+    pub fn set_sound_handler(&mut self, handler: Option<SoundHandler>) {
+        self.sound_handler = handler;
+    }
+
+    /// Advance the delay and sound timers by one 60 Hz tick, this is also the end of a frame.
+    ///
+    /// Instructions never tick the timers, whatever drives execution has to call this 60 times a second.
+    /// `ExecutingChip8` does so while running.
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        // Beeping since the last tick.
+        let sounding = self.sound_timer > 0;
+        if sounding != self.sounding {
+            self.sounding = sounding;
 
-            if self.sound_timer > 0 {
-                // TODO: Accept sound callback when start/stop playing sound.
-                self.sound_timer -= 1;
+            if let Some(handler) = &mut self.sound_handler {
+                handler(sounding);
             }
+        }
 
-            self.timer = Instant::now();
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
         }
+
+        self.frame_draws = 0;
+
+        #[cfg(feature = "scripting")]
+        self.script_frame();
+    }
+
+    /// Is the next instruction a `DRW` beyond `max_draws_per_frame`, which waits for the next frame.
+    /// Games redrawing sprites several times a frame flicker less when held to a draw or two.
+    /// `Quirks::display_wait` holds every frame to a single draw.
+    /// Nothing executes until `tick_timers` ends the frame.
+    pub fn is_waiting_for_frame(&self) -> bool {
+        let wait = self.quirks.display_wait.then_some(1);
+        let Some(max_draws) = wait.into_iter().chain(self.max_draws_per_frame).min() else {
+            return false;
+        };
+
+        self.frame_draws >= max_draws
+            && matches!(self.peek_next_opcode().1, opcode::OpCode::DRW(..))
     }
 
     // Draw sprite at coordinates to video memory.
-    // This also sets the carry register.
+    // This also sets the carry register if any pixel was erased.
     fn draw_sprite(&mut self, x: usize, y: usize, n: u8) {
-        for j in 0..n {
-            let line = self.memory[(self.index + j as u16) as usize];
-
-            for i in 0..8 {
-                if line & (0x80 >> i) != 0 {
-                    let y = ((self.registers[y] + j) % 32) as usize;
-                    let x = ((self.registers[x] + i) % 64) as usize;
-
-                    if self.screen[y][x] {
-                        self.screen[y][x] = false;
-                        self.registers[15] = 1;
-                    } else {
-                        self.screen[y][x] = true;
-                        self.registers[15] = 0;
-                    }
-                }
+        let x = self.registers[x] as usize % 64;
+        let y = self.registers[y] as usize % 32;
+        let mut collision = false;
+
+        for j in 0..n as usize {
+            if y + j >= 32 && !self.quirks.wrap_y {
+                break;
             }
+
+            let line = self.read_memory(self.index_addr(j));
+            let y = (y + j) % 32;
+
+            collision |= if x + 8 <= 64 {
+                self.draw_row(line, x, y)
+            } else {
+                self.draw_row_wrapping(line, x, y)
+            };
         }
+
+        self.registers[15] = collision as u8;
+        self.screen_dirty |= n > 0;
     }
-}
 
-/// Get any value as a pointer or memory address for JIT access.
-trait Address {
-    fn address(&self, offset: usize) -> usize;
-}
+    // XOR a sprite row onto a screen row a whole byte at once, returning whether any pixel was erased.
+    // The row must not wrap around the screen edge.
+    fn draw_row(&mut self, line: u8, x: usize, y: usize) -> bool {
+        let pixels = &mut self.screen[y][x..x + 8];
+
+        let old = pixels.iter().fold(0, |bits, on| bits << 1 | *on as u8);
+        let new = old ^ line;
+
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            *pixel = new & (0x80 >> i) != 0;
+        }
+
+        old & line != 0
+    }
+
+    // Pixel by pixel version of `draw_row` for rows crossing the right edge,
+    // wrapping or clipping the pixels past it depending on `Quirks::wrap_x`.
+    fn draw_row_wrapping(&mut self, line: u8, x: usize, y: usize) -> bool {
+        let mut collision = false;
+
+        for i in 0..8 {
+            if x + i >= 64 && !self.quirks.wrap_x {
+                break;
+            }
+
+            if line & (0x80 >> i) != 0 {
+                let pixel = &mut self.screen[y][(x + i) % 64];
+                collision |= *pixel;
+                *pixel = !*pixel;
+            }
+        }
 
-impl<T> Address for T {
-    fn address(&self, offset: usize) -> usize {
-        self as *const T as usize + offset
+        collision
     }
 }
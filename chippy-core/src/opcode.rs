@@ -1,8 +1,11 @@
-use std::{collections::HashMap, error::Error, fmt};
+use std::{collections::HashMap, error::Error, fmt, ops::Range};
 
 use once_cell::sync::Lazy;
 
-use crate::types::{C8Addr, C8Byte, C8RegIdx};
+use crate::{
+    types::{C8Addr, C8Byte, C8RegIdx},
+    MemorySize,
+};
 
 /// Bad instruction.
 #[derive(Debug)]
@@ -24,7 +27,7 @@ impl fmt::Display for BadInstruction {
 type OpCodeFlagMask = (C8Addr, C8Addr);
 
 /// Opcode enum.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OpCode {
     /// 0nnn - SYS addr.
     /// * Jump to a machine code routine at nnn.
@@ -107,6 +110,7 @@ pub enum OpCode {
     /// | A bitwise OR compares the corrseponding bits from two values, and
     /// | if either bit is 1, then the same bit in the result is also 1.
     /// | Otherwise, it is 0.
+    /// | See `Quirks::reset_vf_on_logic` for interpreters which also clear VF.
     OR(C8RegIdx, C8RegIdx),
 
     /// 8xy2 - AND Vx, Vy.
@@ -117,6 +121,7 @@ pub enum OpCode {
     /// | A bitwise AND compares the corrseponding bits from two values, and if
     /// | both bits are 1, then the same bit in the result is also 1.
     /// | Otherwise, it is 0.
+    /// | See `Quirks::reset_vf_on_logic` for interpreters which also clear VF.
     AND(C8RegIdx, C8RegIdx),
 
     /// 8xy3 - XOR Vx, Vy.
@@ -127,6 +132,7 @@ pub enum OpCode {
     /// | from two values, and if the bits are not both the same, then the
     /// | corresponding bit in the result is set to 1.
     /// | Otherwise, it is 0.
+    /// | See `Quirks::reset_vf_on_logic` for interpreters which also clear VF.
     XOR(C8RegIdx, C8RegIdx),
 
     /// 8xy4 - ADD Vx, Vy.
@@ -141,7 +147,8 @@ pub enum OpCode {
     /// 8xy5 - SUB Vx, Vy.
     /// * Set Vx = Vx - Vy, set VF = NOT borrow.
     ///
-    /// | If Vx > Vy, then VF is set to 1, otherwise 0.
+    /// | If Vx >= Vy, then VF is set to 1, otherwise 0.
+    /// | See `Quirks::strict_sub_flag` for interpreters which use >.
     /// | Then Vy is subtracted from Vx, and the results stored in Vx.
     SUB(C8RegIdx, C8RegIdx),
 
@@ -156,7 +163,8 @@ pub enum OpCode {
     /// 8xy7 - SUBN Vx, Vy.
     /// * Set Vx = Vy - Vx, set VF = NOT borrow.
     ///
-    /// | If Vy > Vx, then VF is set to 1, otherwise 0.
+    /// | If Vy >= Vx, then VF is set to 1, otherwise 0.
+    /// | See `Quirks::strict_sub_flag` for interpreters which use >.
     /// | Then Vx is subtracted from Vy, and the results stored in Vx.
     SUBN(C8RegIdx, C8RegIdx),
 
@@ -209,6 +217,7 @@ pub enum OpCode {
     /// | is set to 0.
     /// | If the sprite is positioned so part of it is outside the coordinates
     /// | of the display, it wraps around to the opposite side of the screen.
+    /// | See `Quirks::wrap_x` and `Quirks::wrap_y` for interpreters which clip instead.
     /// | See instruction 8xy3 for more information on XOR.
     DRW(C8RegIdx, C8RegIdx, C8Byte),
 
@@ -264,6 +273,13 @@ pub enum OpCode {
     /// | corresponding to the value of Vx.
     LDSprite(C8RegIdx),
 
+    /// Fx30 - LD HF, Vx.
+    /// * Set I = location of the big sprite for digit Vx.
+    ///
+    /// | SUPER-CHIP only. The value of I is set to the location for the
+    /// | 8x10 sprite of the decimal digit in Vx, see `BIG_FONT_ADDR`.
+    LDBigSprite(C8RegIdx),
+
     /// Fx33 - LD B, Vx.
     /// * Store BCD representation of Vx in memory locations I, I+1, and I+2.
     ///
@@ -305,22 +321,12 @@ impl OpCode {
     /// * Opcode address.
     ///
     fn extract_opcode_id(opcode: C8Addr) -> C8Addr {
-        let mut extracted_key = None;
-
-        for (key, flag_mask) in OPCODE_FLAG_MASKS.iter() {
-            let flag = flag_mask.0;
-            let mask = flag_mask.1;
-
-            if mask & opcode == flag {
-                extracted_key = Some(*key);
-            }
-        }
-
-        if extracted_key.is_none() {
-            extracted_key = Some(255)
-        }
-
-        extracted_key.unwrap()
+        // Several patterns can match (e.g. 00E0 is also 0nnn), the most specific mask wins.
+        OPCODE_FLAG_MASKS
+            .iter()
+            .filter(|(_, (flag, mask))| mask & opcode == *flag)
+            .max_by_key(|(_, (_, mask))| mask.count_ones())
+            .map_or(255, |(key, _)| *key)
     }
 
     /// Get opcode enum.
@@ -380,10 +386,36 @@ impl OpCode {
             33 => Self::LDS(b3),
             34 => Self::LDR(b3),
             35 => Self::EMPTY,
+            36 => Self::LDBigSprite(b3),
             _ => Self::DATA(opcode),
         }
     }
 
+    /// Get the address this opcode may transfer control to.
+    ///
+    /// `JP0` returns its base address since V0 isn't known statically.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address the opcode is located at.
+    ///
+    /// # Returns
+    ///
+    /// * Jump, call or skip destination if the opcode branches.
+    ///
+    pub fn branch_target(&self, addr: C8Addr) -> Option<C8Addr> {
+        match self {
+            Self::JP(target) | Self::CALL(target) | Self::JP0(target) => Some(*target),
+            Self::SEByte(..)
+            | Self::SNEByte(..)
+            | Self::SE(..)
+            | Self::SNE(..)
+            | Self::SKP(..)
+            | Self::SKNP(..) => Some(addr.wrapping_add(4)),
+            _ => None,
+        }
+    }
+
     /// Get string output for an opcode.
     /// Return a tuple: (assembly, verbose).
     ///
@@ -430,6 +462,7 @@ impl OpCode {
              Self::LDSetSoundTimer(reg) => (format!("LD ST, V{:X}", reg), format!("set sound timer = V{:X}", reg)),
              Self::ADDI(reg) => (format!("ADD I, V{:X}", reg), format!("set I = I + V{:X}", reg)),
             Self::LDSprite(reg) => (format!("LD F, V{:X}", reg), format!("set I = location of sprite for digit V{:X}", reg)),
+            Self::LDBigSprite(reg) => (format!("LD HF, V{:X}", reg), format!("set I = location of big sprite for digit V{:X}", reg)),
             Self::LDBCD(reg) => (format!("LD B, V{:X}", reg), format!("store BCD representation of V{:X} in memory locations I, I+1 and I+2", reg)),
             Self::LDS(reg) => (format!("LD [I], V{:X}", reg), format!("store registers V0 through V{:X} in memory starting at location I", reg)),
             Self::LDR(reg) => (format!("LD V{:X}, [I]", reg), format!("read registers V0 through V{:X} from memory starting at location I", reg)),
@@ -437,11 +470,113 @@ impl OpCode {
             Self::DATA(opcode) => (format!("DATA {:04X}", opcode), format!("- data ({:04X})", opcode))
         }
     }
+    /// Get the opcode in Octo syntax, e.g. `v1 := 0x0a` for `LD V1, 0A`.
+    ///
+    /// Octo's conditionals run the next instruction when true, so the skips are
+    /// written with the opposite comparison. Words Octo has no instruction for are
+    /// written as their two bytes.
+    ///
+    /// # Returns
+    ///
+    /// * Octo statement.
+    ///
+    pub fn to_octo(&self) -> String {
+        match self {
+            Self::CLS => "clear".into(),
+            Self::RET => "return".into(),
+            Self::JP(addr) => format!("jump 0x{:03x}", addr),
+            Self::CALL(addr) => format!(":call 0x{:03x}", addr),
+            Self::SEByte(reg, byte) => format!("if v{:x} != 0x{:02x} then", reg, byte),
+            Self::SNEByte(reg, byte) => format!("if v{:x} == 0x{:02x} then", reg, byte),
+            Self::SE(reg1, reg2) => format!("if v{:x} != v{:x} then", reg1, reg2),
+            Self::LDByte(reg, byte) => format!("v{:x} := 0x{:02x}", reg, byte),
+            Self::ADDByte(reg, byte) => format!("v{:x} += 0x{:02x}", reg, byte),
+            Self::LD(reg1, reg2) => format!("v{:x} := v{:x}", reg1, reg2),
+            Self::OR(reg1, reg2) => format!("v{:x} |= v{:x}", reg1, reg2),
+            Self::AND(reg1, reg2) => format!("v{:x} &= v{:x}", reg1, reg2),
+            Self::XOR(reg1, reg2) => format!("v{:x} ^= v{:x}", reg1, reg2),
+            Self::ADD(reg1, reg2) => format!("v{:x} += v{:x}", reg1, reg2),
+            Self::SUB(reg1, reg2) => format!("v{:x} -= v{:x}", reg1, reg2),
+            Self::SHR(reg1, reg2) => format!("v{:x} >>= v{:x}", reg1, reg2),
+            Self::SUBN(reg1, reg2) => format!("v{:x} =- v{:x}", reg1, reg2),
+            Self::SHL(reg1, reg2) => format!("v{:x} <<= v{:x}", reg1, reg2),
+            Self::SNE(reg1, reg2) => format!("if v{:x} == v{:x} then", reg1, reg2),
+            Self::LDI(addr) => format!("i := 0x{:03x}", addr),
+            Self::JP0(addr) => format!("jump0 0x{:03x}", addr),
+            Self::RND(reg, byte) => format!("v{:x} := random 0x{:02x}", reg, byte),
+            Self::DRW(reg1, reg2, n) => format!("sprite v{:x} v{:x} {}", reg1, reg2, n),
+            Self::SKP(reg) => format!("if v{:x} -key then", reg),
+            Self::SKNP(reg) => format!("if v{:x} key then", reg),
+            Self::LDGetDelayTimer(reg) => format!("v{:x} := delay", reg),
+            Self::LDGetKey(reg) => format!("v{:x} := key", reg),
+            Self::LDSetDelayTimer(reg) => format!("delay := v{:x}", reg),
+            Self::LDSetSoundTimer(reg) => format!("buzzer := v{:x}", reg),
+            Self::ADDI(reg) => format!("i += v{:x}", reg),
+            Self::LDSprite(reg) => format!("i := hex v{:x}", reg),
+            Self::LDBigSprite(reg) => format!("i := bighex v{:x}", reg),
+            Self::LDBCD(reg) => format!("bcd v{:x}", reg),
+            Self::LDS(reg) => format!("save v{:x}", reg),
+            Self::LDR(reg) => format!("load v{:x}", reg),
+            Self::SYS(addr) => format!("0x{:02x} 0x{:02x}", addr >> 8, addr & 0xFF),
+            Self::EMPTY => "0x00 0x00".into(),
+            Self::DATA(word) => format!("0x{:02x} 0x{:02x}", word >> 8, word & 0xFF),
+        }
+    }
+}
+
+/// Decoded opcodes by address, so tight loops aren't decoded every cycle.
+///
+/// Entries keep the word they were decoded from and are decoded again once it changes,
+/// so writes by `LDS`, `LDBCD` or the debugger are picked up without tracking them.
+/// `Chip8::memory_mut` also drops the entries it hands out.
+#[derive(Clone)]
+pub(crate) struct DecodeCache {
+    entries: Vec<Option<(C8Addr, OpCode)>>,
+}
+
+impl Default for DecodeCache {
+    fn default() -> Self {
+        Self {
+            entries: vec![None; MemorySize::Classic.bytes()],
+        }
+    }
+}
+
+impl DecodeCache {
+    /// Decode the opcode at an address.
+    pub fn decode(&mut self, memory: &[u8], addr: usize) -> OpCode {
+        let word = extract_opcode_from_array(memory, addr);
+
+        match self.entries.get_mut(addr) {
+            Some(Some((cached, opcode))) if *cached == word => *opcode,
+            entry => {
+                let opcode = OpCode::from_opcode(word);
+                if let Some(entry) = entry {
+                    *entry = Some((word, opcode));
+                }
+
+                opcode
+            }
+        }
+    }
+
+    /// Cover `len` bytes of memory, after `Chip8::set_memory_size`.
+    pub fn resize(&mut self, len: usize) {
+        self.entries.resize(len, None);
+    }
+
+    /// Forget opcodes decoded from any byte of `range`.
+    pub fn invalidate(&mut self, range: Range<usize>) {
+        // An opcode decoded at the byte before overlaps the range.
+        let start = range.start.saturating_sub(1).min(self.entries.len());
+        let end = range.end.min(self.entries.len());
+        self.entries[start..end].fill(None);
+    }
 }
 
 static OPCODE_FLAG_MASKS: Lazy<HashMap<C8Addr, OpCodeFlagMask>> = Lazy::new(|| {
     let mut m = HashMap::new();
-    m.insert(0, (0x0FFF, 0x0000)); // 0nnn
+    m.insert(0, (0x0000, 0xF000)); // 0nnn
     m.insert(1, (0x00E0, 0xFFFF)); // 00E0
     m.insert(2, (0x00EE, 0xFFFF)); // 00EE
     m.insert(3, (0x1000, 0xF000)); // 1nnn
@@ -478,6 +613,7 @@ static OPCODE_FLAG_MASKS: Lazy<HashMap<C8Addr, OpCodeFlagMask>> = Lazy::new(|| {
     m.insert(34, (0xF065, 0xF0FF)); // Fx65
 
     m.insert(35, (0x0000, 0xFFFF)); // 0000
+    m.insert(36, (0xF030, 0xF0FF)); // Fx30
 
     m
 });
@@ -503,3 +639,25 @@ pub fn extract_opcode_from_array(array: &[u8], ptr: usize) -> C8Addr {
         (C8Addr::from(array[ptr]) << 8) + C8Addr::from(array[ptr + 1])
     }
 }
+
+/// Relative cost of an instruction, in units of a simple register operation.
+/// Approximates the COSMAC VIP where clearing and drawing the display were slowest.
+///
+/// # Arguments
+///
+/// * `opcode` - Opcode enum.
+///
+/// # Returns
+///
+/// * Cost of executing the opcode.
+///
+pub fn cycle_cost(opcode: &OpCode) -> u32 {
+    match opcode {
+        OpCode::CLS => 24,
+        OpCode::DRW(_, _, n) => 8 + 2 * *n as u32,
+        OpCode::CALL(_) | OpCode::RET => 2,
+        OpCode::LDBCD(_) => 8,
+        OpCode::LDS(x) | OpCode::LDR(x) => 2 + *x as u32,
+        _ => 1,
+    }
+}
@@ -0,0 +1,122 @@
+//! Behaviors which differ between CHIP-8 interpreters.
+//!
+//! Defaults follow the most common modern convention, individual ROMs may expect otherwise.
+//! `QuirkProfile` names the sets of quirks matching well known interpreters.
+
+/// Toggles for disputed instruction behavior, see `Chip8::set_quirks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Quirks {
+    /// `SUB` and `SUBN` only set VF when the result is above zero, so equal operands clear it.
+    /// Otherwise VF is set whenever there's no borrow.
+    pub strict_sub_flag: bool,
+    /// `OR`, `AND` and `XOR` clear VF after writing the result, as on the COSMAC VIP.
+    pub reset_vf_on_logic: bool,
+    /// `JP`, `CALL` and `JP V0` clear the lowest bit of the target, keeping the PC even.
+    /// Otherwise jumps to odd addresses are followed as on the original hardware.
+    pub align_jumps: bool,
+    /// Sprite pixels past the right edge wrap to the left edge, otherwise they're clipped.
+    pub wrap_x: bool,
+    /// Sprite rows past the bottom edge wrap to the top edge, otherwise they're clipped.
+    pub wrap_y: bool,
+    /// `SHR Vx, Vy` and `SHL Vx, Vy` shift Vy into Vx, as on the COSMAC VIP.
+    /// Otherwise Vx is shifted in place and Vy is ignored.
+    pub shift_uses_vy: bool,
+    /// `LD [I], Vx` and `LD Vx, [I]` leave I pointing past the last register stored or loaded.
+    /// Otherwise I is left alone.
+    pub load_store_increments_i: bool,
+    /// `JP V0, nnn` becomes `JP Vx, xnn`, adding the register named by the target's
+    /// highest nibble, as on SUPER-CHIP.
+    pub jump_with_vx: bool,
+    /// `DRW` waits for the next frame, so at most one sprite is drawn per frame
+    /// as on the COSMAC VIP.
+    pub display_wait: bool,
+    /// `ADD I, Vx` sets VF when I passes the end of memory and clears it otherwise,
+    /// as on the Amiga interpreter.
+    pub index_overflow_vf: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            strict_sub_flag: false,
+            reset_vf_on_logic: false,
+            align_jumps: false,
+            wrap_x: true,
+            wrap_y: true,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_with_vx: false,
+            display_wait: false,
+            index_overflow_vf: false,
+        }
+    }
+}
+
+/// Quirks of a well known interpreter, see `Quirks::from_profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QuirkProfile {
+    /// The original COSMAC VIP interpreter: logic clears VF, shifts read Vy,
+    /// loads and stores move I, `DRW` waits for the next frame and sprites are clipped.
+    CosmacVip,
+    /// SUPER-CHIP as modern interpreters run it: shifts are in place, loads and stores
+    /// leave I alone, `JP V0` adds Vx and sprites are clipped.
+    SuperChipModern,
+    /// XO-CHIP: shifts read Vy, loads and stores move I and sprites wrap.
+    XoChip,
+    /// Any other combination.
+    Custom(Quirks),
+}
+
+impl QuirkProfile {
+    /// Every named profile.
+    pub const PRESETS: [QuirkProfile; 3] = [Self::CosmacVip, Self::SuperChipModern, Self::XoChip];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CosmacVip => "COSMAC VIP",
+            Self::SuperChipModern => "SUPER-CHIP (modern)",
+            Self::XoChip => "XO-CHIP",
+            Self::Custom(_) => "Custom",
+        }
+    }
+
+    /// Named profile with exactly these quirks, otherwise `Custom`.
+    pub fn detect(quirks: Quirks) -> Self {
+        Self::PRESETS
+            .into_iter()
+            .find(|profile| Quirks::from_profile(*profile) == quirks)
+            .unwrap_or(Self::Custom(quirks))
+    }
+}
+
+impl Quirks {
+    /// Quirks of a profile.
+    pub fn from_profile(profile: QuirkProfile) -> Self {
+        match profile {
+            QuirkProfile::CosmacVip => Self {
+                reset_vf_on_logic: true,
+                wrap_x: false,
+                wrap_y: false,
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                display_wait: true,
+                ..Self::default()
+            },
+            QuirkProfile::SuperChipModern => Self {
+                wrap_x: false,
+                wrap_y: false,
+                jump_with_vx: true,
+                ..Self::default()
+            },
+            QuirkProfile::XoChip => Self {
+                shift_uses_vy: true,
+                load_store_increments_i: true,
+                ..Self::default()
+            },
+            QuirkProfile::Custom(quirks) => quirks,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Drawing the screen to images and text, shared by screenshots, the app window and tools.
+
+use std::iter;
+
+use rand::RngCore;
+
+use crate::Chip8;
+
+/// Area of an image the screen was drawn to, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// Size of a screen pixel.
+    pub scale: usize,
+}
+
+/// Largest integer multiple of `screen` centered in `target`, both given as width and height.
+///
+/// The scale is at least 1, so a target smaller than the screen crops it.
+/// The offsets are then 0 rather than negative.
+pub fn fit(screen: (usize, usize), target: (usize, usize)) -> DrawRect {
+    let scale = (target.0 / screen.0.max(1))
+        .min(target.1 / screen.1.max(1))
+        .max(1);
+
+    let (width, height) = (screen.0 * scale, screen.1 * scale);
+
+    DrawRect {
+        x: target.0.saturating_sub(width) / 2,
+        y: target.1.saturating_sub(height) / 2,
+        width,
+        height,
+        scale,
+    }
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Draw the screen to an RGBA image, each pixel as a `scale` sized square.
+    /// Returns the pixels row by row with the image's width and height.
+    pub fn render_to_rgba(&self, fg: [u8; 4], bg: [u8; 4], scale: usize) -> (Vec<u8>, u32, u32) {
+        let (width, height) = self.screen_dimensions();
+        let mut rgba = Vec::with_capacity(width * height * scale * scale * 4);
+
+        for row in self.screen.iter() {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|on| iter::repeat_n(if *on { fg } else { bg }, scale))
+                .flatten()
+                .collect();
+
+            for _ in 0..scale {
+                rgba.extend_from_slice(&line);
+            }
+        }
+
+        (rgba, (width * scale) as u32, (height * scale) as u32)
+    }
+
+    /// Draw the screen as text, one line per row, e.g. to print it from a terminal.
+    pub fn render_to_ascii(&self, on: char, off: char) -> String {
+        self.screen
+            .iter()
+            .map(|row| {
+                let mut line: String = row.iter().map(|lit| if *lit { on } else { off }).collect();
+                line.push('\n');
+                line
+            })
+            .collect()
+    }
+
+    /// Draw the screen to a `width` by `height` RGBA image, scaled and centered by `fit`
+    /// with the remaining area filled with `letterbox`.
+    ///
+    /// Returns the pixels row by row and where the screen was drawn.
+    pub fn render_fitted_rgba(
+        &self,
+        fg: [u8; 4],
+        bg: [u8; 4],
+        letterbox: [u8; 4],
+        (width, height): (usize, usize),
+    ) -> (Vec<u8>, DrawRect) {
+        let rect = fit(self.screen_dimensions(), (width, height));
+        let mut rgba: Vec<u8> = iter::repeat_n(letterbox, width * height)
+            .flatten()
+            .collect();
+
+        for y in rect.y..(rect.y + rect.height).min(height) {
+            let row = &self.screen[(y - rect.y) / rect.scale];
+
+            for x in rect.x..(rect.x + rect.width).min(width) {
+                let color = if row[(x - rect.x) / rect.scale] {
+                    fg
+                } else {
+                    bg
+                };
+                rgba[(y * width + x) * 4..][..4].copy_from_slice(&color);
+            }
+        }
+
+        (rgba, rect)
+    }
+}
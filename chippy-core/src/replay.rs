@@ -0,0 +1,171 @@
+//! Deterministic replay of a recorded session.
+//!
+//! Replaying the same ROM with the same replay must produce the same screen on every platform.
+//! This relies on all state being driven by the replay instead of the host:
+//!
+//! * `RND` draws from `StdRng` seeded with the replay's seed. This is stable across
+//!   platforms but not across `rand` releases, so bumping `rand` may change results.
+//! * The delay and sound timers tick once per replayed frame instead of from the wall clock.
+//! * Keys are only changed by the replay, at frame boundaries.
+//! * Execution runs a fixed amount of instructions per frame.
+//!
+//! Audio is not part of the verified state, the beeper's waveform uses floats.
+//!
+//! Replays are text, one directive per line with `#` comments:
+//!
+//! ```text
+//! seed 1234
+//! ipf 10
+//! frames 300
+//! # Keys held from frame 60 until the next change, `-` for none.
+//! keys 60 5A
+//! keys 90 -
+//! ```
+
+use std::{error::Error, fmt, str::FromStr};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{rom, Chip8, Chip8Error};
+
+/// Recorded inputs and settings needed to rerun a session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    /// Instructions executed per frame.
+    pub ipf: usize,
+    /// Amount of 60 Hz frames to run.
+    pub frames: usize,
+    /// Keys held starting at a frame, ordered by frame.
+    pub inputs: Vec<(usize, [bool; 16])>,
+}
+
+impl Replay {
+    /// Run the replay on a fresh machine, returning the machine once all frames ran.
+    pub fn run(&self, rom: Vec<u8>) -> Result<Chip8, Chip8Error> {
+        let mut chip8 = Chip8::with_rng(StdRng::seed_from_u64(self.seed));
+        chip8.load_rom(rom);
+
+        let mut inputs = self.inputs.iter().peekable();
+
+        for frame in 0..self.frames {
+            while let Some((_, keys)) = inputs.next_if(|(at, _)| *at <= frame) {
+                chip8.keypad.keys = *keys;
+            }
+
+            chip8.run_cycles(self.ipf)?;
+            chip8.tick_timers();
+        }
+
+        Ok(chip8)
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ParseReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut replay = Replay::default();
+
+        for (index, line) in s.lines().enumerate() {
+            let error = || ParseReplayError { line: index + 1 };
+
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+
+            match (words.next(), words.next(), words.next()) {
+                (None, _, _) => continue,
+                (Some("seed"), Some(seed), None) => {
+                    replay.seed = seed.parse().map_err(|_| error())?
+                }
+                (Some("ipf"), Some(ipf), None) => replay.ipf = ipf.parse().map_err(|_| error())?,
+                (Some("frames"), Some(frames), None) => {
+                    replay.frames = frames.parse().map_err(|_| error())?
+                }
+                (Some("keys"), Some(frame), Some(held)) => {
+                    let frame = frame.parse().map_err(|_| error())?;
+
+                    let mut keys = [false; 16];
+                    if held != "-" {
+                        for key in held.chars() {
+                            keys[key.to_digit(16).ok_or_else(error)? as usize] = true;
+                        }
+                    }
+
+                    replay.inputs.push((frame, keys));
+                }
+                _ => return Err(error()),
+            }
+
+            if words.next().is_some() {
+                return Err(error());
+            }
+        }
+
+        replay.inputs.sort_by_key(|(frame, _)| *frame);
+        Ok(replay)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "ipf {}", self.ipf)?;
+        writeln!(f, "frames {}", self.frames)?;
+
+        for (frame, keys) in &self.inputs {
+            let held: String = (0..16)
+                .filter(|key| keys[*key])
+                .map(|key| format!("{:X}", key))
+                .collect();
+
+            writeln!(
+                f,
+                "keys {} {}",
+                frame,
+                if held.is_empty() { "-" } else { &held }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Replay text which couldn't be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseReplayError {
+    /// Line number starting at 1.
+    pub line: usize,
+}
+
+impl Error for ParseReplayError {}
+
+impl fmt::Display for ParseReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid replay directive on line {}", self.line)
+    }
+}
+
+/// Hash of the screen contents, stable across platforms.
+pub fn screen_hash(screen: &[[bool; 64]; 32]) -> u64 {
+    let pixels: Vec<u8> = screen.iter().flatten().map(|on| *on as u8).collect();
+    rom::hash(&pixels)
+}
+
+/// Replay a session and assert the final screen hashes to `expected_hash`.
+///
+/// # Panics
+///
+/// If execution fails or the hash doesn't match.
+pub fn replay_and_verify(rom: &[u8], replay: &Replay, expected_hash: u64) {
+    let chip8 = replay
+        .run(rom.to_vec())
+        .unwrap_or_else(|err| panic!("Replay failed: {}", err));
+
+    let hash = screen_hash(&chip8.screen);
+    assert_eq!(
+        hash, expected_hash,
+        "Screen hash {:016X} doesn't match the expected {:016X}",
+        hash, expected_hash
+    );
+}
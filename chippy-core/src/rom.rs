@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crate::{
+    opcode::{extract_opcode_from_array, OpCode},
+    types::C8Addr,
+    MemorySize, PROGRAM_ADDR,
+};
+
+/// CHIP-8 dialect a ROM appears to be written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Chip8 => "CHIP-8",
+            Self::SuperChip => "SUPER-CHIP",
+            Self::XoChip => "XO-CHIP",
+        })
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a ROM, used to identify it across runs.
+pub fn hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Guess the variant by scanning for extension opcodes.
+/// This is a heuristic since data can look like instructions.
+pub fn detect_variant(rom: &[u8]) -> Variant {
+    let mut variant = Variant::Chip8;
+
+    for word in rom.chunks_exact(2) {
+        let word = (word[0] as u16) << 8 | word[1] as u16;
+
+        // 5xy2, 5xy3, F000, Fn01, F002, Fx3A
+        if matches!(word & 0xF00F, 0x5002 | 0x5003)
+            || matches!(word, 0xF000 | 0xF002)
+            || matches!(word & 0xF0FF, 0xF001 | 0xF03A)
+        {
+            return Variant::XoChip;
+        }
+
+        // 00Cn, 00FB-00FF, Fx30, Fx75, Fx85
+        if matches!(word, 0x00C1..=0x00CF | 0x00FB..=0x00FF)
+            || matches!(word & 0xF0FF, 0xF030 | 0xF075 | 0xF085)
+        {
+            variant = Variant::SuperChip;
+        }
+    }
+
+    variant
+}
+
+/// Likely problem found by `validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomWarning {
+    /// Length isn't a multiple of the instruction size.
+    OddLength(usize),
+    /// Jump or call at `addr` to `target`, outside of the loaded ROM.
+    JumpOutside { addr: C8Addr, target: C8Addr },
+    /// Uses extension opcodes, so the ROM needs an interpreter for that variant.
+    Extension(Variant),
+    /// Sprite drawn at `addr` would read past the end of memory from `index`.
+    SpriteOutside { addr: C8Addr, index: C8Addr },
+}
+
+impl fmt::Display for RomWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OddLength(len) => write!(f, "Odd length of {} bytes", len),
+            Self::JumpOutside { addr, target } => write!(
+                f,
+                "Jump at {:04X} to {:04X}, outside of the ROM",
+                addr, target
+            ),
+            Self::Extension(variant) => write!(f, "Uses {} opcodes", variant),
+            Self::SpriteOutside { addr, index } => write!(
+                f,
+                "Sprite at {:04X} from I = {:04X} reads past memory",
+                addr, index
+            ),
+        }
+    }
+}
+
+/// Scan a ROM for likely problems before running it.
+///
+/// Like `detect_variant` this is a heuristic, every aligned word is treated as an instruction
+/// and `I` is assumed to hold the last address loaded before a `DRW`.
+/// Sprites are checked against the end of `memory_size`.
+pub fn validate(rom: &[u8], memory_size: MemorySize) -> Vec<RomWarning> {
+    let mut warnings = Vec::new();
+
+    if !rom.len().is_multiple_of(2) {
+        warnings.push(RomWarning::OddLength(rom.len()));
+    }
+
+    let variant = detect_variant(rom);
+    if variant != Variant::Chip8 {
+        warnings.push(RomWarning::Extension(variant));
+    }
+
+    let loaded = PROGRAM_ADDR..PROGRAM_ADDR + rom.len();
+    let mut index = None;
+
+    for offset in (0..rom.len() / 2 * 2).step_by(2) {
+        let addr = (PROGRAM_ADDR + offset) as C8Addr;
+
+        match OpCode::from_opcode(extract_opcode_from_array(rom, offset)) {
+            OpCode::JP(target) | OpCode::CALL(target) | OpCode::JP0(target)
+                if !loaded.contains(&(target as usize)) =>
+            {
+                warnings.push(RomWarning::JumpOutside { addr, target })
+            }
+            OpCode::LDI(addr) => index = Some(addr),
+            OpCode::DRW(_, _, n) => {
+                let past_end = |index: &C8Addr| *index as usize + n as usize > memory_size.bytes();
+                if let Some(index) = index.filter(past_end) {
+                    warnings.push(RomWarning::SpriteOutside { addr, index });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    warnings
+}
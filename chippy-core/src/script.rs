@@ -0,0 +1,173 @@
+//! Rhai scripting hooks, enabled with the `scripting` feature.
+//!
+//! Scripts define any of these functions, which run on the emulation thread:
+//!
+//! * `on_frame()` - Every 60 Hz timer tick.
+//! * `on_breakpoint(addr)` - When execution stops on a breakpoint.
+//!
+//! Inside them `this` is the machine, with these methods:
+//!
+//! * `v(x)` / `set_v(x, value)` - Read/write register Vx.
+//! * `read(addr)` / `write(addr, value)` - Read/write a byte of memory.
+//! * `pixel(x, y)` - Is the pixel on.
+//! * `pc`, `i` - Program counter and index register.
+//!
+//! For example, a cheat keeping V3 at or below 10:
+//!
+//! ```text
+//! fn on_frame() {
+//!     if this.v(3) > 10 {
+//!         this.set_v(3, 10);
+//!     }
+//! }
+//! ```
+
+use rand::RngCore;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use crate::{types::C8Addr, Chip8};
+
+/// Compiled script with its own state.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+impl Script {
+    /// Compile a script and run its top level statements once.
+    pub fn new(source: &str) -> Result<Self, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        engine
+            .register_type_with_name::<Machine>("Machine")
+            .register_get("pc", |machine: &mut Machine| machine.pc as i64)
+            .register_get("i", |machine: &mut Machine| machine.index as i64)
+            .register_fn("v", Machine::v)
+            .register_fn("set_v", Machine::set_v)
+            .register_fn("read", Machine::read)
+            .register_fn("write", Machine::write)
+            .register_fn("pixel", Machine::pixel);
+
+        let ast = engine.compile(source)?;
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast)?;
+
+        Ok(Self { engine, ast, scope })
+    }
+
+    /// Call a script function with `this` bound to the machine, writing back any changes.
+    /// Functions the script doesn't define are skipped.
+    fn call<R: RngCore>(
+        &mut self,
+        chip8: &mut Chip8<R>,
+        name: &str,
+        args: impl rhai::FuncArgs,
+    ) -> Result<(), Box<EvalAltResult>> {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == name)
+        {
+            return Ok(());
+        }
+
+        let mut this = Dynamic::from(Machine::from_chip8(chip8));
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut this);
+
+        let _ = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            name,
+            args,
+        )?;
+
+        this.cast::<Machine>().write_back(chip8);
+        Ok(())
+    }
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Load a script replacing the previous one.
+    pub fn set_script(&mut self, script: Option<Script>) {
+        self.script = script;
+    }
+
+    pub(crate) fn script_frame(&mut self) {
+        self.run_script("on_frame", ());
+    }
+
+    pub(crate) fn script_breakpoint(&mut self) {
+        let addr = self.pc as i64;
+        self.run_script("on_breakpoint", (addr,));
+    }
+
+    fn run_script(&mut self, name: &str, args: impl rhai::FuncArgs) {
+        // Take the script out so it can borrow the machine.
+        if let Some(mut script) = self.script.take() {
+            if let Err(err) = script.call(self, name, args) {
+                log::warn!("Script error in {}: {}", name, err);
+            }
+
+            self.script = Some(script);
+        }
+    }
+}
+
+/// Copy of the machine state exposed to scripts as `this`.
+#[derive(Clone)]
+struct Machine {
+    pc: C8Addr,
+    index: C8Addr,
+    registers: [u8; 16],
+    memory: Vec<u8>,
+    screen: [[bool; 64]; 32],
+}
+
+impl Machine {
+    fn from_chip8<R: RngCore>(chip8: &Chip8<R>) -> Self {
+        Self {
+            pc: chip8.pc,
+            index: chip8.index,
+            registers: chip8.registers,
+            memory: chip8.memory.to_vec(),
+            screen: chip8.screen,
+        }
+    }
+
+    /// Registers and memory are the only writable state.
+    fn write_back<R: RngCore>(self, chip8: &mut Chip8<R>) {
+        chip8.registers = self.registers;
+        chip8.memory.copy_from_slice(&self.memory);
+    }
+
+    fn v(&mut self, x: i64) -> i64 {
+        self.registers.get(x as usize).copied().unwrap_or(0) as i64
+    }
+
+    fn set_v(&mut self, x: i64, value: i64) {
+        if let Some(register) = self.registers.get_mut(x as usize) {
+            *register = value as u8;
+        }
+    }
+
+    fn read(&mut self, addr: i64) -> i64 {
+        self.memory.get(addr as usize).copied().unwrap_or(0) as i64
+    }
+
+    fn write(&mut self, addr: i64, value: i64) {
+        if let Some(byte) = self.memory.get_mut(addr as usize) {
+            *byte = value as u8;
+        }
+    }
+
+    fn pixel(&mut self, x: i64, y: i64) -> bool {
+        self.screen
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+}
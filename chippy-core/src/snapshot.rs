@@ -0,0 +1,176 @@
+//! Copies of the machine state, e.g. for save states.
+//!
+//! Snapshots carry `SNAPSHOT_VERSION` and the hash of their ROM,
+//! so a save state from another layout or another game is refused instead of corrupting the machine.
+
+use std::{error::Error, fmt};
+
+use rand::RngCore;
+
+use crate::{Chip8, Quirks, PROGRAM_ADDR};
+
+/// Layout version of `Snapshot`, bumped whenever the meaning of its fields changes.
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+/// Reason `Chip8::restore` refused a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// Taken by a version with a different state layout.
+    Version { found: u32, expected: u32 },
+    /// Taken while another ROM was loaded.
+    Rom { found: u64, expected: u64 },
+    /// Taken with a different screen resolution, as width and height.
+    Screen {
+        found: (usize, usize),
+        expected: (usize, usize),
+    },
+    /// Bytes passed to `Chip8::load_state_bytes` aren't a snapshot.
+    Corrupt,
+}
+
+impl Error for SnapshotError {}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Version { found, expected } => write!(
+                f,
+                "Save state version {} isn't supported, expected {}",
+                found, expected
+            ),
+            Self::Rom { found, expected } => write!(
+                f,
+                "Save state is for ROM {:016X}, the loaded ROM is {:016X}",
+                found, expected
+            ),
+            Self::Screen { found, expected } => write!(
+                f,
+                "Save state has a {}x{} screen, expected {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            Self::Corrupt => write!(f, "Save state is corrupt"),
+        }
+    }
+}
+
+/// State of a machine at a point in time, see `Chip8::snapshot`.
+///
+/// Settings such as breakpoints, the backend or cycle costs aren't part of the state
+/// and are kept when restoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// `SNAPSHOT_VERSION` when the snapshot was taken.
+    pub version: u32,
+    /// `rom::hash` of `rom`.
+    pub rom_hash: u64,
+    /// Quirks the state was produced with, restored along with it.
+    pub quirks: Quirks,
+    /// Width and height of `screen`.
+    pub screen_size: (usize, usize),
+    pub pc: u16,
+    pub sp: usize,
+    pub index: u16,
+    pub memory: Vec<u8>,
+    pub stack: [u16; 16],
+    pub registers: [u8; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// Pixels row by row.
+    pub screen: Vec<bool>,
+    pub keys: [bool; 16],
+    /// `Chip8::cycle_count` when the snapshot was taken.
+    pub cycle_count: u64,
+    /// ROM restored by `Chip8::reset`.
+    pub rom: Vec<u8>,
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Copy the machine state.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            rom_hash: self.rom_hash(),
+            quirks: self.quirks,
+            screen_size: self.screen_dimensions(),
+            pc: self.pc,
+            sp: self.sp,
+            index: self.index,
+            memory: self.memory.to_vec(),
+            stack: self.stack,
+            registers: self.registers,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            screen: self.screen.iter().flatten().copied().collect(),
+            keys: self.keypad.keys,
+            cycle_count: self.cycle_count,
+            rom: self.rom.clone(),
+        }
+    }
+
+    /// Return to the state of a snapshot taken with the same ROM loaded.
+    /// Memory beyond what the snapshot holds is cleared.
+    ///
+    /// Nothing is changed if the snapshot is refused.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<(), SnapshotError> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::Version {
+                found: snapshot.version,
+                expected: SNAPSHOT_VERSION,
+            });
+        }
+
+        if snapshot.rom_hash != self.rom_hash() {
+            return Err(SnapshotError::Rom {
+                found: snapshot.rom_hash,
+                expected: self.rom_hash(),
+            });
+        }
+
+        if snapshot.screen_size != self.screen_dimensions() {
+            return Err(SnapshotError::Screen {
+                found: snapshot.screen_size,
+                expected: self.screen_dimensions(),
+            });
+        }
+
+        self.set_quirks(snapshot.quirks);
+        self.pc = snapshot.pc;
+        self.sp = snapshot.sp.min(self.stack.len());
+        self.index = snapshot.index;
+        self.stack = snapshot.stack;
+        self.registers = snapshot.registers;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.keypad.keys = snapshot.keys;
+        self.cycle_count = snapshot.cycle_count;
+        self.rom = snapshot.rom.clone();
+        self.rom.truncate(self.memory.len() - PROGRAM_ADDR);
+
+        self.memory.fill(0);
+        let len = snapshot.memory.len().min(self.memory.len());
+        self.memory[..len].copy_from_slice(&snapshot.memory[..len]);
+
+        for (i, pixel) in self.screen.iter_mut().flatten().enumerate() {
+            *pixel = snapshot.screen.get(i).copied().unwrap_or(false);
+        }
+
+        self.screen_dirty = true;
+        self.stopped_at = None;
+        Ok(())
+    }
+
+    /// Encode the machine state as a compact binary blob, e.g. for quick saves.
+    /// The blob carries the same version, ROM hash and quirks as `snapshot`.
+    #[cfg(feature = "bincode")]
+    pub fn save_state_bytes(&self) -> Vec<u8> {
+        bincode::serialize(&self.snapshot()).expect("Snapshots always serialize")
+    }
+
+    /// Return to a state encoded by `save_state_bytes`, see `restore`.
+    #[cfg(feature = "bincode")]
+    pub fn load_state_bytes(&mut self, bytes: &[u8]) -> Result<(), SnapshotError> {
+        let snapshot: Snapshot = bincode::deserialize(bytes).map_err(|_| SnapshotError::Corrupt)?;
+        self.restore(&snapshot)
+    }
+}
@@ -0,0 +1,244 @@
+//! Per-instruction execution tracing, e.g. to diff against a reference emulator.
+//!
+//! While a trace hook is set every instruction goes through the interpreter,
+//! since compiled blocks don't stop between instructions.
+//!
+//! To find where execution diverges from a reference emulator, write a trace in
+//! `TraceFormat::State` (see `state_line`), produce the same format with the reference
+//! and `diff` the two. The first differing line is the first instruction with a different result.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use rand::{rngs::StdRng, RngCore};
+
+use crate::{
+    opcode::OpCode,
+    types::{C8Addr, C8Byte},
+    Chip8,
+};
+
+/// Lines written between flushes, so a crash loses little of the trace.
+const FLUSH_INTERVAL: u64 = 1000;
+
+/// Handler called after every executed instruction, see `Chip8::set_trace_hook`.
+pub type TraceHook<R = StdRng> = Box<dyn FnMut(&TraceEvent, &Chip8<R>) + Send + Sync>;
+
+/// Instruction which was just executed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TraceEvent {
+    /// Instructions executed before this one since the machine was created.
+    pub cycle: u64,
+    pub pc: C8Addr,
+    /// Raw instruction word.
+    pub word: u16,
+    pub opcode: OpCode,
+}
+
+/// Line format of a `TraceWriter`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Readable listing of each instruction:
+    ///
+    /// ```text
+    /// 0000000000 0200 6005 LD V0, 05             V 05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 I 0000
+    /// ```
+    ///
+    /// Columns are the cycle, PC, instruction word, mnemonic, then V0-VF and I after execution.
+    #[default]
+    Instructions,
+    /// State after each instruction for comparing against other emulators, see `state_line`.
+    State,
+}
+
+/// Architectural state after a traced instruction as a single line, without a line break:
+///
+/// ```text
+/// PC=0208 I=0300 SP=1 V=05000000000000000000000000000000 S=0206 M=0300:02,0301:03
+/// ```
+///
+/// * `PC`, `I` - Program counter and index register, 4 hex digits.
+/// * `SP` - Stack pointer in hex.
+/// * `V` - V0 to VF, 2 hex digits each.
+/// * `S` - Used stack entries from the bottom, comma separated, empty when the stack is empty.
+/// * `M` - Memory written by the instruction as `address:value`, comma separated in the order
+///   written, empty when nothing was written.
+///
+/// All hex digits are uppercase. Timers, the screen and keys aren't included.
+/// Only valid while a trace hook runs, since writes are only recorded while tracing.
+pub fn state_line<R: RngCore>(chip8: &Chip8<R>) -> String {
+    let registers: String = chip8
+        .registers
+        .iter()
+        .map(|value| format!("{:02X}", value))
+        .collect();
+
+    let stack: Vec<String> = chip8.stack[..chip8.sp]
+        .iter()
+        .map(|addr| format!("{:04X}", addr))
+        .collect();
+
+    let writes: Vec<String> = chip8
+        .traced_writes()
+        .iter()
+        .map(|(addr, value)| format!("{:04X}:{:02X}", addr, value))
+        .collect();
+
+    format!(
+        "PC={:04X} I={:04X} SP={:X} V={} S={} M={}",
+        chip8.pc,
+        chip8.index,
+        chip8.sp,
+        registers,
+        stack.join(","),
+        writes.join(",")
+    )
+}
+
+/// Writes a trace to a file, one line per instruction in a `TraceFormat`.
+///
+/// Once the file reaches `max_bytes` it's moved to `<path>.1`, replacing any previous one,
+/// and a new file is started. So at most twice `max_bytes` is kept.
+pub struct TraceWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    max_bytes: u64,
+    written: u64,
+    lines: u64,
+    format: TraceFormat,
+}
+
+impl TraceWriter {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> io::Result<Self> {
+        let path = path.into();
+        let file = BufWriter::new(File::create(&path)?);
+
+        Ok(Self {
+            path,
+            file,
+            max_bytes,
+            written: 0,
+            lines: 0,
+            format: TraceFormat::default(),
+        })
+    }
+
+    /// Format of the lines written from now on.
+    pub fn set_format(&mut self, format: TraceFormat) {
+        self.format = format;
+    }
+
+    /// Write the line for an instruction.
+    pub fn write<R: RngCore>(&mut self, event: &TraceEvent, chip8: &Chip8<R>) -> io::Result<()> {
+        if self.written >= self.max_bytes {
+            self.rotate()?;
+        }
+
+        let line = match self.format {
+            TraceFormat::Instructions => {
+                let mut line = format!(
+                    "{:010} {:04X} {:04X} {:<20} V",
+                    event.cycle,
+                    event.pc,
+                    event.word,
+                    event.opcode.get_opcode_str().0
+                );
+
+                for value in chip8.registers {
+                    line += &format!(" {:02X}", value);
+                }
+
+                line += &format!(" I {:04X}\n", chip8.index);
+                line
+            }
+            TraceFormat::State => state_line(chip8) + "\n",
+        };
+
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+
+        self.lines += 1;
+        if self.lines.is_multiple_of(FLUSH_INTERVAL) {
+            self.file.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Hook writing every instruction, errors are printed and stop the trace.
+    pub fn into_hook<R: RngCore>(mut self) -> TraceHook<R> {
+        let mut failed = false;
+
+        Box::new(move |event, chip8| {
+            if failed {
+                return;
+            }
+
+            if let Err(err) = self.write(event, chip8) {
+                log::error!("Unable to write trace {}: {}", self.path.display(), err);
+                failed = true;
+            }
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, rotated)?;
+
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Drop for TraceWriter {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Call `hook` after every executed instruction, `None` to stop tracing.
+    pub fn set_trace_hook(&mut self, hook: Option<TraceHook<R>>) {
+        self.trace_hook = hook;
+    }
+
+    /// Instructions executed since the machine was created.
+    pub fn cycle_count(&self) -> u64 {
+        self.cycle_count
+    }
+
+    /// Memory written by the instruction passed to the trace hook, as address and value.
+    /// Empty outside of the trace hook.
+    pub fn traced_writes(&self) -> &[(C8Addr, C8Byte)] {
+        &self.traced_writes
+    }
+
+    pub(crate) fn tracing(&self) -> bool {
+        self.trace_hook.is_some()
+    }
+
+    /// Pass an executed instruction to the trace hook.
+    pub(crate) fn trace(&mut self, pc: C8Addr, word: u16, opcode: OpCode) {
+        // Take the hook out so it can borrow the machine.
+        if let Some(mut hook) = self.trace_hook.take() {
+            let event = TraceEvent {
+                cycle: self.cycle_count - 1,
+                pc,
+                word,
+                opcode,
+            };
+
+            hook(&event, self);
+            self.trace_hook.get_or_insert(hook);
+        }
+
+        self.traced_writes.clear();
+    }
+}
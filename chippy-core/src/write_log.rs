@@ -0,0 +1,65 @@
+//! History of recent memory writes, e.g. to follow self-modifying code
+//! or find what corrupted a value.
+//!
+//! Unlike breakpoints this never stops execution, it only records.
+//! Writes made by instructions are logged (`LD B, Vx`, `LD [I], Vx` and `Chip8::write_memory`),
+//! not changes made to `Chip8::memory` directly.
+//! Writes to mapped I/O go to its handler instead of memory and aren't logged either.
+
+use std::collections::VecDeque;
+
+use rand::RngCore;
+
+use crate::{
+    types::{C8Addr, C8Byte},
+    Chip8,
+};
+
+/// Memory write recorded by the write log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryWrite {
+    pub addr: C8Addr,
+    /// Value before the write.
+    pub old: C8Byte,
+    pub new: C8Byte,
+    /// Instruction which wrote, see `Chip8::cycle_count`.
+    pub cycle: u64,
+}
+
+impl<R: RngCore> Chip8<R> {
+    /// Keep the last `capacity` memory writes, 0 stops recording and drops the log.
+    pub fn set_write_log(&mut self, capacity: usize) {
+        self.write_log_capacity = capacity;
+
+        // Keep the newest writes.
+        let excess = self.write_log.len().saturating_sub(capacity);
+        self.write_log.drain(..excess);
+        self.write_log.shrink_to(capacity);
+    }
+
+    /// Recorded writes, oldest first.
+    pub fn recent_writes(&self) -> &VecDeque<MemoryWrite> {
+        &self.write_log
+    }
+
+    pub fn clear_write_log(&mut self) {
+        self.write_log.clear();
+    }
+
+    pub(crate) fn log_write(&mut self, addr: usize, new: C8Byte) {
+        if self.write_log_capacity == 0 {
+            return;
+        }
+
+        if self.write_log.len() == self.write_log_capacity {
+            self.write_log.pop_front();
+        }
+
+        self.write_log.push_back(MemoryWrite {
+            addr: addr as C8Addr,
+            old: self.memory[addr],
+            new,
+            cycle: self.cycle_count,
+        });
+    }
+}
@@ -0,0 +1,124 @@
+//! Results and VF of the arithmetic opcodes over every pair of operands, on every backend.
+
+mod common;
+
+use chippy_core::{Chip8, Quirks};
+use common::BACKENDS;
+
+/// Run the loaded instruction with V1 = `x` and V2 = `y`, returning V1 and VF.
+fn execute(chip8: &mut Chip8, x: u8, y: u8) -> (u8, u8) {
+    chip8.pc = 0x200;
+    chip8.registers[1] = x;
+    chip8.registers[2] = y;
+    chip8.registers[0xF] = 0xAA;
+
+    chip8.run_cycles(1).unwrap();
+    (chip8.registers[1], chip8.registers[0xF])
+}
+
+/// Check `opcode` (operating on V1 and V2) against `expected` for every operand pair.
+fn check(opcode: [u8; 2], expected: impl Fn(u8, u8) -> (u8, u8)) {
+    check_with(Quirks::default(), opcode, expected);
+}
+
+fn check_with(quirks: Quirks, opcode: [u8; 2], expected: impl Fn(u8, u8) -> (u8, u8)) {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &opcode);
+        chip8.set_quirks(quirks);
+
+        for x in 0..=u8::MAX {
+            for y in 0..=u8::MAX {
+                assert_eq!(
+                    execute(&mut chip8, x, y),
+                    expected(x, y),
+                    "{:02X}{:02X} with V1 = {}, V2 = {} on {:?}",
+                    opcode[0],
+                    opcode[1],
+                    x,
+                    y,
+                    backend
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn add_sets_carry() {
+    check([0x81, 0x24], |x, y| {
+        let (result, carry) = x.overflowing_add(y);
+        (result, carry as u8)
+    });
+}
+
+#[test]
+fn sub_sets_no_borrow() {
+    check([0x81, 0x25], |x, y| (x.wrapping_sub(y), (x >= y) as u8));
+}
+
+#[test]
+fn subn_sets_no_borrow() {
+    check([0x81, 0x27], |x, y| (y.wrapping_sub(x), (y >= x) as u8));
+}
+
+#[test]
+fn sub_equal_operands_set_vf() {
+    let mut chip8 = common::machine(&[0x81, 0x25]);
+
+    assert_eq!(execute(&mut chip8, 7, 7), (0, 1));
+}
+
+#[test]
+fn strict_sub_sets_greater() {
+    let quirks = Quirks {
+        strict_sub_flag: true,
+        ..Quirks::default()
+    };
+
+    check_with(quirks, [0x81, 0x25], |x, y| {
+        (x.wrapping_sub(y), (x > y) as u8)
+    });
+    check_with(quirks, [0x81, 0x27], |x, y| {
+        (y.wrapping_sub(x), (y > x) as u8)
+    });
+}
+
+#[test]
+fn logic_keeps_vf() {
+    check([0x81, 0x21], |x, y| (x | y, 0xAA));
+    check([0x81, 0x22], |x, y| (x & y, 0xAA));
+    check([0x81, 0x23], |x, y| (x ^ y, 0xAA));
+}
+
+#[test]
+fn logic_resets_vf_with_quirk() {
+    let quirks = Quirks {
+        reset_vf_on_logic: true,
+        ..Quirks::default()
+    };
+
+    check_with(quirks, [0x81, 0x21], |x, y| (x | y, 0));
+    check_with(quirks, [0x81, 0x22], |x, y| (x & y, 0));
+    check_with(quirks, [0x81, 0x23], |x, y| (x ^ y, 0));
+}
+
+#[test]
+fn shr_sets_shifted_out_bit() {
+    check([0x81, 0x26], |x, _| (x >> 1, x & 1));
+}
+
+#[test]
+fn shl_sets_shifted_out_bit() {
+    check([0x81, 0x2E], |x, _| (x << 1, x >> 7));
+}
+
+#[test]
+fn shifts_read_vy_with_quirk() {
+    let quirks = Quirks {
+        shift_uses_vy: true,
+        ..Quirks::default()
+    };
+
+    check_with(quirks, [0x81, 0x26], |_, y| (y >> 1, y & 1));
+    check_with(quirks, [0x81, 0x2E], |_, y| (y << 1, y >> 7));
+}
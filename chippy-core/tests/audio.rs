@@ -0,0 +1,73 @@
+//! Beep waveforms and the samples handed to the audio device.
+
+use chippy_core::{
+    audio::{Beeper, Waveform},
+    Chip8,
+};
+
+const SAMPLE_RATE: u32 = 6400;
+/// Samples in a period of the 100 Hz beep, exact so square waves flip on the same sample.
+const PERIOD: usize = 64;
+
+/// `len` samples of a 100 Hz beep.
+fn samples(waveform: Waveform, len: usize) -> Vec<f32> {
+    let mut beeper = Beeper::default();
+    beeper.frequency = 100.0;
+    beeper.waveform = waveform;
+
+    (0..len).map(|_| beeper.next_sample(SAMPLE_RATE)).collect()
+}
+
+#[test]
+fn waves_span_the_full_range() {
+    for waveform in [Waveform::Square, Waveform::Sine, Waveform::Triangle] {
+        let samples = samples(waveform, PERIOD);
+        let max = samples.iter().copied().fold(f32::MIN, f32::max);
+        let min = samples.iter().copied().fold(f32::MAX, f32::min);
+
+        assert!((0.99..=1.0).contains(&max), "{:?}", waveform);
+        assert!((-1.0..=-0.99).contains(&min), "{:?}", waveform);
+    }
+}
+
+#[test]
+fn waves_repeat_every_period() {
+    for waveform in [Waveform::Square, Waveform::Sine, Waveform::Triangle] {
+        let samples = samples(waveform, 10 * PERIOD);
+
+        for (i, (a, b)) in samples.iter().zip(&samples[PERIOD..]).enumerate() {
+            assert!((a - b).abs() < 1e-3, "{:?} sample {}", waveform, i);
+        }
+    }
+
+    // Peaking a quarter period in, rather than repeating within a period.
+    let sine = samples(Waveform::Sine, PERIOD);
+    assert!((sine[PERIOD / 4] - 1.0).abs() < 1e-3);
+}
+
+/// Samples from a machine beeping at `volume`.
+fn beeping(volume: f32, muted: bool) -> Vec<f32> {
+    let mut chip8 = Chip8::new();
+    chip8.sound_timer = 10;
+    chip8.beeper.volume = volume;
+    chip8.beeper.muted = muted;
+
+    let mut buffer = vec![f32::NAN; 2 * PERIOD];
+    chip8.audio_samples(&mut buffer, SAMPLE_RATE, 2);
+    buffer
+}
+
+#[test]
+fn volume_scales_samples() {
+    let loud = beeping(1.0, false);
+    let quiet = beeping(0.25, false);
+
+    assert!(loud.iter().all(|sample| sample.abs() == 1.0));
+    assert!(quiet.iter().all(|sample| sample.abs() == 0.25));
+    assert!(beeping(0.0, false).iter().all(|sample| *sample == 0.0));
+}
+
+#[test]
+fn muting_silences_samples() {
+    assert!(beeping(1.0, true).iter().all(|sample| *sample == 0.0));
+}
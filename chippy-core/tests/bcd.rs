@@ -0,0 +1,14 @@
+//! Reading the digits stored by `LD B, Vx` back with `Chip8::bcd_at`.
+
+mod common;
+
+#[test]
+fn digits_of_stored_value() {
+    // `LD V0, EA`, `LD I, 0300`, `LD B, V0`.
+    let mut chip8 = common::machine(&[0x60, 0xEA, 0xA3, 0x00, 0xF0, 0x33]);
+    chip8.step(3).unwrap();
+
+    assert_eq!(chip8.register(0), 234);
+    assert_eq!(chip8.index(), 0x300);
+    assert_eq!(chip8.bcd_at(0x300), (2, 3, 4));
+}
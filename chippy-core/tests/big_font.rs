@@ -0,0 +1,19 @@
+use chippy_core::{opcode::OpCode, Chip8, BIG_FONT_ADDR};
+
+#[test]
+fn fx30_points_at_big_digit() {
+    // LD V3, 07 then LD HF, V3.
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(vec![0x63, 0x07, 0xF3, 0x30]);
+    chip8.run_cycles(2).unwrap();
+
+    assert_eq!(chip8.index() as usize, BIG_FONT_ADDR + 7 * 10);
+    // Top rows of the big 7.
+    assert_eq!(chip8.memory[chip8.index() as usize..][..2], [0xFF, 0xFF]);
+}
+
+#[test]
+fn fx30_decodes() {
+    assert_eq!(OpCode::from_opcode(0xF330), OpCode::LDBigSprite(3));
+    assert_eq!(OpCode::LDBigSprite(3).get_opcode_str().0, "LD HF, V3");
+}
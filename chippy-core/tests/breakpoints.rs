@@ -0,0 +1,129 @@
+//! Stopping on breakpoints and stepping through a program.
+
+mod common;
+
+use chippy_core::{
+    breakpoint::{Condition, RunOutcome},
+    opcode::OpCode,
+};
+
+/// `ADD V0, 01` then `JP 0200`, counting up in V0 forever.
+const COUNT_UP: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+/// `DRW V0, V0, 1` twice, then `JP 0204`.
+const DRAW_TWICE: [u8; 6] = [0xD0, 0x01, 0xD0, 0x01, 0x12, 0x04];
+
+#[test]
+fn register_condition_stops_on_the_matching_iteration() {
+    for backend in common::BACKENDS {
+        let mut chip8 = common::machine_on(backend, &COUNT_UP);
+        chip8.set_breakpoint(0x200, Condition::RegEquals(0, 5));
+
+        assert!(chip8.run_until_break(1000).unwrap());
+        assert_eq!(chip8.pc, 0x200, "{:?}", backend);
+        assert_eq!(chip8.registers[0], 5, "{:?}", backend);
+
+        // Passed over when resuming, the condition holds again 256 additions later.
+        assert!(chip8.run_until_break(1000).unwrap());
+        assert_eq!(chip8.registers[0], 5, "{:?}", backend);
+        assert_eq!(chip8.cycle_count(), 2 * (5 + 256), "{:?}", backend);
+    }
+}
+
+#[test]
+fn temporary_breakpoint_fires_once() {
+    let mut chip8 = common::machine(&COUNT_UP);
+    chip8.set_temporary_breakpoint(0x202);
+
+    assert!(chip8.run_until_break(100).unwrap());
+    assert_eq!(chip8.pc, 0x202);
+    assert_eq!(chip8.registers[0], 1);
+
+    assert!(!chip8.run_until_break(100).unwrap());
+    assert!(!chip8.at_breakpoint());
+}
+
+#[test]
+fn peek_leaves_the_pc_alone() {
+    let chip8 = common::machine(&COUNT_UP);
+
+    assert_eq!(chip8.peek_next_opcode(), (0x7001, OpCode::ADDByte(0, 1)));
+    assert_eq!(chip8.pc, 0x200);
+}
+
+#[test]
+fn step_lands_after_the_counted_instructions() {
+    let mut chip8 = common::machine(&COUNT_UP);
+
+    assert_eq!(chip8.step(3), Ok(3));
+    assert_eq!(chip8.pc, 0x202);
+    assert_eq!(chip8.registers[0], 2);
+
+    // `JP 0200` is a halt, stepping stops on it.
+    let mut chip8 = common::machine(&[0x12, 0x00]);
+    assert_eq!(chip8.step(3), Ok(0));
+    assert_eq!(chip8.pc, 0x200);
+
+    // The second `DRW` waits for the next frame, so it isn't counted.
+    let mut chip8 = common::machine(&DRAW_TWICE);
+    chip8.max_draws_per_frame = Some(1);
+    assert_eq!(chip8.step(3), Ok(1));
+    assert_eq!(chip8.pc, 0x202);
+
+    chip8.tick_timers();
+    assert_eq!(chip8.step(3), Ok(1));
+    assert_eq!(chip8.pc, 0x204);
+}
+
+#[test]
+fn run_until_halt_reports_why_it_stopped() {
+    // `ADD V0, 01` three times, then `JP 0206`.
+    let mut chip8 = common::machine(&[0x70, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x06]);
+    // Breakpoints are passed over.
+    chip8.set_breakpoint(0x202, Condition::Always);
+    assert_eq!(chip8.run_until_halt(100), RunOutcome::Halted);
+    assert_eq!((chip8.pc, chip8.registers[0]), (0x206, 3));
+
+    let mut chip8 = common::machine(&COUNT_UP);
+    assert_eq!(chip8.run_until_halt(100), RunOutcome::CycleLimit);
+    assert_eq!(chip8.cycle_count(), 100);
+
+    // `LD V0, K` with no key pressed.
+    let mut chip8 = common::machine(&[0xF0, 0x0A]);
+    assert_eq!(chip8.run_until_halt(100), RunOutcome::Idle);
+}
+
+#[test]
+fn run_until_halt_ticks_the_timers() {
+    let mut chip8 = common::machine(&DRAW_TWICE);
+    let mut quirks = chip8.quirks();
+    quirks.display_wait = true;
+    chip8.set_quirks(quirks);
+    assert_eq!(chip8.run_until_halt(100), RunOutcome::Halted);
+    assert_eq!(chip8.pc, 0x204);
+
+    // `LD V0, 05`, `LD DT, V0`, then `LD V0, DT` and `SE V0, 00` until it runs out,
+    // `JP 020A` once it has.
+    let mut chip8 = common::machine(&[
+        0x60, 0x05, 0xF0, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x04, 0x12, 0x0A,
+    ]);
+    assert_eq!(chip8.run_until_halt(1000), RunOutcome::Halted);
+    assert_eq!(chip8.pc, 0x20A);
+}
+
+#[test]
+fn held_back_draw_runs_nothing() {
+    let mut chip8 = common::machine(&DRAW_TWICE);
+    chip8.max_draws_per_frame = Some(1);
+
+    assert_eq!(chip8.run_cycles(10), Ok(1));
+    assert!(chip8.is_waiting_for_frame());
+    assert_eq!((chip8.pc, chip8.cycle_count()), (0x202, 1));
+
+    // Waiting isn't mistaken for a breakpoint.
+    assert!(!chip8.run_until_break(10).unwrap());
+    assert_eq!(chip8.cycle_count(), 1);
+
+    chip8.tick_timers();
+    assert_eq!(chip8.run_cycles(10), Ok(10));
+    assert_eq!(chip8.pc, 0x204);
+}
@@ -0,0 +1,60 @@
+//! `Chip8::run_for` driven by fake clocks.
+
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chippy_core::clock::Clock;
+
+/// `ADD V0, 01` then `JP 0200` back to it.
+const COUNT: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+/// Clock which only moves when slept on, or never when `frozen`.
+#[derive(Clone, Default)]
+struct FakeClock {
+    now: Arc<Mutex<Duration>>,
+    frozen: bool,
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        if !self.frozen {
+            *self.now.lock().unwrap() += duration;
+        }
+    }
+}
+
+#[test]
+fn run_for_sleeps_through_the_duration() {
+    let clock = FakeClock::default();
+    let mut chip8 = common::machine(&COUNT);
+    chip8.set_clock(Box::new(clock.clone()));
+    chip8.delay_timer = 10;
+
+    assert_eq!(chip8.run_for(Duration::from_millis(100), 600), Ok(60));
+    assert_eq!(clock.now(), Duration::from_millis(100));
+    assert_eq!(chip8.delay_timer, 4);
+}
+
+#[test]
+fn run_for_returns_on_a_frozen_clock() {
+    let clock = FakeClock {
+        frozen: true,
+        ..FakeClock::default()
+    };
+
+    let mut chip8 = common::machine(&COUNT);
+    chip8.set_clock(Box::new(clock));
+    chip8.delay_timer = 10;
+
+    assert_eq!(chip8.run_for(Duration::from_millis(100), 600), Ok(60));
+    assert_eq!(chip8.registers[0], 30);
+    assert_eq!(chip8.delay_timer, 4);
+}
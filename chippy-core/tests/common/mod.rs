@@ -0,0 +1,28 @@
+//! Helpers shared by the integration tests, each of which uses only some of them.
+#![allow(dead_code)]
+
+use chippy_core::{Backend, Chip8, ExecutingChip8};
+
+pub const BACKENDS: [Backend; 2] = [Backend::Interpreter, Backend::Jit];
+
+/// Machine with `rom` loaded, ready to run it from the program start.
+pub fn machine(rom: &[u8]) -> Chip8 {
+    machine_on(Backend::Interpreter, rom)
+}
+
+/// Machine with `rom` loaded, executing it through `backend`.
+pub fn machine_on(backend: Backend, rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_backend(backend);
+    chip8.load_rom(rom.to_vec());
+    chip8
+}
+
+/// Shared machine with `rom` loaded and running, executed only by calling `run_frame`.
+pub fn executing(rom: &[u8]) -> ExecutingChip8 {
+    let chip8 = ExecutingChip8::new();
+    chip8.set_vsync(true);
+    chip8.load_rom(rom.to_vec());
+    chip8.set_running(true);
+    chip8
+}
@@ -0,0 +1,60 @@
+use chippy_core::{
+    disassembler::{self, CoverageLine},
+    Chip8,
+};
+
+/// Calls a subroutine, then halts. The sprite after the halt is never reached.
+const ROM: [u8; 12] = [
+    0x22, 0x06, // 200: CALL 206
+    0x12, 0x02, // 202: JP 202
+    0xF0, 0x90, // 204: sprite data
+    0x60, 0x01, // 206: LD V0, 01
+    0x00, 0xEE, // 208: RET
+    0xF0, 0x90, // 20A: sprite data
+];
+
+fn run() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_coverage(true);
+    chip8.load_rom(ROM.to_vec());
+    chip8.run_cycles(10).unwrap();
+    chip8
+}
+
+fn lines() -> Vec<CoverageLine> {
+    disassembler::coverage_lines(&run())
+}
+
+#[test]
+fn executed_lines_are_code() {
+    let executed: Vec<u16> = lines()
+        .iter()
+        .filter(|line| line.executed)
+        .map(|line| line.line.addr)
+        .collect();
+
+    assert_eq!(executed, [0x200, 0x202, 0x206, 0x208]);
+}
+
+#[test]
+fn unreached_data_is_data() {
+    let lines = lines();
+
+    assert!(!lines[2].executed);
+    assert!(!lines[5].executed);
+}
+
+#[test]
+fn targets_are_labeled() {
+    let targets: Vec<u16> = lines()
+        .iter()
+        .filter(|line| line.is_target)
+        .map(|line| line.line.addr)
+        .collect();
+    assert_eq!(targets, [0x202, 0x206]);
+
+    let listing = disassembler::coverage_listing(&run());
+    assert!(listing.contains("\nL_0206:\n0206  6001"));
+    assert!(listing.contains("; data  - data (F090)"));
+    assert!(listing.contains("(-> L_0206)"));
+}
@@ -0,0 +1,27 @@
+//! Instructions costing more cycles when `Chip8::cycle_costs` is enabled.
+
+mod common;
+
+use chippy_core::opcode::{cycle_cost, OpCode};
+
+/// `DRW V0, V0, 5` then `JP 0200`, costing 18 and 1 cycles.
+const DRAW_LOOP: [u8; 4] = [0xD0, 0x05, 0x12, 0x00];
+
+#[test]
+fn drawing_costs_more_than_arithmetic() {
+    assert!(cycle_cost(&OpCode::DRW(0, 0, 5)) > cycle_cost(&OpCode::ADD(0, 1)));
+    assert!(cycle_cost(&OpCode::DRW(0, 0, 1)) < cycle_cost(&OpCode::DRW(0, 0, 15)));
+}
+
+#[test]
+fn costs_slow_execution_down() {
+    let mut chip8 = common::machine(&DRAW_LOOP);
+    chip8.run_cycles(100).unwrap();
+    assert_eq!(chip8.cycle_count(), 100);
+
+    let mut chip8 = common::machine(&DRAW_LOOP);
+    chip8.cycle_costs = true;
+    // Five times through the loop, then the `DRW` overshooting the budget.
+    assert_eq!(chip8.run_cycles(100), Ok(5 * 19 + 18));
+    assert_eq!(chip8.cycle_count(), 11);
+}
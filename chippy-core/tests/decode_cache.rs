@@ -0,0 +1,38 @@
+//! Instructions decoded before being overwritten are decoded again.
+
+mod common;
+
+use common::BACKENDS;
+
+/// `ADD V2, 01`, `LD I, 0200`, `LD [I], V1` then `JP 0200`,
+/// overwriting the first instruction with V0 and V1 after it ran.
+const SELF_MODIFYING: [u8; 8] = [0x72, 0x01, 0xA2, 0x00, 0xF1, 0x55, 0x12, 0x00];
+
+#[test]
+fn program_overwriting_itself() {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &SELF_MODIFYING);
+        // `ADD V3, 01`
+        chip8.registers[0] = 0x73;
+        chip8.registers[1] = 0x01;
+
+        // Twice through the loop, so whole blocks are run by the JIT.
+        assert_eq!(chip8.run_cycles(8), Ok(8));
+        assert_eq!(chip8.registers[2], 1, "{:?}", backend);
+        assert_eq!(chip8.registers[3], 1, "{:?}", backend);
+    }
+}
+
+#[test]
+fn patching_memory() {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &SELF_MODIFYING);
+        chip8.run_cycles(4).unwrap();
+
+        chip8.memory_mut(0x200, 2).copy_from_slice(&[0x74, 0x01]);
+        chip8.run_cycles(4).unwrap();
+
+        assert_eq!(chip8.registers[2], 1, "{:?}", backend);
+        assert_eq!(chip8.registers[4], 1, "{:?}", backend);
+    }
+}
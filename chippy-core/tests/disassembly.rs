@@ -0,0 +1,32 @@
+//! `Disassembly` decoding lazily what `disassemble` collects.
+
+use chippy_core::disassembler::{disassemble, DisasmLine, Disassembly};
+
+/// `CLS`, `LD V0, 01`, `JP 0200`, then a stray byte.
+const MEMORY: [u8; 7] = [0x00, 0xE0, 0x60, 0x01, 0x12, 0x00, 0xAB];
+
+#[test]
+fn iterator_matches_collected_listing() {
+    // Odd starts decode the words straddling instructions, odd ends the last byte on its own.
+    for (start, end) in [(0, 6), (1, 6), (1, 7), (2, 7), (3, 3), (6, 2)] {
+        let lazy = Disassembly::new(&MEMORY, start, end);
+        let len = lazy.len();
+        let lines: Vec<DisasmLine> = lazy.collect();
+
+        assert_eq!(lines, disassemble(&MEMORY, start, end));
+        assert_eq!(lines.len(), len, "{}..{}", start, end);
+
+        let addrs: Vec<u16> = lines.iter().map(|line| line.addr).collect();
+        let expected: Vec<u16> = (start..end as u16).step_by(2).collect();
+        assert_eq!(addrs, expected);
+
+        for line in lines {
+            assert_eq!(line, DisasmLine::decode(&MEMORY, line.addr));
+        }
+    }
+
+    let mnemonics: Vec<String> = Disassembly::new(&MEMORY, 1, 7)
+        .map(|line| line.mnemonic)
+        .collect();
+    assert_eq!(mnemonics, ["DATA E060", "SYS 0112", "SYS 00AB"]);
+}
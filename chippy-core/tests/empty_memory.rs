@@ -0,0 +1,41 @@
+mod common;
+
+use chippy_core::{breakpoint::RunOutcome, Chip8, Chip8Error};
+
+/// `LD V0, 01` then nothing, so execution continues into zeroed memory.
+const ROM: [u8; 2] = [0x60, 0x01];
+
+fn machine(trap: bool) -> Chip8 {
+    let mut chip8 = common::machine(&ROM);
+    chip8.trap_empty_memory = trap;
+    chip8
+}
+
+#[test]
+fn empty_memory_is_skipped_by_default() {
+    let mut chip8 = machine(false);
+
+    assert_eq!(chip8.run_until_halt(100), RunOutcome::CycleLimit);
+    assert_eq!(chip8.pc, 0x200 + 2 * 100);
+}
+
+#[test]
+fn empty_memory_fails_when_trapped() {
+    let mut chip8 = machine(true);
+
+    assert_eq!(
+        chip8.run_until_halt(100),
+        RunOutcome::Error(Chip8Error::EmptyMemory(0x202))
+    );
+    assert_eq!(chip8.pc, 0x202);
+}
+
+#[test]
+fn single_empty_word_is_skipped() {
+    // `0000` then `JP 0200`.
+    let mut chip8 = common::machine(&[0x00, 0x00, 0x12, 0x00]);
+    chip8.trap_empty_memory = true;
+
+    assert_eq!(chip8.run_cycles(2), Ok(2));
+    assert_eq!(chip8.pc, 0x200);
+}
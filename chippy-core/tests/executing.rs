@@ -0,0 +1,114 @@
+//! `ExecutingChip8` driven one frame at a time through `run_frame`.
+
+mod common;
+
+use std::time::Duration;
+
+use chippy_core::{breakpoint::RunOutcome, ResetKind};
+
+/// `ADD V0, 01` then `JP 0200`, counting up in V0 forever.
+const COUNT_UP: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+#[test]
+fn halting_is_sent_once() {
+    let chip8 = common::executing(&[0x12, 0x00]);
+
+    chip8.run_frame();
+    assert_eq!(chip8.drain_events(), [RunOutcome::Halted]);
+
+    chip8.run_frame();
+    assert_eq!(chip8.drain_events(), []);
+}
+
+#[test]
+fn wrappers_change_the_shared_machine() {
+    let chip8 = common::executing(&COUNT_UP);
+    assert_eq!(chip8.read().unwrap().rom(), COUNT_UP);
+
+    chip8.set_key(0x5, true);
+    assert!(chip8.read().unwrap().keypad.keys[0x5]);
+
+    chip8.run_frame();
+    let snapshot = chip8.snapshot();
+    assert_ne!(chip8.read().unwrap().registers[0], 0);
+
+    chip8.reset(ResetKind::Cold);
+    assert_eq!(chip8.read().unwrap().registers[0], 0);
+    assert_eq!(chip8.read().unwrap().pc, 0x200);
+
+    chip8.restore(&snapshot).unwrap();
+    assert_eq!(chip8.snapshot(), snapshot);
+
+    chip8.load_rom(vec![0x12, 0x00]);
+    assert_eq!(
+        chip8.read().unwrap().memory[0x200..0x204],
+        [0x12, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn frame_runs_the_instructions_per_frame() {
+    let chip8 = common::executing(&COUNT_UP);
+    chip8.set_ipf(25);
+
+    chip8.run_frame();
+    assert_eq!(chip8.read().unwrap().cycle_count(), 25);
+
+    chip8.run_frame();
+    assert_eq!(chip8.read().unwrap().cycle_count(), 50);
+}
+
+#[test]
+fn frame_is_capped_at_max_cycles() {
+    let chip8 = common::executing(&COUNT_UP);
+    chip8.set_ipf(100);
+    chip8.set_max_cycles(30);
+
+    chip8.run_frame();
+    assert_eq!(chip8.read().unwrap().cycle_count(), 30);
+    assert!(chip8.is_capped());
+
+    chip8.set_max_cycles(100);
+    chip8.run_frame();
+    assert_eq!(chip8.read().unwrap().cycle_count(), 130);
+    assert!(!chip8.is_capped());
+}
+
+#[test]
+fn draws_are_held_to_the_limit_per_frame() {
+    // `DRW V0, V0, 1` then `JP 0200`.
+    let chip8 = common::executing(&[0xD0, 0x01, 0x12, 0x00]);
+    chip8.write().unwrap().max_draws_per_frame = Some(1);
+    chip8.set_ipf(10);
+
+    // The second `DRW` waits for the next frame.
+    for frame in 1..=3 {
+        chip8.run_frame();
+        assert_eq!(chip8.read().unwrap().cycle_count(), frame * 2);
+    }
+
+    chip8.write().unwrap().max_draws_per_frame = None;
+    chip8.run_frame();
+    assert_eq!(chip8.read().unwrap().cycle_count(), 6 + 10);
+}
+
+#[test]
+fn run_for_pauses_once_over() {
+    let chip8 = common::executing(&COUNT_UP);
+    chip8.set_ipf(10);
+    chip8.set_running(false);
+
+    chip8.run_for(Duration::ZERO);
+    assert!(chip8.is_running());
+
+    chip8.run_frame();
+    assert!(!chip8.is_running());
+    assert_eq!(chip8.read().unwrap().cycle_count(), 10);
+
+    // Running again cancels the pause.
+    chip8.run_for(Duration::ZERO);
+    chip8.set_running(true);
+    chip8.run_frame();
+    chip8.run_frame();
+    assert!(chip8.is_running());
+}
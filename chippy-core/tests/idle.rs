@@ -0,0 +1,40 @@
+use chippy_core::{breakpoint::RunOutcome, Chip8};
+
+/// Title screen waiting for key 5: `LD V0, 05`, `SKP V0`, `JP 0202`, then `JP 0206`.
+const ROM: [u8; 8] = [0x60, 0x05, 0xE0, 0x9E, 0x12, 0x02, 0x12, 0x06];
+
+#[test]
+fn key_poll_loop_is_idle() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(ROM.to_vec());
+
+    assert_eq!(chip8.run_until_halt(1000), RunOutcome::Idle);
+    assert!(chip8.is_polling_key());
+
+    // Either instruction of the loop counts.
+    chip8.run_cycles(1).unwrap();
+    assert!(chip8.is_idle());
+}
+
+#[test]
+fn key_press_wakes_poll_loop() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(ROM.to_vec());
+    chip8.run_until_halt(1000);
+
+    chip8.keypad.set(5, true);
+    assert!(!chip8.is_idle());
+
+    assert_eq!(chip8.run_until_halt(1000), RunOutcome::Halted);
+    assert_eq!(chip8.pc, 0x206);
+}
+
+#[test]
+fn other_keys_keep_polling() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(ROM.to_vec());
+    chip8.run_until_halt(1000);
+
+    chip8.keypad.set(3, true);
+    assert!(chip8.is_idle());
+}
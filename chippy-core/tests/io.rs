@@ -0,0 +1,63 @@
+//! Instructions reading and writing memory through a mapped `IoHandler`.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chippy_core::{
+    io::IoHandler,
+    types::{C8Addr, C8Byte},
+};
+
+/// `LD I, 02FF`, `LD [I], V1` storing V0 and V1 across the start of the mapped range,
+/// then `LD V1, [I]` loading them back.
+const ROM: [u8; 6] = [0xA2, 0xFF, 0xF1, 0x55, 0xF1, 0x65];
+
+/// Address the handler is mapped to.
+const PORT: C8Addr = 0x300;
+
+/// Reads a fixed value and remembers what was written.
+struct Port {
+    written: Arc<Mutex<Vec<(C8Addr, C8Byte)>>>,
+}
+
+impl IoHandler for Port {
+    fn read(&mut self, _addr: C8Addr) -> C8Byte {
+        0x42
+    }
+
+    fn write(&mut self, addr: C8Addr, value: C8Byte) {
+        self.written.lock().unwrap().push((addr, value));
+    }
+}
+
+#[test]
+fn mapped_addresses_go_through_the_handler() {
+    let written = Arc::new(Mutex::new(Vec::new()));
+    let mut chip8 = common::machine(&ROM);
+    chip8.set_io_handler(Some((
+        PORT..=PORT,
+        Box::new(Port {
+            written: written.clone(),
+        }),
+    )));
+    chip8.set_write_log(16);
+    chip8.registers[0] = 0x11;
+    chip8.registers[1] = 0x22;
+
+    chip8.run_cycles(2).unwrap();
+    assert_eq!(*written.lock().unwrap(), [(PORT, 0x22)]);
+    assert_eq!(chip8.memory_range(0x2FF, 2), [0x11, 0x00]);
+
+    // Only the write to backing memory is logged, the handler's has no old value.
+    let logged: Vec<_> = chip8
+        .recent_writes()
+        .iter()
+        .map(|write| write.addr)
+        .collect();
+    assert_eq!(logged, [0x2FF]);
+
+    chip8.run_cycles(1).unwrap();
+    assert_eq!(chip8.registers[..2], [0x11, 0x42]);
+    assert_eq!(chip8.read_memory(PORT as usize), 0x42);
+}
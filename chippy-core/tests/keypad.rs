@@ -0,0 +1,30 @@
+//! Which keys the keypad reports as held.
+
+mod common;
+
+use chippy_core::Keypad;
+
+#[test]
+fn any_pressed_is_the_lowest_key() {
+    let mut keypad = Keypad::default();
+    assert_eq!(keypad.any_pressed(), None);
+
+    keypad.set(0xC, true);
+    assert_eq!(keypad.any_pressed(), Some(0xC));
+
+    keypad.set(0x3, true);
+    assert_eq!(keypad.any_pressed(), Some(0x3));
+
+    keypad.set(0x3, false);
+    keypad.set(0xC, false);
+    assert_eq!(keypad.any_pressed(), None);
+}
+
+#[test]
+fn loading_a_rom_releases_keys() {
+    let mut chip8 = common::machine(&[0x12, 0x00]);
+    chip8.keypad.set(0x5, true);
+
+    chip8.load_rom(vec![0x12, 0x00]);
+    assert_eq!(chip8.keypad.any_pressed(), None);
+}
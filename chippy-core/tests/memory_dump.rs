@@ -0,0 +1,44 @@
+//! Dumping memory regions and loading the dumps back.
+
+mod common;
+
+use chippy_core::{MemoryRegion, PROGRAM_ADDR};
+
+/// `LD V0, 2A`, `LD I, 0300`, `LD [I], V0` then `JP 0206` spinning.
+const ROM: [u8; 8] = [0x60, 0x2A, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06];
+
+#[test]
+fn dumps_load_back_into_a_fresh_machine() {
+    let mut chip8 = common::machine(&ROM);
+    chip8.run_cycles(4).unwrap();
+
+    for region in [MemoryRegion::Full, MemoryRegion::Program] {
+        let dump = chip8.dump_memory(region);
+
+        let mut restored = common::machine(&[]);
+        restored.load_memory_dump(region, &dump);
+
+        assert_eq!(restored.memory, chip8.memory, "{:?}", region);
+        assert_eq!(restored.dump_memory(region), dump, "{:?}", region);
+    }
+}
+
+#[test]
+fn program_dump_starts_at_the_program() {
+    let chip8 = common::machine(&ROM);
+    let dump = chip8.dump_memory(MemoryRegion::Program);
+
+    assert_eq!(dump.len(), chip8.memory.len() - PROGRAM_ADDR);
+    assert_eq!(dump[..ROM.len()], ROM);
+}
+
+#[test]
+fn oversized_dump_is_truncated() {
+    let mut chip8 = common::machine(&[]);
+    let dump = vec![0xAB; chip8.memory.len() + 16];
+    chip8.load_memory_dump(MemoryRegion::Program, &dump);
+
+    assert!(chip8.memory[PROGRAM_ADDR..]
+        .iter()
+        .all(|byte| *byte == 0xAB));
+}
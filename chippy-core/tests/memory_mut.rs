@@ -0,0 +1,43 @@
+//! Patching code through `Chip8::memory_mut` while it runs.
+
+mod common;
+
+use common::BACKENDS;
+
+/// `LD V0, 01`, then `JP 0200` back to it.
+const LOOP: [u8; 4] = [0x60, 0x01, 0x12, 0x00];
+
+/// `LD V0, 01`, `LD I, 0200` and `LD [I], V0` storing V0 over the first instruction's opcode.
+/// The stored `01` makes it a `SYS`, leaving V0 alone the second time around.
+const SELF_MODIFYING: [u8; 8] = [0x60, 0x01, 0xA2, 0x00, 0xF0, 0x55, 0x12, 0x00];
+
+#[test]
+fn patched_instruction_runs() {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &LOOP);
+
+        // Decode or compile the loop first.
+        chip8.run_cycles(2).unwrap();
+        assert_eq!(chip8.registers[0], 0x01);
+
+        // `LD V0, 05`, patching only the byte holding the value.
+        chip8.memory_mut(0x201, 1)[0] = 0x05;
+        chip8.run_cycles(1).unwrap();
+
+        assert_eq!(chip8.registers[0], 0x05, "{:?}", backend);
+    }
+}
+
+#[test]
+fn lds_overwrite_runs() {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &SELF_MODIFYING);
+
+        chip8.run_cycles(4).unwrap();
+        chip8.registers[0] = 0x07;
+        chip8.run_cycles(1).unwrap();
+
+        assert_eq!(chip8.memory_range(0x200, 2), [0x01, 0x01]);
+        assert_eq!(chip8.registers[0], 0x07, "{:?}", backend);
+    }
+}
@@ -0,0 +1,77 @@
+//! I and the PC wrapping at the end of memory, for each `MemorySize`.
+
+mod common;
+
+use chippy_core::{breakpoint::Condition, Chip8, MemorySize};
+use common::BACKENDS;
+
+/// `ADD I, V0` then `ADD I, V1`.
+const ADD_INDEX: [u8; 4] = [0xF0, 0x1E, 0xF1, 0x1E];
+
+/// Machine of `size` with the words of `code` placed at `addr` and the PC on them.
+fn machine_at(size: MemorySize, addr: usize, code: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.set_memory_size(size);
+    chip8.memory_mut(addr, code.len()).copy_from_slice(code);
+    chip8.pc = addr as u16;
+    chip8
+}
+
+#[test]
+fn index_reaches_the_end_of_memory() {
+    for (size, last) in [(MemorySize::Classic, 0xFFF), (MemorySize::Extended, 0xFFFF)] {
+        for backend in BACKENDS {
+            let mut chip8 = common::machine_on(backend, &ADD_INDEX);
+            chip8.set_memory_size(size);
+            chip8.index = last - 0x0F;
+            chip8.registers[0] = 0x0F;
+            chip8.registers[1] = 0x01;
+
+            chip8.run_cycles(1).unwrap();
+            assert_eq!(chip8.index, last, "{:?} on {:?}", size, backend);
+
+            chip8.run_cycles(1).unwrap();
+            assert_eq!(chip8.index, 0, "{:?} on {:?}", size, backend);
+        }
+    }
+}
+
+#[test]
+fn pc_wraps_at_the_end_of_memory() {
+    // `ADD V0, 01` in the last word.
+    let mut chip8 = machine_at(MemorySize::Extended, 0xFFFE, &[0x70, 0x01]);
+    chip8.interpreter().unwrap();
+
+    assert_eq!(chip8.registers[0], 1);
+    assert_eq!(chip8.pc, 0);
+}
+
+#[test]
+fn skip_wraps_at_the_end_of_memory() {
+    // `SE V0, 00` skipping the last word.
+    let mut chip8 = machine_at(MemorySize::Extended, 0xFFFC, &[0x30, 0x00]);
+    chip8.interpreter().unwrap();
+    assert_eq!(chip8.pc, 0);
+
+    let mut chip8 = machine_at(MemorySize::Classic, 0xFFC, &[0x30, 0x00]);
+    chip8.interpreter().unwrap();
+    assert_eq!(chip8.pc, 0);
+}
+
+#[test]
+fn blocks_run_up_to_the_end_of_memory() {
+    // `ADD V0, 01` filling the last 16 bytes, then `JP 0000` at the start of memory.
+    let code = [0x70, 0x01].repeat(8);
+
+    for backend in BACKENDS {
+        let mut chip8 = machine_at(MemorySize::Extended, 0xFFF0, &code);
+        chip8.memory_mut(0, 2).copy_from_slice(&[0x10, 0x00]);
+        chip8.set_backend(backend);
+        // Outside of the block, but the block is checked for breakpoints.
+        chip8.set_breakpoint(0x200, Condition::Always);
+
+        assert_eq!(chip8.run_cycles(8), Ok(8));
+        assert_eq!(chip8.registers[0], 8, "{:?}", backend);
+        assert_eq!(chip8.pc, 0, "{:?}", backend);
+    }
+}
@@ -0,0 +1,29 @@
+//! Jumps to odd addresses counted by `Chip8::misaligned_jumps`.
+
+mod common;
+
+/// `JP 0203`, landing between the words of `LD V0, 01` and `JP 0200`.
+const ROM: [u8; 6] = [0x12, 0x03, 0x60, 0x01, 0x12, 0x00];
+
+#[test]
+fn odd_jump_is_counted_when_warning() {
+    let mut chip8 = common::machine(&ROM);
+    chip8.warn_misaligned_pc = true;
+
+    chip8.step(1).unwrap();
+    assert_eq!(chip8.pc, 0x203);
+    assert_eq!(chip8.misaligned_jumps(), 1);
+
+    // Instructions running from odd addresses aren't counted again.
+    chip8.step(1).unwrap();
+    assert_eq!(chip8.misaligned_jumps(), 1);
+}
+
+#[test]
+fn odd_jump_is_followed_silently_by_default() {
+    let mut chip8 = common::machine(&ROM);
+
+    chip8.step(1).unwrap();
+    assert_eq!(chip8.pc, 0x203);
+    assert_eq!(chip8.misaligned_jumps(), 0);
+}
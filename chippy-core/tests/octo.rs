@@ -0,0 +1,37 @@
+//! Octo and native renderings of the same opcodes.
+
+use chippy_core::opcode::OpCode;
+
+fn check(word: u16, native: &str, octo: &str) {
+    let opcode = OpCode::from_opcode(word);
+
+    assert_eq!(opcode.get_opcode_str().0, native, "{:04X}", word);
+    assert_eq!(opcode.to_octo(), octo, "{:04X}", word);
+}
+
+#[test]
+fn load_byte() {
+    check(0x610A, "LD V1, 0A", "v1 := 0x0a");
+}
+
+#[test]
+fn skips_invert_the_condition() {
+    check(0x3A05, "SE VA, 05", "if va != 0x05 then");
+    check(0x9120, "SNE V1, V2", "if v1 == v2 then");
+    check(0xE39E, "SKP V3", "if v3 -key then");
+}
+
+#[test]
+fn memory_and_timers() {
+    check(0xA2F0, "LD I, 02F0", "i := 0x2f0");
+    check(0xD125, "DRW V1, V2, 05", "sprite v1 v2 5");
+    check(0xF418, "LD ST, V4", "buzzer := v4");
+    check(0xF565, "LD V5, [I]", "load v5");
+}
+
+#[test]
+fn control_flow() {
+    check(0x00E0, "CLS", "clear");
+    check(0x1208, "JP 0208", "jump 0x208");
+    check(0x2300, "CALL 0300", ":call 0x300");
+}
@@ -0,0 +1,186 @@
+//! Quirks changing instruction behavior, on every backend executing the instruction.
+
+mod common;
+
+use chippy_core::{Chip8, QuirkProfile, Quirks};
+use common::BACKENDS;
+
+/// `LD V1, 01`, `LD V2, 08`, `SHL V1, V2` and `JP 0200` back to the start.
+const SHIFT_LOOP: [u8; 8] = [0x61, 0x01, 0x62, 0x08, 0x81, 0x2E, 0x12, 0x00];
+
+/// `LD I, 0300`, then `LD [I], V2` and `LD V1, [I]`.
+const LOAD_STORE: [u8; 6] = [0xA3, 0x00, 0xF2, 0x55, 0xF1, 0x65];
+
+/// `JP V0, 0310`.
+const JUMP: [u8; 2] = [0xB3, 0x10];
+
+/// `DRW V0, V0, 1` twice.
+const DRAW_TWICE: [u8; 4] = [0xD0, 0x01, 0xD0, 0x01];
+
+/// `ADD I, V0`.
+const ADD_INDEX: [u8; 2] = [0xF0, 0x1E];
+
+fn machine(quirks: Quirks, rom: &[u8]) -> Chip8 {
+    let mut chip8 = common::machine(rom);
+    chip8.set_quirks(quirks);
+    chip8
+}
+
+#[test]
+fn toggling_shift_uses_vy_changes_a_running_rom() {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &SHIFT_LOOP);
+
+        chip8.run_cycles(4).unwrap();
+        assert_eq!(chip8.registers[1], 0x02, "{:?}", backend);
+
+        chip8.set_quirks(Quirks {
+            shift_uses_vy: true,
+            ..chip8.quirks()
+        });
+
+        chip8.run_cycles(4).unwrap();
+        assert_eq!(chip8.registers[1], 0x10, "{:?}", backend);
+    }
+}
+
+#[test]
+fn load_store_moves_index_with_quirk() {
+    let mut chip8 = common::machine(&LOAD_STORE);
+    chip8.run_cycles(3).unwrap();
+    assert_eq!(chip8.index, 0x300);
+
+    let quirks = Quirks {
+        load_store_increments_i: true,
+        ..Quirks::default()
+    };
+
+    let mut chip8 = machine(quirks, &LOAD_STORE);
+    chip8.run_cycles(2).unwrap();
+    assert_eq!(chip8.index, 0x303);
+    chip8.run_cycles(1).unwrap();
+    assert_eq!(chip8.index, 0x305);
+}
+
+#[test]
+fn jump_adds_vx_with_quirk() {
+    let mut chip8 = common::machine(&JUMP);
+    chip8.registers[0] = 0x02;
+    chip8.registers[3] = 0x04;
+    chip8.interpreter().unwrap();
+    let plain = chip8.pc;
+
+    let quirks = Quirks {
+        jump_with_vx: true,
+        ..Quirks::default()
+    };
+
+    let mut chip8 = machine(quirks, &JUMP);
+    chip8.registers[0] = 0x02;
+    chip8.registers[3] = 0x04;
+    chip8.interpreter().unwrap();
+
+    assert_eq!(chip8.pc - plain, 0x02);
+}
+
+#[test]
+fn display_wait_draws_once_per_frame() {
+    let quirks = Quirks {
+        display_wait: true,
+        ..Quirks::default()
+    };
+
+    let mut chip8 = machine(quirks, &DRAW_TWICE);
+    chip8.run_cycles(10).unwrap();
+    assert_eq!(chip8.pc, 0x202);
+
+    chip8.tick_timers();
+    chip8.run_cycles(1).unwrap();
+    assert_eq!(chip8.pc, 0x204);
+}
+
+#[test]
+fn index_overflow_sets_vf_with_quirk() {
+    let quirks = Quirks {
+        index_overflow_vf: true,
+        ..Quirks::default()
+    };
+
+    for backend in BACKENDS {
+        for (index, vf) in [(0xFFE, 0), (0xFFF, 1)] {
+            let mut chip8 = common::machine_on(backend, &ADD_INDEX);
+            chip8.set_quirks(quirks);
+            chip8.index = index;
+            chip8.registers[0] = 0x01;
+            chip8.registers[0xF] = 0xAA;
+
+            chip8.run_cycles(1).unwrap();
+            assert_eq!(chip8.index, (index + 1) & 0xFFF, "{:?}", backend);
+            assert_eq!(chip8.registers[0xF], vf, "{:?}", backend);
+        }
+    }
+}
+
+#[test]
+fn profiles_have_their_documented_quirks() {
+    let vip = Quirks::from_profile(QuirkProfile::CosmacVip);
+    assert_eq!(
+        vip,
+        Quirks {
+            strict_sub_flag: false,
+            reset_vf_on_logic: true,
+            align_jumps: false,
+            wrap_x: false,
+            wrap_y: false,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_with_vx: false,
+            display_wait: true,
+            index_overflow_vf: false,
+        }
+    );
+
+    let super_chip = Quirks::from_profile(QuirkProfile::SuperChipModern);
+    assert_eq!(
+        super_chip,
+        Quirks {
+            strict_sub_flag: false,
+            reset_vf_on_logic: false,
+            align_jumps: false,
+            wrap_x: false,
+            wrap_y: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            jump_with_vx: true,
+            display_wait: false,
+            index_overflow_vf: false,
+        }
+    );
+
+    let xo_chip = Quirks::from_profile(QuirkProfile::XoChip);
+    assert_eq!(
+        xo_chip,
+        Quirks {
+            strict_sub_flag: false,
+            reset_vf_on_logic: false,
+            align_jumps: false,
+            wrap_x: true,
+            wrap_y: true,
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            jump_with_vx: false,
+            display_wait: false,
+            index_overflow_vf: false,
+        }
+    );
+}
+
+#[test]
+fn profiles_are_detected_from_their_quirks() {
+    for profile in QuirkProfile::PRESETS {
+        assert_eq!(QuirkProfile::detect(Quirks::from_profile(profile)), profile);
+    }
+
+    let quirks = Quirks::default();
+    assert_eq!(QuirkProfile::detect(quirks), QuirkProfile::Custom(quirks));
+}
@@ -0,0 +1,63 @@
+//! `RND` drawing from the generator the machine was given.
+
+use chippy_core::Chip8;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+/// `RND V0, FF` through `RND V3, FF`, then a `JP 0208` it stops in.
+const ROM: [u8; 10] = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0xC3, 0xFF, 0x12, 0x08];
+
+/// Generator which only ever produces zeroes.
+struct Zeroes;
+
+impl RngCore for Zeroes {
+    fn next_u32(&mut self) -> u32 {
+        0
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.fill(0);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        dest.fill(0);
+        Ok(())
+    }
+}
+
+/// Registers after running `ROM` on a machine.
+fn random_registers<R: RngCore>(mut chip8: Chip8<R>) -> [u8; 4] {
+    chip8.load_rom(ROM.to_vec());
+    chip8.step(4).unwrap();
+    chip8.registers[..4].try_into().unwrap()
+}
+
+#[test]
+fn stub_generator_is_used() {
+    assert_eq!(random_registers(Chip8::with_rng(Zeroes)), [0; 4]);
+}
+
+#[test]
+fn same_seed_gives_same_numbers() {
+    let seeded = || Chip8::with_rng(StdRng::seed_from_u64(7));
+    assert_eq!(random_registers(seeded()), random_registers(seeded()));
+}
+
+#[test]
+fn reseeding_repeats_the_numbers() {
+    let mut chip8 = Chip8::new();
+    chip8.reseed(42);
+    chip8.load_rom(ROM.to_vec());
+    chip8.step(4).unwrap();
+    let first = chip8.registers;
+
+    chip8.reseed(42);
+    chip8.load_rom(ROM.to_vec());
+    chip8.step(4).unwrap();
+
+    assert_eq!(chip8.registers, first);
+    assert_eq!(chip8.seed(), Some(42));
+}
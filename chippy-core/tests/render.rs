@@ -0,0 +1,112 @@
+//! Drawing the screen to images, and knowing when it needs drawing again.
+
+mod common;
+
+use chippy_core::{
+    render::{fit, DrawRect},
+    Chip8,
+};
+
+const FG: [u8; 4] = [255; 4];
+const BG: [u8; 4] = [0, 0, 0, 255];
+const LETTERBOX: [u8; 4] = [40, 40, 40, 255];
+
+#[test]
+fn fit_centers_the_scaled_screen() {
+    // Height limits the scale, leaving margins above and below.
+    let rect = fit((64, 32), (320, 200));
+    assert_eq!(
+        rect,
+        DrawRect {
+            x: 0,
+            y: 20,
+            width: 320,
+            height: 160,
+            scale: 5,
+        }
+    );
+
+    let (rgba, rect) = Chip8::new().render_fitted_rgba(FG, BG, LETTERBOX, (320, 200));
+    assert_eq!(rgba.len(), 320 * 200 * 4);
+
+    let pixel = |x: usize, y: usize| &rgba[(y * 320 + x) * 4..][..4];
+    assert_eq!(pixel(0, rect.y - 1), LETTERBOX);
+    assert_eq!(pixel(0, rect.y), BG);
+    assert_eq!(pixel(319, rect.y + rect.height - 1), BG);
+    assert_eq!(pixel(319, rect.y + rect.height), LETTERBOX);
+}
+
+#[test]
+fn lit_pixel_becomes_a_scaled_block() {
+    let mut chip8 = Chip8::new();
+    chip8.screen[1][2] = true;
+
+    let (rgba, width, height) = chip8.render_to_rgba(FG, BG, 3);
+    assert_eq!((width, height), (64 * 3, 32 * 3));
+
+    for (i, pixel) in rgba.chunks(4).enumerate() {
+        let (x, y) = (i % width as usize, i / width as usize);
+        let lit = (6..9).contains(&x) && (3..6).contains(&y);
+        assert_eq!(pixel, if lit { FG } else { BG }, "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn screen_is_dirty_after_drawing() {
+    // `ADD V0, 01`, `DRW V0, V0, 0`, `DRW V0, V0, 1` then `CLS`.
+    let mut chip8 = common::machine(&[0x70, 0x01, 0xD0, 0x00, 0xD0, 0x01, 0x00, 0xE0]);
+    assert!(chip8.take_screen_dirty());
+    assert!(!chip8.take_screen_dirty());
+
+    // Neither arithmetic nor an empty sprite change the screen.
+    chip8.step(2).unwrap();
+    assert!(!chip8.take_screen_dirty());
+
+    chip8.step(1).unwrap();
+    assert!(chip8.take_screen_dirty());
+
+    chip8.step(1).unwrap();
+    assert!(chip8.take_screen_dirty());
+    assert!(!chip8.take_screen_dirty());
+}
+
+#[test]
+fn rgba_matches_flattened_screen() {
+    let mut chip8 = Chip8::new();
+    for (y, row) in chip8.screen.iter_mut().enumerate() {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = (x * 7 + y * 3) % 5 == 0;
+        }
+    }
+
+    for scale in [1, 2, 3] {
+        let mut expected = Vec::new();
+        for row in &chip8.screen {
+            for _ in 0..scale {
+                for on in row {
+                    for _ in 0..scale {
+                        expected.extend_from_slice(if *on { &FG } else { &BG });
+                    }
+                }
+            }
+        }
+
+        let (rgba, _, _) = chip8.render_to_rgba(FG, BG, scale);
+        assert!(rgba == expected, "scale {}", scale);
+    }
+}
+
+#[test]
+fn images_are_sized_from_screen_dimensions() {
+    let chip8 = Chip8::new();
+    let (width, height) = chip8.screen_dimensions();
+    assert_eq!((width, height), (64, 32));
+
+    let (rgba, w, h) = chip8.render_to_rgba(FG, BG, 4);
+    assert_eq!((w as usize, h as usize), (width * 4, height * 4));
+    assert_eq!(rgba.len(), width * height * 16 * 4);
+
+    let ascii = chip8.render_to_ascii('#', '.');
+    assert_eq!(ascii.lines().count(), height);
+    assert!(ascii.lines().all(|line| line.len() == width));
+}
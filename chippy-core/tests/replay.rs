@@ -0,0 +1,12 @@
+use chippy_core::replay::{self, Replay};
+
+const ROM: &[u8] = include_bytes!("../../chippy-app/src/Instruction-test.ch8");
+
+#[test]
+fn instruction_test_replay() {
+    let replay: Replay = include_str!("../replays/instruction_test.replay")
+        .parse()
+        .unwrap();
+
+    replay::replay_and_verify(ROM, &replay, 0x8F21671912C12851);
+}
@@ -0,0 +1,82 @@
+//! What each `ResetKind` clears and keeps.
+
+use chippy_core::{Chip8, ResetKind};
+
+/// `LD V3, 42`, `LD I, 0300`, `LD [I], V3` with `V0` through `V3`, `CALL 020A`,
+/// then a `JP 020A` it stops in.
+const ROM: [u8; 12] = [
+    0x63, 0x2A, 0xA3, 0x00, 0xF3, 0x55, 0x22, 0x0A, 0x00, 0x00, 0x12, 0x0A,
+];
+
+/// Run `ROM` until it is spinning in the subroutine.
+fn ran() -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(ROM.to_vec());
+    for _ in 0..5 {
+        chip8.interpreter().unwrap();
+    }
+
+    chip8.delay_timer = 30;
+    chip8.screen[0][0] = true;
+    assert_eq!(chip8.sp, 1);
+    chip8
+}
+
+#[test]
+fn warm_reset_keeps_registers_and_memory() {
+    let mut chip8 = ran();
+    let index = chip8.index;
+    chip8.reset(ResetKind::Warm);
+
+    assert_eq!(chip8.registers[3], 42);
+    assert_eq!(chip8.index, index);
+    assert_eq!(chip8.memory[0x303], 42);
+
+    assert_eq!(chip8.pc, 0x200);
+    assert_eq!(chip8.sp, 0);
+    assert_eq!(chip8.delay_timer, 0);
+    assert!(!chip8.screen[0][0]);
+}
+
+#[test]
+fn cold_reset_zeroes_registers_and_restores_memory() {
+    let mut chip8 = ran();
+    chip8.reset(ResetKind::Cold);
+
+    assert_eq!(chip8.registers, [0; 16]);
+    assert_eq!(chip8.index, 0);
+    assert_eq!(chip8.memory[0x303], 0);
+    assert_eq!(chip8.memory[0x200..0x20C], ROM);
+
+    assert_eq!(chip8.pc, 0x200);
+    assert_eq!(chip8.sp, 0);
+    assert_eq!(chip8.delay_timer, 0);
+    assert!(!chip8.screen[0][0]);
+}
+
+#[test]
+fn load_rom_resets_cold() {
+    let mut chip8 = ran();
+    chip8.load_rom(ROM.to_vec());
+
+    assert_eq!(chip8.registers, [0; 16]);
+    assert_eq!(chip8.memory[0x303], 0);
+}
+
+#[test]
+fn cold_reset_restores_overwritten_program() {
+    // `LD I, 0200`, `LD [I], V0` over the first instruction, then a `JP 0204` it stops in.
+    let rom = [0xA2, 0x00, 0xF0, 0x55, 0x12, 0x04];
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(rom.to_vec());
+    chip8.run_until_halt(100);
+    assert_eq!(chip8.memory[0x200], 0);
+
+    chip8.reset(ResetKind::Cold);
+    assert_eq!(chip8.memory[0x200..0x206], rom);
+    assert_eq!(chip8.rom(), rom);
+
+    // The restored program runs the same way again.
+    chip8.run_until_halt(100);
+    assert_eq!(chip8.pc, 0x204);
+}
@@ -0,0 +1,33 @@
+//! Rhai scripts changing the machine from their hooks.
+#![cfg(feature = "scripting")]
+
+mod common;
+
+use chippy_core::script::Script;
+
+/// `LD V3, FF`, then `LD V4, V3` in a loop, so the program sees what V3 holds.
+const ROM: [u8; 6] = [0x63, 0xFF, 0x84, 0x30, 0x12, 0x02];
+
+/// Keeps V3 at or below 10, as in the module docs.
+const CLAMP: &str = "
+fn on_frame() {
+    if this.v(3) > 10 {
+        this.set_v(3, 10);
+    }
+}
+";
+
+#[test]
+fn script_register_changes_reach_the_program() {
+    let mut chip8 = common::machine(&ROM);
+    chip8.set_script(Some(Script::new(CLAMP).unwrap()));
+
+    chip8.run_cycles(2).unwrap();
+    assert_eq!(chip8.registers[4], 0xFF);
+
+    chip8.tick_timers();
+    assert_eq!(chip8.registers[3], 10);
+
+    chip8.run_cycles(2).unwrap();
+    assert_eq!(chip8.registers[4], 10);
+}
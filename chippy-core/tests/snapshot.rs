@@ -0,0 +1,42 @@
+#![cfg(feature = "serde")]
+
+use chippy_core::{
+    snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION},
+    Chip8,
+};
+
+const ROM: &[u8] = include_bytes!("../../chippy-app/src/Instruction-test.ch8");
+
+#[test]
+fn json_state_restores_equal_machine() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(ROM.to_vec());
+    chip8.run_cycles(500).unwrap();
+
+    let json = serde_json::to_string_pretty(&chip8.snapshot()).unwrap();
+    let parsed: Snapshot = serde_json::from_str(&json).unwrap();
+
+    let mut restored = Chip8::new();
+    restored.load_rom(ROM.to_vec());
+    restored.restore(&parsed).unwrap();
+
+    assert_eq!(restored.snapshot(), chip8.snapshot());
+    assert_eq!(restored.cycle_count(), 500);
+}
+
+#[test]
+fn other_version_is_refused() {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(ROM.to_vec());
+
+    let mut snapshot = chip8.snapshot();
+    snapshot.version += 1;
+
+    assert_eq!(
+        chip8.restore(&snapshot),
+        Err(SnapshotError::Version {
+            found: SNAPSHOT_VERSION + 1,
+            expected: SNAPSHOT_VERSION,
+        })
+    );
+}
@@ -0,0 +1,118 @@
+//! `DRW` compared against drawing pixel by pixel, wherever the sprite lands.
+
+mod common;
+
+use chippy_core::Chip8;
+
+type Screen = [[bool; 64]; 32];
+
+/// Rows of the sprite drawn, with pixels on both ends so clipped columns show.
+const SPRITE: [u8; 4] = [0xFF, 0x81, 0xA5, 0xC3];
+
+/// Machine about to run `DRW V0, V1, 4` at `(x, y)` with `SPRITE`, then halt.
+fn machine(x: u8, y: u8) -> Chip8 {
+    // `LD I, 0206`, `DRW V0, V1, 4`, `JP 0204`, then the sprite.
+    let mut rom = vec![0xA2, 0x06, 0xD0, 0x14, 0x12, 0x04];
+    rom.extend(SPRITE);
+
+    let mut chip8 = common::machine(&rom);
+    chip8.registers[0] = x;
+    chip8.registers[1] = y;
+    chip8
+}
+
+/// Screen with a checkered pattern, so drawing both sets and erases pixels.
+fn checkered() -> Screen {
+    let mut screen = [[false; 64]; 32];
+    for (y, row) in screen.iter_mut().enumerate() {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = (x / 3 + y) % 2 == 0;
+        }
+    }
+
+    screen
+}
+
+/// `SPRITE` XORed onto `screen` one pixel at a time, returning whether any pixel was erased.
+fn reference(screen: &mut Screen, x: u8, y: u8, wrap_x: bool, wrap_y: bool) -> bool {
+    let (x, y) = (x as usize % 64, y as usize % 32);
+    let mut collision = false;
+
+    for (j, line) in SPRITE.iter().enumerate() {
+        for i in 0..8 {
+            if (x + i >= 64 && !wrap_x) || (y + j >= 32 && !wrap_y) {
+                continue;
+            }
+
+            if line & (0x80 >> i) != 0 {
+                let pixel = &mut screen[(y + j) % 32][(x + i) % 64];
+                collision |= *pixel;
+                *pixel = !*pixel;
+            }
+        }
+    }
+
+    collision
+}
+
+/// Draw at `(x, y)` on the checkered screen and compare with `reference`.
+fn assert_draws_like_reference(x: u8, y: u8, wrap_x: bool, wrap_y: bool) {
+    let mut chip8 = machine(x, y);
+    let mut quirks = chip8.quirks();
+    quirks.wrap_x = wrap_x;
+    quirks.wrap_y = wrap_y;
+    chip8.set_quirks(quirks);
+    chip8.screen = checkered();
+
+    let mut expected = checkered();
+    let collision = reference(&mut expected, x, y, wrap_x, wrap_y);
+
+    chip8.step(2).unwrap();
+
+    let at = format!("({}, {}) wrap_x {} wrap_y {}", x, y, wrap_x, wrap_y);
+    assert!(chip8.screen == expected, "screen at {}", at);
+    assert_eq!(chip8.registers[0xF], collision as u8, "VF at {}", at);
+}
+
+#[test]
+fn matches_reference() {
+    let positions = [
+        // Byte aligned.
+        (0, 0),
+        (8, 4),
+        (56, 10),
+        // Unaligned.
+        (3, 0),
+        (13, 7),
+        (55, 27),
+        // Crossing the right edge.
+        (59, 5),
+        (63, 12),
+        // Off the screen, drawn at the position modulo the screen size.
+        (70, 40),
+    ];
+
+    for (x, y) in positions {
+        assert_draws_like_reference(x, y, true, true);
+    }
+}
+
+#[test]
+fn wraps_or_clips_each_axis_at_the_corner() {
+    for wrap_x in [false, true] {
+        for wrap_y in [false, true] {
+            assert_draws_like_reference(60, 30, wrap_x, wrap_y);
+        }
+    }
+
+    // Only the top left quarter of the sprite is left when clipping both axes.
+    let mut chip8 = machine(60, 30);
+    let mut quirks = chip8.quirks();
+    quirks.wrap_x = false;
+    quirks.wrap_y = false;
+    chip8.set_quirks(quirks);
+    chip8.step(2).unwrap();
+
+    let lit = chip8.screen.iter().flatten().filter(|on| **on).count();
+    assert_eq!(lit, 4 + 1);
+}
@@ -0,0 +1,84 @@
+//! `CALL` and `RET` past the ends of the stack under each `StackPolicy`.
+
+mod common;
+
+use chippy_core::{Chip8, Chip8Error, StackPolicy, MAX_CALL_DEPTH, STACK_WARNING_DEPTH};
+
+/// `CALL 0200`, calling itself until the stack is full.
+const RECURSE: [u8; 2] = [0x22, 0x00];
+/// `RET` with nothing to return to.
+const RETURN: [u8; 2] = [0x00, 0xEE];
+
+fn machine(policy: StackPolicy, rom: [u8; 2]) -> Chip8 {
+    let mut chip8 = common::machine(&rom);
+    chip8.set_stack_policy(policy);
+    chip8
+}
+
+/// Fill the stack, then execute one more `CALL`.
+fn overflow(policy: StackPolicy) -> (Chip8, Result<(), Chip8Error>) {
+    let mut chip8 = machine(policy, RECURSE);
+    for _ in 0..MAX_CALL_DEPTH {
+        chip8.interpreter().unwrap();
+    }
+
+    let result = chip8.interpreter();
+    (chip8, result)
+}
+
+#[test]
+fn error_fails_at_boundaries() {
+    let (chip8, result) = overflow(StackPolicy::Error);
+    assert_eq!(result, Err(Chip8Error::StackOverflow(0x200)));
+    assert_eq!(chip8.sp, MAX_CALL_DEPTH);
+
+    let mut chip8 = machine(StackPolicy::Error, RETURN);
+    assert_eq!(chip8.interpreter(), Err(Chip8Error::StackUnderflow(0x200)));
+    assert_eq!(chip8.pc, 0x200);
+}
+
+#[test]
+fn saturate_skips_the_instruction() {
+    let (chip8, result) = overflow(StackPolicy::Saturate);
+    assert_eq!(result, Ok(()));
+    assert_eq!((chip8.sp, chip8.pc), (MAX_CALL_DEPTH, 0x202));
+
+    let mut chip8 = machine(StackPolicy::Saturate, RETURN);
+    assert_eq!(chip8.interpreter(), Ok(()));
+    assert_eq!((chip8.sp, chip8.pc), (0, 0x202));
+}
+
+#[test]
+fn wrap_moves_the_stack_pointer_around() {
+    let (chip8, result) = overflow(StackPolicy::Wrap);
+    assert_eq!(result, Ok(()));
+    assert_eq!((chip8.sp, chip8.pc), (1, 0x200));
+
+    let mut chip8 = machine(StackPolicy::Wrap, RETURN);
+    chip8.stack[MAX_CALL_DEPTH - 1] = 0x300;
+    assert_eq!(chip8.interpreter(), Ok(()));
+    assert_eq!((chip8.sp, chip8.pc), (MAX_CALL_DEPTH - 1, 0x302));
+}
+
+#[test]
+fn error_stops_shared_machine() {
+    let chip8 = common::executing(&RETURN);
+    chip8.write().unwrap().set_stack_policy(StackPolicy::Error);
+
+    chip8.run_frame();
+
+    assert_eq!(chip8.last_error(), Some(Chip8Error::StackUnderflow(0x200)));
+    assert!(!chip8.is_running());
+}
+
+#[test]
+fn call_depth_follows_nested_calls() {
+    let mut chip8 = machine(StackPolicy::Error, RECURSE);
+    assert_eq!(chip8.call_depth(), 0);
+
+    for depth in 1..=MAX_CALL_DEPTH {
+        chip8.interpreter().unwrap();
+        assert_eq!(chip8.call_depth(), depth);
+        assert_eq!(chip8.stack_near_limit(), depth >= STACK_WARNING_DEPTH);
+    }
+}
@@ -0,0 +1,43 @@
+#![cfg(feature = "bincode")]
+
+mod common;
+
+use chippy_core::Chip8;
+
+const ROM: &[u8] = include_bytes!("../../chippy-app/src/Instruction-test.ch8");
+
+fn machine(cycles: usize) -> Chip8 {
+    let mut chip8 = common::machine(ROM);
+    chip8.run_cycles(cycles).unwrap();
+    chip8
+}
+
+#[test]
+fn state_bytes_round_trip() {
+    let chip8 = machine(500);
+    let bytes = chip8.save_state_bytes();
+
+    let mut restored = machine(0);
+    restored.load_state_bytes(&bytes).unwrap();
+
+    assert_eq!(restored.snapshot(), chip8.snapshot());
+    assert_eq!(restored.save_state_bytes(), bytes);
+}
+
+#[test]
+fn state_bytes_size_is_stable() {
+    // Nothing in a snapshot varies in length with the state, only with the ROM.
+    assert_eq!(
+        machine(0).save_state_bytes().len(),
+        machine(500).save_state_bytes().len()
+    );
+}
+
+#[test]
+fn truncated_state_is_refused() {
+    let bytes = machine(500).save_state_bytes();
+    let mut chip8 = machine(0);
+
+    assert!(chip8.load_state_bytes(&bytes[..bytes.len() / 2]).is_err());
+    assert_eq!(chip8.snapshot(), machine(0).snapshot());
+}
@@ -0,0 +1,31 @@
+//! `SYS addr` calling the handler set with `Chip8::set_sys_handler`.
+
+mod common;
+
+/// `SYS 0345`, then `ADD V0, 01` to show execution went on.
+const ROM: [u8; 4] = [0x03, 0x45, 0x70, 0x01];
+
+#[test]
+fn handler_gets_the_address() {
+    let mut chip8 = common::machine(&ROM);
+    chip8.set_sys_handler(Some(Box::new(|addr, chip8| chip8.index = addr)));
+
+    chip8.step(2).unwrap();
+    assert_eq!(chip8.index, 0x345);
+    assert_eq!(chip8.registers[0], 1);
+
+    // Called again on later `SYS` instructions.
+    chip8.index = 0;
+    chip8.pc = 0x200;
+    chip8.step(1).unwrap();
+    assert_eq!(chip8.index, 0x345);
+}
+
+#[test]
+fn ignored_without_handler() {
+    let mut chip8 = common::machine(&ROM);
+
+    chip8.step(2).unwrap();
+    assert_eq!(chip8.index, 0);
+    assert_eq!((chip8.pc, chip8.registers[0]), (0x204, 1));
+}
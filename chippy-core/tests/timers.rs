@@ -0,0 +1,33 @@
+use std::sync::{Arc, Mutex};
+
+use chippy_core::Chip8;
+
+#[test]
+fn delay_timer_reaches_zero_after_a_second() {
+    let mut chip8 = Chip8::new();
+    chip8.delay_timer = 60;
+
+    for _ in 0..60 {
+        chip8.tick_timers();
+    }
+
+    assert_eq!(chip8.delay_timer, 0);
+}
+
+#[test]
+fn sound_handler_sees_start_and_stop() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_clone = seen.clone();
+
+    let mut chip8 = Chip8::new();
+    chip8.set_sound_handler(Some(Box::new(move |on| {
+        seen_clone.lock().unwrap().push(on)
+    })));
+    chip8.sound_timer = 2;
+
+    for _ in 0..4 {
+        chip8.tick_timers();
+    }
+
+    assert_eq!(*seen.lock().unwrap(), [true, false]);
+}
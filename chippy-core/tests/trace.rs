@@ -0,0 +1,57 @@
+//! Lines written while tracing, compared against known good output.
+
+mod common;
+
+use std::{
+    env, fs,
+    sync::{Arc, Mutex},
+};
+
+use chippy_core::trace::{state_line, TraceWriter};
+
+/// `LD V0, 05`, `LD I, 0300`, `LD B, V0`, then a `JP 0206` it stops in.
+const ROM: [u8; 8] = [0x60, 0x05, 0xA3, 0x00, 0xF0, 0x33, 0x12, 0x06];
+
+#[test]
+fn instruction_lines() {
+    let path = env::temp_dir().join(format!("chippy-trace-{}.log", std::process::id()));
+
+    let mut chip8 = common::machine(&ROM);
+    let writer = TraceWriter::new(&path, u64::MAX).unwrap();
+    chip8.set_trace_hook(Some(writer.into_hook()));
+    chip8.step(3).unwrap();
+    // Dropping the writer flushes it.
+    chip8.set_trace_hook(None);
+
+    let trace = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(trace, include_str!("golden/instructions.trace"));
+}
+
+#[test]
+fn state_lines() {
+    // `LD V0, EA`, `LD I, 0300`, `CALL 0208`, then a `JP 0206` it stops in,
+    // with `LD B, V0` and `RET` at 0208.
+    let mut chip8 = common::machine(&[
+        0x60, 0xEA, 0xA3, 0x00, 0x22, 0x08, 0x12, 0x06, 0xF0, 0x33, 0x00, 0xEE,
+    ]);
+
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let hook_lines = lines.clone();
+    chip8.set_trace_hook(Some(Box::new(move |_, chip8| {
+        hook_lines.lock().unwrap().push(state_line(chip8))
+    })));
+    chip8.step(5).unwrap();
+
+    assert_eq!(
+        *lines.lock().unwrap(),
+        [
+            "PC=0202 I=0000 SP=0 V=EA000000000000000000000000000000 S= M=",
+            "PC=0204 I=0300 SP=0 V=EA000000000000000000000000000000 S= M=",
+            "PC=0208 I=0300 SP=1 V=EA000000000000000000000000000000 S=0204 M=",
+            "PC=020A I=0300 SP=1 V=EA000000000000000000000000000000 S=0204 M=0300:02,0301:03,0302:04",
+            "PC=0206 I=0300 SP=0 V=EA000000000000000000000000000000 S= M=",
+        ]
+    );
+}
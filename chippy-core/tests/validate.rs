@@ -0,0 +1,74 @@
+//! Warnings from scanning crafted ROMs with `Chip8::validate_rom`.
+
+use chippy_core::{
+    rom::{RomWarning, Variant},
+    Chip8, MemorySize,
+};
+
+/// `LD I, 0FFC` then `DRW V0, V0, 5`, reading a sprite past the end of 4 KiB.
+const SPRITE_AT_END: [u8; 4] = [0xAF, 0xFC, 0xD0, 0x05];
+
+fn validate(rom: &[u8]) -> Vec<RomWarning> {
+    Chip8::new().validate_rom(rom)
+}
+
+#[test]
+fn clean_rom_has_no_warnings() {
+    // `LD V0, 01`, `CALL 0206`, `JP 0204`, `RET`.
+    assert_eq!(
+        validate(&[0x60, 0x01, 0x22, 0x06, 0x12, 0x04, 0x00, 0xEE]),
+        []
+    );
+}
+
+#[test]
+fn odd_length() {
+    assert_eq!(validate(&[0x12, 0x00, 0xFF]), [RomWarning::OddLength(3)]);
+}
+
+#[test]
+fn jump_outside() {
+    // `JP 0300`, then `CALL 0100`.
+    assert_eq!(
+        validate(&[0x13, 0x00, 0x21, 0x00]),
+        [
+            RomWarning::JumpOutside {
+                addr: 0x200,
+                target: 0x300
+            },
+            RomWarning::JumpOutside {
+                addr: 0x202,
+                target: 0x100
+            },
+        ]
+    );
+}
+
+#[test]
+fn extension_opcodes() {
+    // `SCR` and `HIGH` from SUPER-CHIP.
+    assert_eq!(
+        validate(&[0x00, 0xFB, 0x00, 0xFF]),
+        [RomWarning::Extension(Variant::SuperChip)]
+    );
+    // `LD I, 0200` with a long address from XO-CHIP.
+    assert_eq!(
+        validate(&[0xF0, 0x00, 0x02, 0x00]),
+        [RomWarning::Extension(Variant::XoChip)]
+    );
+}
+
+#[test]
+fn sprite_outside_depends_on_memory_size() {
+    assert_eq!(
+        validate(&SPRITE_AT_END),
+        [RomWarning::SpriteOutside {
+            addr: 0x202,
+            index: 0xFFC
+        }]
+    );
+
+    let mut chip8 = Chip8::new();
+    chip8.set_memory_size(MemorySize::Extended);
+    assert_eq!(chip8.validate_rom(&SPRITE_AT_END), []);
+}
@@ -0,0 +1,37 @@
+//! Memory writes recorded by `Chip8::set_write_log`.
+
+mod common;
+
+use chippy_core::write_log::MemoryWrite;
+use common::BACKENDS;
+
+/// `LD V0, 0A`, `LD V1, 0B`, `LD I, 0300`, `LD [I], V1`, then a `JP 0208` it stops in.
+const ROM: [u8; 10] = [0x60, 0x0A, 0x61, 0x0B, 0xA3, 0x00, 0xF1, 0x55, 0x12, 0x08];
+
+#[test]
+fn stored_registers_are_logged() {
+    for backend in BACKENDS {
+        let mut chip8 = common::machine_on(backend, &ROM);
+        chip8.memory[0x301] = 0xFF;
+        chip8.set_write_log(16);
+        chip8.run_until_halt(100);
+
+        // Both bytes are written by the fourth instruction.
+        let write = |addr, old, new| MemoryWrite {
+            addr,
+            old,
+            new,
+            cycle: 3,
+        };
+        assert_eq!(
+            chip8.recent_writes(),
+            &[write(0x300, 0x00, 0x0A), write(0x301, 0xFF, 0x0B)],
+            "{:?}",
+            backend
+        );
+
+        // Only the newest writes are kept.
+        chip8.set_write_log(1);
+        assert_eq!(chip8.recent_writes(), &[write(0x301, 0xFF, 0x0B)]);
+    }
+}